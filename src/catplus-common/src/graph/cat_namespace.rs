@@ -0,0 +1,129 @@
+// The namespace of the Cat+ ontology in the graphs of the converter.
+//
+// The terms of [super::namespaces::cat] are built under the placeholder namespace
+// `http://example.org/cat#`. When another namespace is configured (see
+// [super::graph_builder::GraphConfig]), the graphs are rewritten to it once built (see
+// [super::graph_builder::GraphBuilder::insert]) and the prefix map follows, so that the
+// serializations only show the configured namespace.
+use anyhow::{Context, Result};
+use sophia::inmem::graph::LightGraph;
+use sophia_api::{
+    graph::{Graph, MutableGraph},
+    term::{IriRef, SimpleTerm},
+    triple::Triple,
+    MownStr,
+};
+
+use super::{namespaces::cat, utils::check_namespace};
+
+/// The placeholder namespace of the terms of [super::namespaces::cat], which is also the
+/// namespace of the graphs of earlier versions of the converter.
+pub fn builtin_cat_namespace() -> &'static str {
+    cat::PREFIX.as_str()
+}
+
+/// A valid Cat+ namespace, e.g. `https://purl.org/catplus/ontology#`. Defaults to the
+/// built-in one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CatNamespace(String);
+
+impl CatNamespace {
+    pub fn new(namespace: &str) -> Result<Self> {
+        check_namespace(namespace).context("Invalid Cat+ namespace")?;
+        Ok(Self(namespace.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_builtin(&self) -> bool {
+        self.0 == builtin_cat_namespace()
+    }
+}
+
+impl Default for CatNamespace {
+    fn default() -> Self {
+        Self(builtin_cat_namespace().to_string())
+    }
+}
+
+/// Moves the terms of `graph` from the built-in namespace to `namespace`.
+pub fn to_cat_namespace(graph: &LightGraph, namespace: &CatNamespace) -> Result<LightGraph> {
+    rename_namespace(graph, builtin_cat_namespace(), namespace.as_str())
+}
+
+/// Moves the terms of `graph` from `namespace` back to the built-in one, e.g. to read them
+/// with [super::from_graph::FromGraph].
+pub fn from_cat_namespace(graph: &LightGraph, namespace: &CatNamespace) -> Result<LightGraph> {
+    rename_namespace(graph, namespace.as_str(), builtin_cat_namespace())
+}
+
+/// Copies `graph`, replacing the namespace `from` by `to` in its IRIs and datatypes.
+pub fn rename_namespace(graph: &LightGraph, from: &str, to: &str) -> Result<LightGraph> {
+    let mut renamed = LightGraph::new();
+    for t in graph.triples() {
+        let [s, p, o] = t?.spo();
+        renamed.insert(rename(s, from, to), rename(p, from, to), rename(o, from, to))?;
+    }
+    Ok(renamed)
+}
+
+fn rename<'a>(term: &SimpleTerm<'a>, from: &str, to: &str) -> SimpleTerm<'a> {
+    let renamed = |iri: &IriRef<MownStr<'a>>| -> Option<IriRef<MownStr<'a>>> {
+        let local = iri.as_str().strip_prefix(from)?;
+        Some(IriRef::new_unchecked(format!("{}{}", to, local).into()))
+    };
+    match term {
+        SimpleTerm::Iri(iri) => renamed(iri).map(SimpleTerm::Iri),
+        SimpleTerm::LiteralDatatype(lexical, datatype) => {
+            renamed(datatype).map(|datatype| SimpleTerm::LiteralDatatype(lexical.clone(), datatype))
+        }
+        _ => None,
+    }
+    .unwrap_or_else(|| term.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rdf::rdf_parser::parse_turtle_to_graph;
+    use sophia_isomorphism::isomorphic_graphs;
+
+    #[test]
+    fn test_rename_namespace() -> Result<()> {
+        let graph = parse_turtle_to_graph(
+            r#"
+            PREFIX cat: <http://example.org/cat#>
+            PREFIX schema: <https://schema.org/>
+
+            [] a cat:Batch;
+                schema:name "23";
+                cat:batchName "http://example.org/cat#Batch";
+                cat:hasBatch <http://example.org/other#node>.
+            "#,
+        )?;
+
+        let namespace = CatNamespace::new("https://purl.org/catplus#")?;
+        let renamed = to_cat_namespace(&graph, &namespace)?;
+        let restored = from_cat_namespace(&renamed, &namespace)?;
+
+        let expected = parse_turtle_to_graph(
+            r#"
+            PREFIX cat: <https://purl.org/catplus#>
+            PREFIX schema: <https://schema.org/>
+
+            [] a cat:Batch;
+                schema:name "23";
+                cat:batchName "http://example.org/cat#Batch";
+                cat:hasBatch <http://example.org/other#node>.
+            "#,
+        )?;
+        assert!(isomorphic_graphs(&renamed, &expected)?);
+        assert!(isomorphic_graphs(&restored, &graph)?);
+
+        assert!(CatNamespace::new("not an iri").is_err());
+        assert!(CatNamespace::new("https://purl.org/catplus").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,221 @@
+// Deduplication of the chemicals of a graph.
+//
+// The same compound is usually listed by every sample that contains it and by the
+// campaign. The registry of a [GraphBuilder] remembers the node of every chemical inserted
+// into its graph, so that a chemical with a known identity key is linked to the existing
+// node instead of being inserted again. Chemicals sharing a key but differing in another
+// property are not the same compound: they keep nodes of their own, and are reported as
+// conflicts since their key is ambiguous.
+use crate::{
+    graph::{graph_builder::GraphBuilder, insert_into::InsertIntoGraph},
+    models::types::{Chemical, Observation},
+};
+use anyhow::Result;
+use sophia_api::term::SimpleTerm;
+use std::{collections::HashMap, fmt};
+
+/// The property identifying a [Chemical].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChemicalKey {
+    /// The `chemicalID` of the input.
+    #[default]
+    ChemicalId,
+    /// The InChI of the compound.
+    Inchi,
+    /// The CAS registry number; chemicals without one are not deduplicated.
+    Cas,
+}
+
+impl ChemicalKey {
+    /// The value of the key for `chemical`, if any.
+    pub fn of<'a>(&self, chemical: &'a Chemical) -> Option<&'a str> {
+        let value = match self {
+            ChemicalKey::ChemicalId => Some(&chemical.chemical_id),
+            ChemicalKey::Inchi => Some(&chemical.inchi),
+            ChemicalKey::Cas => chemical.cas_number.as_ref(),
+        };
+        value.map(|v| v.trim()).filter(|v| !v.is_empty())
+    }
+}
+
+/// Two chemicals with the same key but different values for `property`, inserted as
+/// separate nodes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChemicalConflict {
+    pub key: String,
+    pub property: &'static str,
+    /// The value of the first inserted chemical with the key.
+    pub first: String,
+    pub other: String,
+}
+
+impl fmt::Display for ChemicalConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "chemical '{}': {} is '{}' and '{}', keeping separate nodes",
+            self.key, self.property, self.first, self.other
+        )
+    }
+}
+
+/// The chemicals inserted into a graph, by key: the distinct chemicals sharing a key, in
+/// insertion order.
+#[derive(Default)]
+pub(crate) struct ChemicalRegistry {
+    nodes: HashMap<String, Vec<(SimpleTerm<'static>, Chemical)>>,
+    conflicts: Vec<ChemicalConflict>,
+}
+
+impl ChemicalRegistry {
+    /// The conflicts found so far.
+    pub(crate) fn conflicts(&self) -> &[ChemicalConflict] {
+        &self.conflicts
+    }
+}
+
+/// Returns the node of the chemical identical to `chemical` and `true`, or registers
+/// `chemical` under a new node of `builder` and returns it with `false`. A chemical whose
+/// key is known but whose properties differ is named after all of its properties.
+pub(crate) fn find_or_register(
+    builder: &mut GraphBuilder,
+    chemical: &Chemical,
+) -> Result<(SimpleTerm<'static>, bool)> {
+    let Some(key) = builder.config().chemical_key.of(chemical).map(str::to_string) else {
+        return Ok((chemical.get_uri(builder)?, false));
+    };
+    let known = builder.chemicals.nodes.get(&key).cloned().unwrap_or_default();
+    if let Some((iri, _)) =
+        known.iter().find(|(_, known)| conflicts(&key, known, chemical).is_empty())
+    {
+        return Ok((iri.clone(), true));
+    }
+    let iri = match known.first() {
+        None => chemical.get_uri(builder)?,
+        Some((_, first)) => {
+            builder.chemicals.conflicts.extend(conflicts(&key, first, chemical));
+            let properties = properties(chemical);
+            let fields: Vec<&str> = std::iter::once(key.as_str())
+                .chain(properties.iter().map(|(_, value)| value.as_str()))
+                .collect();
+            builder.generate_iri_term("chemical", &fields)?
+        }
+    };
+    builder.chemicals.nodes.entry(key).or_default().push((iri.clone(), chemical.clone()));
+    Ok((iri, false))
+}
+
+fn conflicts(key: &str, first: &Chemical, other: &Chemical) -> Vec<ChemicalConflict> {
+    properties(first)
+        .into_iter()
+        .zip(properties(other))
+        .filter(|((_, first), (_, other))| first != other)
+        .map(|((property, first), (_, other))| ChemicalConflict {
+            key: key.to_string(),
+            property,
+            first,
+            other,
+        })
+        .collect()
+}
+
+/// The properties of a chemical inserted into the graph, as comparable strings.
+fn properties(chemical: &Chemical) -> [(&'static str, String); 10] {
+    let optional = |value: &Option<String>| value.clone().unwrap_or_default();
+    let observation = |value: Option<&Observation>| value.map(Observation::key).unwrap_or_default();
+    [
+        ("chemicalID", chemical.chemical_id.clone()),
+        ("chemicalName", chemical.chemical_name.clone()),
+        ("CASNumber", optional(&chemical.cas_number)),
+        ("molecularMass", observation(Some(&chemical.molecular_mass))),
+        ("smiles", chemical.smiles.clone()),
+        ("swissCatNumber", optional(&chemical.swiss_cat_number)),
+        ("Inchi", chemical.inchi.clone()),
+        ("keywords", optional(&chemical.keywords)),
+        ("molecularFormula", chemical.molecular_formula.clone()),
+        ("density", observation(chemical.density.as_ref())),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::{
+        graph_builder::GraphConfig, insert_into::Link, namespaces::cat, utils::IriStrategy,
+    };
+    use sophia_api::{graph::Graph, prelude::Any, term::Term, triple::Triple};
+    use std::collections::HashSet;
+
+    struct Chemicals(Vec<Chemical>);
+
+    impl InsertIntoGraph for Chemicals {
+        fn insert_into(&self, builder: &mut GraphBuilder, iri: SimpleTerm) -> anyhow::Result<()> {
+            self.0.attach_into(
+                builder,
+                Link {
+                    source_iri: iri,
+                    pred: cat::hasChemical.as_simple(),
+                    target_iri: None,
+                    position: None,
+                },
+            )
+        }
+    }
+
+    fn chemical(chemical_id: &str, name: &str) -> Chemical {
+        serde_json::from_value(serde_json::json!({
+            "chemicalID": chemical_id,
+            "chemicalName": name,
+            "CASNumber": "100-42-5",
+            "molecularMass": {"value": 104.15, "unit": "g/mol"},
+            "smiles": "C=CC1=CC=CC=C1",
+            "Inchi": "1S/C8H8/c1-2-8-6-4-3-5-7-8/h2-7H,1H2",
+            "molecularFormula": "C8H8",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_identical_chemicals_share_a_node() -> anyhow::Result<()> {
+        let chemicals = Chemicals(vec![
+            chemical("135", "Styrene"),
+            chemical("135", "Styrene"),
+            chemical("135", "Vinylbenzene"),
+            chemical("135", "Vinylbenzene"),
+            chemical("136", "Styrene"),
+        ]);
+        let nodes = |b: &GraphBuilder| {
+            let nodes: HashSet<_> = b
+                .graph
+                .triples_matching(Any, [cat::hasChemical], Any)
+                .map(|t| t.unwrap().o().clone())
+                .collect();
+            nodes.len()
+        };
+
+        let mut b = GraphBuilder::new();
+        b.insert(&chemicals)?;
+        assert_eq!(nodes(&b), 3);
+        assert_eq!(
+            b.chemical_conflicts(),
+            [ChemicalConflict {
+                key: "135".to_string(),
+                property: "chemicalName",
+                first: "Styrene".to_string(),
+                other: "Vinylbenzene".to_string(),
+            }]
+        );
+
+        let config = GraphConfig {
+            iri_strategy: IriStrategy::content_derived("http://example.org/")?,
+            chemical_key: ChemicalKey::Inchi,
+            ..Default::default()
+        };
+        let mut b = GraphBuilder::with_config(config);
+        b.insert(&chemicals)?;
+        assert_eq!(nodes(&b), 3);
+        assert_eq!(b.chemical_conflicts().len(), 2);
+
+        Ok(())
+    }
+}
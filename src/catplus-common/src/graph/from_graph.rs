@@ -0,0 +1,171 @@
+use anyhow::{bail, Context, Result};
+use sophia::{api::ns::rdf, inmem::graph::LightGraph};
+use sophia_api::{
+    graph::Graph,
+    prelude::Any,
+    term::{SimpleTerm, Term},
+    triple::Triple,
+};
+
+use crate::{graph::namespaces::schema, shacl::shapes::display_term};
+use std::cmp::Ordering;
+
+/// FromGraph provides a trait to read a type back from a graph, the inverse of
+/// [crate::graph::insert_into::InsertIntoGraph].
+///
+/// RDF does not order the values of a property, so that lists are read back in the order
+/// of the `schema:position` of their items, see [Node::read_list].
+pub trait FromGraph: Sized {
+    /// Reads `Self` from the node `term` of `graph`, which is the literal itself for
+    /// plain values.
+    fn from_graph(graph: &LightGraph, term: &SimpleTerm) -> Result<Self>;
+
+    /// Finds the node of `graph` a whole document of this type is read from, e.g. the
+    /// batch of a Synth graph.
+    fn find_root(_graph: &LightGraph) -> Result<SimpleTerm<'static>> {
+        bail!("{} cannot be read as a document", std::any::type_name::<Self>())
+    }
+}
+
+impl FromGraph for String {
+    fn from_graph(_graph: &LightGraph, term: &SimpleTerm) -> Result<Self> {
+        match term.lexical_form() {
+            Some(lexical) => Ok(lexical.to_string()),
+            None => bail!("Expected a literal, found {}", display_term(&term.into_term())),
+        }
+    }
+}
+
+impl FromGraph for f64 {
+    fn from_graph(graph: &LightGraph, term: &SimpleTerm) -> Result<Self> {
+        let lexical = String::from_graph(graph, term)?;
+        lexical.parse().with_context(|| format!("Expected a number, found {:?}", lexical))
+    }
+}
+
+/// The only node of `graph` with type `class`, e.g. to find the root of a document.
+pub fn find_instance<T: Term>(graph: &LightGraph, class: T) -> Result<SimpleTerm<'static>> {
+    let class: SimpleTerm<'static> = class.into_term();
+    let mut instances = graph
+        .triples_matching(Any, [rdf::type_], [&class])
+        .filter_map(Result::ok)
+        .map(|t| t.s().into_term::<SimpleTerm<'static>>());
+    match (instances.next(), instances.next()) {
+        (Some(instance), None) => Ok(instance),
+        (None, _) => bail!("The graph has no node of type {}", display_term(&class)),
+        (Some(_), Some(_)) => {
+            bail!("The graph has several nodes of type {}", display_term(&class))
+        }
+    }
+}
+
+/// A node of a graph, whose properties are read with [FromGraph].
+pub struct Node<'a> {
+    graph: &'a LightGraph,
+    iri: SimpleTerm<'static>,
+}
+
+impl<'a> Node<'a> {
+    pub fn new(graph: &'a LightGraph, iri: &SimpleTerm) -> Self {
+        Self { graph, iri: iri.into_term() }
+    }
+
+    /// Whether the node has a value for `pred`.
+    pub fn has<P: Term>(&self, pred: P) -> bool {
+        self.graph.triples_matching([&self.iri], [pred], Any).next().is_some()
+    }
+
+    /// The objects of the triples of the node with predicate `pred`.
+    pub fn objects<P: Term>(&self, pred: P) -> Vec<SimpleTerm<'static>> {
+        self.graph
+            .triples_matching([&self.iri], [pred], Any)
+            .filter_map(Result::ok)
+            .map(|t| t.o().into_term())
+            .collect()
+    }
+
+    /// The subjects of the triples with predicate `pred` and the node as object, e.g. the
+    /// actions of a batch.
+    pub fn subjects<P: Term>(&self, pred: P) -> Vec<SimpleTerm<'static>> {
+        self.graph
+            .triples_matching(Any, [pred], [&self.iri])
+            .filter_map(Result::ok)
+            .map(|t| t.s().into_term())
+            .collect()
+    }
+
+    /// Reads the value of `pred`, failing if there is none or several.
+    pub fn read<T: FromGraph, P: Term + Copy>(&self, pred: P) -> Result<T> {
+        match self.read_optional(pred)? {
+            Some(value) => Ok(value),
+            None => bail!("{} has no value for {}", self, display_term(&pred.into_term())),
+        }
+    }
+
+    /// Reads the value of `pred` if any, failing if there are several.
+    pub fn read_optional<T: FromGraph, P: Term + Copy>(&self, pred: P) -> Result<Option<T>> {
+        let objects = self.objects(pred);
+        if objects.len() > 1 {
+            bail!("{} has several values for {}", self, display_term(&pred.into_term()));
+        }
+        objects.first().map(|o| self.read_object(pred, o)).transpose()
+    }
+
+    /// Reads all the values of `pred`.
+    pub fn read_all<T: FromGraph, P: Term + Copy>(&self, pred: P) -> Result<Vec<T>> {
+        self.objects(pred).iter().map(|o| self.read_object(pred, o)).collect()
+    }
+
+    /// Reads all the values of `pred` in the order of their `schema:position`, see
+    /// [crate::graph::insert_into::Link::position]. The values without a position, e.g. of
+    /// a list of one item or of a graph written by another tool, are sorted with `compare`.
+    pub fn read_list<T: FromGraph, P: Term + Copy>(
+        &self,
+        pred: P,
+        compare: impl FnMut(&T, &T) -> Ordering,
+    ) -> Result<Vec<T>> {
+        let items = self
+            .objects(pred)
+            .into_iter()
+            .map(|o| Ok((self.read_object(pred, &o)?, o)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(sort_list(self.graph, items, compare))
+    }
+
+    fn read_object<T: FromGraph, P: Term>(&self, pred: P, object: &SimpleTerm) -> Result<T> {
+        T::from_graph(self.graph, object).with_context(|| {
+            format!("Failed to read {} of {}", display_term(&pred.into_term()), self)
+        })
+    }
+}
+
+impl std::fmt::Display for Node<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", display_term(&self.iri))
+    }
+}
+
+/// Sorts the `items` read from nodes of `graph` by the `schema:position` of their node,
+/// followed by the items without a position sorted with `compare`. A node shared by
+/// identical items has the position of the first one.
+pub fn sort_list<T>(
+    graph: &LightGraph,
+    mut items: Vec<(T, SimpleTerm<'static>)>,
+    mut compare: impl FnMut(&T, &T) -> Ordering,
+) -> Vec<T> {
+    items.sort_by(|(a, _), (b, _)| compare(a, b));
+    items.sort_by_key(|(_, node)| {
+        Node::new(graph, node)
+            .objects(schema::position)
+            .iter()
+            .filter_map(|p| p.lexical_form()?.parse::<usize>().ok())
+            .min()
+            .unwrap_or(usize::MAX)
+    });
+    items.into_iter().map(|(item, _)| item).collect()
+}
+
+/// `None` for an empty list, as absent lists of the input are not inserted into the graph.
+pub fn non_empty<T>(items: Vec<T>) -> Option<Vec<T>> {
+    (!items.is_empty()).then_some(items)
+}
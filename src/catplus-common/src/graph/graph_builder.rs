@@ -1,12 +1,59 @@
-use crate::rdf::rdf_serializers::{serialize_graph_to_jsonld, serialize_graph_to_turtle};
+use crate::{
+    rdf::{
+        canonical::canonicalize_graph,
+        jsonld_compaction::CompactionOptions,
+        rdf_serializers::{
+            serialize_graph_to_compact_jsonld, serialize_graph_to_jsonld,
+            serialize_graph_to_nquads, serialize_graph_to_ntriples, serialize_graph_to_rdfxml,
+            serialize_graph_to_trig, serialize_graph_to_turtle,
+        },
+    },
+    shacl::{report::ValidationReport, shapes::Shapes},
+};
 use anyhow::{Context, Result};
 use sophia::inmem::graph::LightGraph;
+use sophia_api::{
+    graph::{Graph, MutableGraph},
+    term::SimpleTerm,
+};
 
-use super::insert_into::InsertIntoGraph;
+use super::{
+    cat_namespace::{to_cat_namespace, CatNamespace},
+    chemical_registry::{ChemicalConflict, ChemicalKey, ChemicalRegistry},
+    insert_into::InsertIntoGraph,
+    mapping::{FieldMapping, Mapping, MappingUsage},
+    prefix_map::{generate_prefix_map_for, PrefixMap},
+    utils::{bnode_term, content_derived_iri, generate_bnode_term, IriStrategy},
+};
+
+/// How the nodes of a graph are built.
+#[derive(Clone, Debug, Default)]
+pub struct GraphConfig {
+    /// How the nodes are named.
+    pub iri_strategy: IriStrategy,
+    /// The property identifying a chemical, see [super::chemical_registry].
+    pub chemical_key: ChemicalKey,
+    /// The overrides of the built-in mapping, see [super::mapping].
+    pub mapping: Mapping,
+    /// The namespace of the Cat+ terms, see [super::cat_namespace].
+    pub cat_namespace: CatNamespace,
+}
 
 /// An RDF Graph
 pub struct GraphBuilder {
     pub graph: LightGraph,
+    config: GraphConfig,
+    /// The number of blank nodes labelled in insertion order, see [IriStrategy].
+    bnode_count: u64,
+    /// The chemicals inserted so far.
+    pub(crate) chemicals: ChemicalRegistry,
+    mapping_usage: MappingUsage,
+}
+
+impl Default for GraphBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 /// Builds an RDF graph of Synthesis data for the cat+ ontology.
@@ -17,17 +64,105 @@ pub struct GraphBuilder {
 /// * insert:  starts the process of building the graph from the input structure
 /// * serialize_to_turtle: serializes the graph to a turtle output
 impl GraphBuilder {
+    /// Creates an empty graph with the default [GraphConfig].
     pub fn new() -> Self {
-        Self { graph: LightGraph::new() }
+        Self::with_config(GraphConfig::default())
+    }
+
+    /// Creates an empty graph whose nodes are built as set by `config`.
+    pub fn with_config(config: GraphConfig) -> Self {
+        Self {
+            graph: LightGraph::new(),
+            config,
+            bnode_count: 0,
+            chemicals: ChemicalRegistry::default(),
+            mapping_usage: MappingUsage::default(),
+        }
+    }
+
+    /// How the nodes of the graph are built.
+    pub fn config(&self) -> &GraphConfig {
+        &self.config
     }
 
     /// Inserts a new object into the graph as a collection of triples.
+    ///
+    /// Identical chemicals are inserted once, see [super::chemical_registry], and the
+    /// mapping overrides of [super::mapping] apply. The triples are inserted in the
+    /// configured Cat+ namespace, see [super::cat_namespace].
     pub fn insert(&mut self, other: &dyn InsertIntoGraph) -> Result<()> {
-        other.insert_into(&mut self.graph, other.get_uri())?;
+        if self.config.cat_namespace.is_builtin() {
+            let iri = other.get_uri(self)?;
+            return other.insert_into(self, iri);
+        }
+        let graph = std::mem::replace(&mut self.graph, LightGraph::new());
+        let inserted = other.get_uri(self).and_then(|iri| other.insert_into(self, iri));
+        let graph = std::mem::replace(&mut self.graph, graph);
+        inserted?;
+        self.graph.insert_all(to_cat_namespace(&graph, &self.config.cat_namespace)?.triples())?;
+        Ok(())
+    }
+
+    /// A new blank node: randomly labelled by [generate_bnode_term], or labelled in
+    /// insertion order with [IriStrategy::ContentDerived].
+    pub fn generate_bnode_term(&mut self) -> SimpleTerm<'static> {
+        match self.config.iri_strategy {
+            IriStrategy::Random => generate_bnode_term(),
+            IriStrategy::ContentDerived { .. } => {
+                self.bnode_count += 1;
+                bnode_term(format!("b{}", self.bnode_count - 1))
+            }
+        }
+    }
+
+    /// Names a node of type `kind` (e.g. `batch`) identified by `key` according to the
+    /// [IriStrategy] of the graph.
+    pub fn generate_iri_term(&mut self, kind: &str, key: &[&str]) -> Result<SimpleTerm<'static>> {
+        match &self.config.iri_strategy {
+            IriStrategy::Random => Ok(self.generate_bnode_term()),
+            IriStrategy::ContentDerived { base } => content_derived_iri(base, kind, key),
+        }
+    }
 
+    /// The chemicals sharing an identity key but not their other properties, found
+    /// while inserting into the graph.
+    pub fn chemical_conflicts(&self) -> &[ChemicalConflict] {
+        self.chemicals.conflicts()
+    }
+
+    /// The fields of the mapping overrides (as `Model.field`) which match no field of the
+    /// inserted models, most likely misspelled.
+    pub fn unknown_mapped_fields(&self) -> Vec<String> {
+        self.mapping_usage.unknown_fields(&self.config.mapping)
+    }
+
+    /// The class of the nodes of `model`, if overridden by the mapping.
+    pub(crate) fn mapped_class(&self, model: &str) -> Option<SimpleTerm<'static>> {
+        self.config.mapping.model(model)?.class.clone()
+    }
+
+    /// The overrides of `field` of `model` by the mapping, if any.
+    pub(crate) fn mapped_field(&mut self, model: &str, field: &str) -> Option<FieldMapping> {
+        self.mapping_usage.field(&self.config.mapping, model, field)
+    }
+
+    /// The prefix map of the serializations, with the configured Cat+ namespace.
+    pub fn prefix_map(&self) -> PrefixMap {
+        generate_prefix_map_for(&self.config.cat_namespace)
+    }
+
+    /// Relabels the blank nodes of the graph canonically and sorts its triples, see
+    /// [crate::rdf::canonical], so that the serializations of equal graphs are identical.
+    pub fn canonicalize(&mut self) -> Result<()> {
+        self.graph = canonicalize_graph(&self.graph)?;
         Ok(())
     }
 
+    /// Validates the RDF graph against SHACL `shapes`.
+    pub fn validate(&self, shapes: &Shapes) -> ValidationReport {
+        shapes.validate(&self.graph)
+    }
+
     /// Get the turtle serialization of the RDF graph
     ///
     /// Assumes a new graph has been created and built.
@@ -36,7 +171,8 @@ impl GraphBuilder {
     /// A `Result` containing the graph as Turtle serialization, or an error
     /// if the graph retrieval fails.
     pub fn serialize_to_turtle(&self) -> Result<String> {
-        serialize_graph_to_turtle(&self.graph).context("Failed to serialize graph to Turtle")
+        serialize_graph_to_turtle(&self.graph, &self.prefix_map())
+            .context("Failed to serialize graph to Turtle")
     }
 
     /// Get the turtle serialization of the RDF graph
@@ -49,4 +185,77 @@ impl GraphBuilder {
     pub fn serialize_to_jsonld(&self) -> Result<String> {
         serialize_graph_to_jsonld(&self.graph).context("Failed to serialize graph to JSON-LD")
     }
+
+    /// Get the N-Triples serialization of the RDF graph
+    ///
+    /// Assumes a new graph has been created and built.
+    ///
+    /// # Returns
+    /// The line-oriented `nt` serialization of the graph, or an error if the
+    /// serialization fails.
+    pub fn serialize_to_ntriples(&self) -> Result<String> {
+        serialize_graph_to_ntriples(&self.graph).context("Failed to serialize graph to N-Triples")
+    }
+
+    /// Get the N-Quads serialization of the RDF graph, as the default graph
+    ///
+    /// Assumes a new graph has been created and built.
+    ///
+    /// # Returns
+    /// The line-oriented `nq` serialization of the graph, or an error if the
+    /// serialization fails.
+    pub fn serialize_to_nquads(&self) -> Result<String> {
+        serialize_graph_to_nquads(&self.graph).context("Failed to serialize graph to N-Quads")
+    }
+
+    /// Get the TriG serialization of the RDF graph, as the default graph
+    ///
+    /// Assumes a new graph has been created and built.
+    ///
+    /// # Returns
+    /// The `trig` serialization of the graph, or an error if the serialization fails.
+    pub fn serialize_to_trig(&self) -> Result<String> {
+        serialize_graph_to_trig(&self.graph, &self.prefix_map())
+            .context("Failed to serialize graph to TriG")
+    }
+
+    /// Get the RDF/XML serialization of the RDF graph
+    ///
+    /// Assumes a new graph has been created and built.
+    ///
+    /// # Returns
+    /// The `rdf` serialization of the graph, or an error if the serialization fails.
+    pub fn serialize_to_rdfxml(&self) -> Result<String> {
+        serialize_graph_to_rdfxml(&self.graph, &self.prefix_map())
+            .context("Failed to serialize graph to RDF/XML")
+    }
+
+    /// Get the compacted JSON-LD document of the RDF graph
+    ///
+    /// Assumes a new graph has been created and built.
+    ///
+    /// # Returns
+    /// The compacted `jsonld` document as JSON, e.g. to embed it into Zarr attributes,
+    /// or an error if the serialization fails.
+    pub fn to_compact_jsonld(&self) -> Result<serde_json::Value> {
+        serialize_graph_to_compact_jsonld(
+            &self.graph,
+            &self.prefix_map(),
+            &CompactionOptions::default(),
+        )
+        .context("Failed to serialize graph to compacted JSON-LD")
+    }
+
+    /// Get the compacted JSON-LD serialization of the RDF graph
+    ///
+    /// Assumes a new graph has been created and built.
+    ///
+    /// # Returns
+    /// The compacted (and framed, if requested by `options`) `jsonld` serialization of
+    /// the graph, or an error if the serialization fails.
+    pub fn serialize_to_compact_jsonld(&self, options: &CompactionOptions) -> Result<String> {
+        let document = serialize_graph_to_compact_jsonld(&self.graph, &self.prefix_map(), options)
+            .context("Failed to serialize graph to compacted JSON-LD")?;
+        serde_json::to_string_pretty(&document).context("Failed to write compacted JSON-LD")
+    }
 }
@@ -4,7 +4,10 @@ use sophia_api::{
     term::{SimpleTerm, Term},
 };
 
-use crate::graph::utils::generate_bnode_term;
+use crate::graph::{graph_builder::GraphBuilder, namespaces::schema};
+
+/// Derives [InsertIntoGraph] from `rdf` attributes, see [catplus_derive].
+pub use catplus_derive::InsertIntoGraph;
 
 /// Used in [InsertIntoGraph::attach_and_insert].
 #[derive(Clone)]
@@ -12,43 +15,103 @@ pub struct Link<'a, 'b, 'c> {
     pub source_iri: SimpleTerm<'a>,
     pub pred: SimpleTerm<'b>,
     pub target_iri: Option<SimpleTerm<'c>>,
+    /// The position of the attached node in its list, from 1, written as its
+    /// `schema:position` as RDF does not order the values of a property. Only set for the
+    /// `ordered` fields of `#[derive(InsertIntoGraph)]` with several items.
+    pub position: Option<usize>,
+}
+
+impl Link<'_, '_, '_> {
+    /// Writes the position of the link, if any, as the `schema:position` of `iri`.
+    pub fn insert_position(
+        &self,
+        builder: &mut GraphBuilder,
+        iri: &SimpleTerm,
+    ) -> anyhow::Result<()> {
+        if let Some(position) = self.position {
+            builder.graph.insert(iri, schema::position, position.as_simple())?;
+        }
+        Ok(())
+    }
 }
 
 /// InsertIntoGraph provides a trait to implement the conversion into a graph
 /// by different types.
 pub trait InsertIntoGraph {
-    /// Inserts `&self` into `graph` with subject IRI `iri`
-    fn insert_into(&self, graph: &mut LightGraph, iri: SimpleTerm) -> anyhow::Result<()>;
+    /// Inserts `&self` into the graph of `builder` with subject IRI `iri`
+    fn insert_into(&self, builder: &mut GraphBuilder, iri: SimpleTerm) -> anyhow::Result<()>;
 
-    /// Inserts `&self` into `graph` with subject IRI `iri` (default to a blank node)
-    /// and "attach" self to an existing node with an additional triple.
-    fn attach_into(&self, graph: &mut LightGraph, attach: Link) -> anyhow::Result<()> {
-        let iri = attach.target_iri.unwrap_or_else(|| self.get_uri());
-        _ = graph.insert(&attach.source_iri, &attach.pred, &iri);
+    /// Inserts `&self` into the graph of `builder` with subject IRI `iri` (default to a
+    /// blank node) and "attach" self to an existing node with an additional triple.
+    fn attach_into(&self, builder: &mut GraphBuilder, attach: Link) -> anyhow::Result<()> {
+        let iri = match &attach.target_iri {
+            Some(iri) => iri.clone(),
+            None => self.get_uri(builder)?,
+        };
+        _ = builder.graph.insert(&attach.source_iri, &attach.pred, &iri);
+        attach.insert_position(builder, &iri)?;
 
-        self.insert_into(graph, iri)
+        self.insert_into(builder, iri)
     }
 
-    fn get_uri(&self) -> SimpleTerm<'static> {
-        generate_bnode_term()
+    /// Inserts `&self` into the graph of `builder` with subject IRI `iri` (default to a
+    /// blank node) and attach the existing node to self with an additional triple, whose
+    /// subject is self (e.g. an action and its batch).
+    fn attach_reverse_into(&self, builder: &mut GraphBuilder, attach: Link) -> anyhow::Result<()> {
+        let iri = match &attach.target_iri {
+            Some(iri) => iri.clone(),
+            None => self.get_uri(builder)?,
+        };
+        builder.graph.insert(&iri, &attach.pred, &attach.source_iri)?;
+        attach.insert_position(builder, &iri)?;
+
+        self.insert_into(builder, iri)
+    }
+
+    /// The node of `&self`, named by `builder`.
+    fn get_uri(&self, builder: &mut GraphBuilder) -> anyhow::Result<SimpleTerm<'static>> {
+        Ok(builder.generate_bnode_term())
     }
 }
 
+/// Inserts `value` into `graph` with subject IRI `iri`, with the default [GraphBuilder]
+/// settings: the former signature of [InsertIntoGraph::insert_into], for the callers that
+/// build a [LightGraph] themselves.
+#[deprecated(note = "insert into a `GraphBuilder`, e.g. with `GraphBuilder::insert`")]
+pub fn insert_into_graph(
+    value: &dyn InsertIntoGraph,
+    graph: &mut LightGraph,
+    iri: SimpleTerm,
+) -> anyhow::Result<()> {
+    let mut builder = GraphBuilder::new();
+    std::mem::swap(&mut builder.graph, graph);
+    let inserted = value.insert_into(&mut builder, iri);
+    std::mem::swap(&mut builder.graph, graph);
+    inserted
+}
+
 /// Default implementation for [Option<T>].
 impl<T> InsertIntoGraph for Option<T>
 where
     T: InsertIntoGraph,
 {
-    fn insert_into(&self, archive: &mut LightGraph, iri: SimpleTerm) -> anyhow::Result<()> {
+    fn insert_into(&self, builder: &mut GraphBuilder, iri: SimpleTerm) -> anyhow::Result<()> {
         if let Some(v) = self {
-            v.insert_into(archive, iri)?
+            v.insert_into(builder, iri)?
         }
         Ok(())
     }
 
-    fn attach_into(&self, graph: &mut LightGraph, attach: Link) -> anyhow::Result<()> {
+    fn attach_into(&self, builder: &mut GraphBuilder, attach: Link) -> anyhow::Result<()> {
         if let Some(v) = self {
-            v.attach_into(graph, attach)?
+            v.attach_into(builder, attach)?
+        }
+        Ok(())
+    }
+
+    fn attach_reverse_into(&self, builder: &mut GraphBuilder, attach: Link) -> anyhow::Result<()> {
+        if let Some(v) = self {
+            v.attach_reverse_into(builder, attach)?
         }
         Ok(())
     }
@@ -58,16 +121,23 @@ impl<T> InsertIntoGraph for Vec<T>
 where
     T: InsertIntoGraph,
 {
-    fn insert_into(&self, graph: &mut LightGraph, iri: SimpleTerm) -> anyhow::Result<()> {
+    fn insert_into(&self, builder: &mut GraphBuilder, iri: SimpleTerm) -> anyhow::Result<()> {
+        for item in self {
+            item.insert_into(builder, iri.clone())?;
+        }
+        Ok(())
+    }
+
+    fn attach_into(&self, builder: &mut GraphBuilder, attach: Link) -> anyhow::Result<()> {
         for item in self {
-            item.insert_into(graph, iri.clone())?;
+            item.attach_into(builder, attach.clone())?;
         }
         Ok(())
     }
 
-    fn attach_into(&self, graph: &mut LightGraph, attach: Link) -> anyhow::Result<()> {
+    fn attach_reverse_into(&self, builder: &mut GraphBuilder, attach: Link) -> anyhow::Result<()> {
         for item in self {
-            item.attach_into(graph, attach.clone())?;
+            item.attach_reverse_into(builder, attach.clone())?;
         }
         Ok(())
     }
@@ -75,15 +145,319 @@ where
 
 /// Default implementation for [SimpleTerm].
 impl<'a> InsertIntoGraph for SimpleTerm<'a> {
-    fn insert_into(&self, _graph: &mut LightGraph, _iri: SimpleTerm) -> anyhow::Result<()> {
+    fn insert_into(&self, _builder: &mut GraphBuilder, _iri: SimpleTerm) -> anyhow::Result<()> {
         unimplemented!("cannot insert {:?} into graph, use `attach_and_insert`", &self)
     }
 
-    fn attach_into(&self, graph: &mut LightGraph, attach: Link) -> anyhow::Result<()> {
+    fn attach_into(&self, builder: &mut GraphBuilder, attach: Link) -> anyhow::Result<()> {
         assert!(!self.is_triple());
 
-        _ = graph.insert(&attach.source_iri, &attach.pred, self);
+        _ = builder.graph.insert(&attach.source_iri, &attach.pred, self);
+
+        Ok(())
+    }
+}
+
+/// Implementation for literal values, attached as objects of the link.
+macro_rules! impl_insert_literal {
+    ($($t:ty),*) => {$(
+        impl InsertIntoGraph for $t {
+            fn insert_into(
+                &self,
+                _builder: &mut GraphBuilder,
+                _iri: SimpleTerm,
+            ) -> anyhow::Result<()> {
+                unimplemented!("cannot insert literal {:?} into graph, use `attach_into`", &self)
+            }
+
+            fn attach_into(&self, builder: &mut GraphBuilder, attach: Link) -> anyhow::Result<()> {
+                self.as_simple().attach_into(builder, attach)
+            }
+        }
+    )*};
+}
+
+impl_insert_literal!(String, f64, i32, usize);
+
+/// Support of the code generated by `#[derive(InsertIntoGraph)]`.
+#[doc(hidden)]
+pub mod derive {
+    use super::{InsertIntoGraph, Link};
+    pub use crate::graph::graph_builder::GraphBuilder;
+    use anyhow::bail;
+    use sophia::{api::ns::rdf, inmem::graph::LightGraph};
+    pub use sophia_api::term::SimpleTerm;
+    use sophia_api::{
+        graph::{Graph, MutableGraph},
+        ns::NsTerm,
+        term::Term,
+        triple::Triple,
+    };
+
+    pub type Result<T> = anyhow::Result<T>;
+
+    pub fn as_simple<T: Term>(term: &T) -> SimpleTerm<'_> {
+        term.as_simple()
+    }
+
+    pub fn typed_literal(value: &str, datatype: NsTerm) -> SimpleTerm<'static> {
+        (value * datatype).into_term()
+    }
+
+    /// Attaches the class of `model`, as overridden by the mapping, to `iri`.
+    pub fn attach_class(
+        builder: &mut GraphBuilder,
+        iri: &SimpleTerm,
+        model: &str,
+        class: Option<SimpleTerm>,
+    ) -> Result<()> {
+        match builder.mapped_class(model).as_ref().or(class.as_ref()) {
+            Some(class) => class.attach_into(
+                builder,
+                Link {
+                    source_iri: iri.clone(),
+                    pred: rdf::type_.into_term(),
+                    target_iri: None,
+                    position: None,
+                },
+            ),
+            None => Ok(()),
+        }
+    }
+
+    /// Attaches `value` to `iri` with `pred`, as overridden by the mapping of `field` of
+    /// `model`.
+    pub fn attach<T: InsertIntoGraph + ?Sized>(
+        builder: &mut GraphBuilder,
+        iri: &SimpleTerm,
+        model: &str,
+        field: &str,
+        pred: SimpleTerm,
+        value: &T,
+    ) -> Result<()> {
+        attach_mapped(builder, iri, model, field, pred, |builder, link| {
+            value.attach_into(builder, link)
+        })
+    }
+
+    /// Like [attach], with `iri` as the object of the triple.
+    pub fn attach_reverse<T: InsertIntoGraph + ?Sized>(
+        builder: &mut GraphBuilder,
+        iri: &SimpleTerm,
+        model: &str,
+        field: &str,
+        pred: SimpleTerm,
+        value: &T,
+    ) -> Result<()> {
+        attach_mapped(builder, iri, model, field, pred, |builder, link| {
+            value.attach_reverse_into(builder, link)
+        })
+    }
+
+    /// A list field, whose items are attached with their position.
+    pub trait List {
+        type Item: InsertIntoGraph;
+
+        fn items(&self) -> &[Self::Item];
+    }
+
+    impl<T: InsertIntoGraph> List for Vec<T> {
+        type Item = T;
+
+        fn items(&self) -> &[T] {
+            self
+        }
+    }
+
+    impl<T: InsertIntoGraph> List for Option<Vec<T>> {
+        type Item = T;
+
+        fn items(&self) -> &[T] {
+            self.as_deref().unwrap_or_default()
+        }
+    }
+
+    /// Like [attach], with the positions of the items if there are several.
+    pub fn attach_ordered<L: List>(
+        builder: &mut GraphBuilder,
+        iri: &SimpleTerm,
+        model: &str,
+        field: &str,
+        pred: SimpleTerm,
+        value: &L,
+    ) -> Result<()> {
+        attach_mapped(builder, iri, model, field, pred, |builder, link| {
+            let items = value.items();
+            for (position, item) in (1..).zip(items) {
+                let link = Link { position: (items.len() > 1).then_some(position), ..link.clone() };
+                item.attach_into(builder, link)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Like [attach_reverse], with the positions of the items if there are several.
+    pub fn attach_reverse_ordered<L: List>(
+        builder: &mut GraphBuilder,
+        iri: &SimpleTerm,
+        model: &str,
+        field: &str,
+        pred: SimpleTerm,
+        value: &L,
+    ) -> Result<()> {
+        attach_mapped(builder, iri, model, field, pred, |builder, link| {
+            let items = value.items();
+            for (position, item) in (1..).zip(items) {
+                let link = Link { position: (items.len() > 1).then_some(position), ..link.clone() };
+                item.attach_reverse_into(builder, link)?;
+            }
+            Ok(())
+        })
+    }
+
+    fn attach_mapped(
+        builder: &mut GraphBuilder,
+        iri: &SimpleTerm,
+        model: &str,
+        field: &str,
+        pred: SimpleTerm,
+        attach: impl FnOnce(&mut GraphBuilder, Link) -> Result<()>,
+    ) -> Result<()> {
+        let Some(mapping) = builder.mapped_field(model, field) else {
+            let link = Link { source_iri: iri.clone(), pred, target_iri: None, position: None };
+            return attach(builder, link);
+        };
+        if mapping.skip {
+            return Ok(());
+        }
+        let pred = mapping.predicate.unwrap_or(pred);
+        let link =
+            Link { source_iri: iri.clone(), pred: pred.clone(), target_iri: None, position: None };
+        let Some(datatype) = mapping.datatype else {
+            return attach(builder, link);
+        };
+
+        // The literals are re-typed once attached (to an empty graph), whatever their
+        // built-in datatype.
+        let graph = std::mem::replace(&mut builder.graph, LightGraph::new());
+        let result = attach(builder, link);
+        let attached = std::mem::replace(&mut builder.graph, graph);
+        result?;
+        for t in attached.triples() {
+            let [s, p, o] = t?.spo();
+            if !(Term::eq(s, iri) && Term::eq(p, &pred)) {
+                builder.graph.insert(s, p, o)?;
+                continue;
+            }
+            let Some(lexical) = o.lexical_form() else {
+                bail!("{}.{}: a datatype can only be mapped for literal values", model, field);
+            };
+            let literal =
+                SimpleTerm::LiteralDatatype(lexical, datatype.as_ref().map_unchecked(Into::into));
+            builder.graph.insert(s, p, literal)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        graph::{
+            graph_builder::GraphBuilder,
+            namespaces::{allores, cat},
+            utils::generate_bnode_term,
+        },
+        rdf::rdf_parser::parse_turtle_to_graph,
+    };
+    use sophia::api::ns::xsd;
+    use sophia_isomorphism::isomorphic_graphs;
+
+    #[derive(InsertIntoGraph)]
+    #[rdf(type = "cat::Batch")]
+    struct Run {
+        #[rdf(pred = "cat::sampleName")]
+        name: String,
+        #[rdf(pred = "cat::msLevel")]
+        level: Option<i32>,
+        #[rdf(pred = "allores::AFX_0000622", datatype = "xsd::dateTime")]
+        start_time: Option<String>,
+        #[rdf(pred = "cat::hasBatch", reverse)]
+        steps: Vec<Step>,
+        #[rdf(flatten)]
+        container: Container,
+        #[rdf(skip)]
+        _cache: Vec<f64>,
+    }
+
+    #[derive(InsertIntoGraph)]
+    #[rdf(type = "cat::MeasureAction")]
+    struct Step {
+        #[rdf(pred = "cat::role")]
+        role: String,
+    }
+
+    #[derive(InsertIntoGraph)]
+    struct Container {
+        #[rdf(pred = "cat::containerID")]
+        container_id: String,
+    }
+
+    #[test]
+    fn test_derived_insert_into() -> anyhow::Result<()> {
+        let run = Run {
+            name: "Vial 1".to_string(),
+            level: Some(2),
+            start_time: Some("2024-07-25T12:00:00".to_string()),
+            steps: vec![Step { role: "injection".to_string() }],
+            container: Container { container_id: "17".to_string() },
+            _cache: vec![1.0],
+        };
+
+        let mut b = GraphBuilder::new();
+        b.insert(&run)?;
+        let expected = parse_turtle_to_graph(
+            r#"
+            PREFIX cat: <http://example.org/cat#>
+            PREFIX xsd: <http://www.w3.org/2001/XMLSchema#>
+            PREFIX allores: <http://purl.allotrope.org/ontologies/result#>
+
+            _:run a cat:Batch;
+                cat:sampleName "Vial 1";
+                cat:msLevel 2;
+                allores:AFX_0000622 "2024-07-25T12:00:00"^^xsd:dateTime;
+                cat:containerID "17".
+            [] a cat:MeasureAction;
+                cat:role "injection";
+                cat:hasBatch _:run.
+            "#,
+        )?;
+        assert!(isomorphic_graphs(&b.graph, &expected)?);
+
+        Ok(())
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_insert_into_graph() -> anyhow::Result<()> {
+        let step = Step { role: "injection".to_string() };
+        let mut graph = parse_turtle_to_graph(
+            "PREFIX cat: <http://example.org/cat#> <http://example.org/a> a cat:Batch.",
+        )?;
+
+        insert_into_graph(&step, &mut graph, generate_bnode_term())?;
+
+        let expected = parse_turtle_to_graph(
+            r#"
+            PREFIX cat: <http://example.org/cat#>
 
+            <http://example.org/a> a cat:Batch.
+            [] a cat:MeasureAction;
+                cat:role "injection".
+            "#,
+        )?;
+        assert!(isomorphic_graphs(&graph, &expected)?);
         Ok(())
     }
 }
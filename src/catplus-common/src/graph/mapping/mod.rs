@@ -0,0 +1,291 @@
+// Runtime overrides of the mapping of the models to the graph.
+//
+// The predicates, classes and datatypes given by `#[derive(InsertIntoGraph)]` are the
+// built-in mapping. A mapping file can override them for each model (e.g. `Batch`) and
+// field (e.g. `batch_name`), so that ontology changes do not require a rebuild:
+//
+//     prefixes:
+//       ex: https://example.org/ontology#
+//     models:
+//       Batch:
+//         class: ex:Batch
+//         fields:
+//           optimization_type: ex:label
+//           link:
+//             predicate: ex:reference
+//             datatype: xsd:anyURI
+//           reaction_type:
+//             skip: true
+//
+// The mapping applies to the graphs built with it, see [super::graph_builder::GraphConfig].
+mod yaml;
+
+use crate::graph::prefix_map::generate_prefix_map;
+use anyhow::{bail, Context, Result};
+use sophia_api::term::{IriRef, SimpleTerm};
+use std::collections::{HashMap, HashSet};
+use yaml::Yaml;
+
+/// Overrides of the built-in mapping, by model name.
+#[derive(Clone, Debug, Default)]
+pub struct Mapping {
+    models: HashMap<String, ModelMapping>,
+}
+
+/// Overrides of the built-in mapping of a model.
+#[derive(Clone, Debug, Default)]
+pub struct ModelMapping {
+    /// The class of the nodes of the model, replacing (or adding) the built-in one.
+    pub class: Option<SimpleTerm<'static>>,
+    /// Overrides by field name.
+    pub fields: HashMap<String, FieldMapping>,
+}
+
+/// Overrides of the built-in mapping of a field.
+#[derive(Clone, Debug, Default)]
+pub struct FieldMapping {
+    pub predicate: Option<SimpleTerm<'static>>,
+    /// The datatype of the literal values of the field.
+    pub datatype: Option<IriRef<String>>,
+    /// Leaves the field out of the graph.
+    pub skip: bool,
+}
+
+impl Mapping {
+    /// Reads a mapping file, see the module documentation for its layout.
+    pub fn from_yaml(input: &str) -> Result<Self> {
+        let Yaml::Mapping(document) = yaml::parse(input)? else {
+            bail!("The mapping must be a YAML mapping");
+        };
+        let mut prefixes: HashMap<String, String> = generate_prefix_map()
+            .into_iter()
+            .map(|(prefix, iri)| (prefix.as_str().to_string(), iri.as_str().to_string()))
+            .collect();
+        if let Some(declared) = entry(&document, "prefixes") {
+            for (prefix, iri) in entries(declared, "prefixes")? {
+                prefixes.insert(prefix.clone(), scalar(iri, prefix)?.to_string());
+            }
+        }
+        let resolve = |value: &Yaml, at: &str| -> Result<String> {
+            let value = scalar(value, at)?;
+            expand(value, &prefixes).with_context(|| format!("Invalid IRI for '{}'", at))
+        };
+
+        let mut models = HashMap::new();
+        for (key, _) in document.iter() {
+            if key != "prefixes" && key != "models" {
+                bail!("Unknown key '{}', expected 'prefixes' or 'models'", key);
+            }
+        }
+        let Some(declared) = entry(&document, "models") else {
+            return Ok(Self { models });
+        };
+        for (model, declared) in entries(declared, "models")? {
+            let mut mapping = ModelMapping::default();
+            for (key, value) in entries(declared, model)? {
+                match key.as_str() {
+                    "class" => mapping.class = Some(iri_term(resolve(value, model)?)),
+                    "fields" => {
+                        for (field, value) in entries(value, model)? {
+                            let at = format!("{}.{}", model, field);
+                            mapping.fields.insert(field.clone(), read_field(value, &at, &resolve)?);
+                        }
+                    }
+                    _ => {
+                        bail!("Unknown key '{}' of '{}', expected 'class' or 'fields'", key, model)
+                    }
+                }
+            }
+            models.insert(model.clone(), mapping);
+        }
+        Ok(Self { models })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.models.is_empty()
+    }
+
+    pub fn model(&self, model: &str) -> Option<&ModelMapping> {
+        self.models.get(model)
+    }
+}
+
+/// Reads the mapping of a field: a predicate, or a mapping of `predicate`, `datatype` and
+/// `skip`.
+fn read_field(
+    value: &Yaml,
+    at: &str,
+    resolve: &dyn Fn(&Yaml, &str) -> Result<String>,
+) -> Result<FieldMapping> {
+    let Yaml::Mapping(keys) = value else {
+        return Ok(FieldMapping {
+            predicate: Some(iri_term(resolve(value, at)?)),
+            ..Default::default()
+        });
+    };
+    let mut field = FieldMapping::default();
+    for (key, value) in keys {
+        match key.as_str() {
+            "predicate" => field.predicate = Some(iri_term(resolve(value, at)?)),
+            "datatype" => field.datatype = Some(IriRef::new_unchecked(resolve(value, at)?)),
+            "skip" => {
+                field.skip = match scalar(value, at)? {
+                    "true" => true,
+                    "false" => false,
+                    other => bail!(
+                        "Invalid 'skip' of '{}': expected true or false, found '{}'",
+                        at,
+                        other
+                    ),
+                }
+            }
+            _ => bail!(
+                "Unknown key '{}' of '{}', expected 'predicate', 'datatype' or 'skip'",
+                key,
+                at
+            ),
+        }
+    }
+    Ok(field)
+}
+
+fn entry<'a>(entries: &'a [(String, Yaml)], key: &str) -> Option<&'a Yaml> {
+    entries.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+}
+
+fn entries<'a>(value: &'a Yaml, at: &str) -> Result<&'a [(String, Yaml)]> {
+    match value {
+        Yaml::Mapping(entries) => Ok(entries),
+        Yaml::Scalar(_) => bail!("Expected a mapping for '{}'", at),
+    }
+}
+
+fn scalar<'a>(value: &'a Yaml, at: &str) -> Result<&'a str> {
+    match value {
+        Yaml::Scalar(s) if !s.is_empty() => Ok(s),
+        Yaml::Scalar(_) => bail!("Missing value for '{}'", at),
+        Yaml::Mapping(_) => bail!("Expected a value for '{}', found a mapping", at),
+    }
+}
+
+/// Expands a compact IRI (`cat:Batch`) with `prefixes`; `<...>` and absolute IRIs are kept.
+fn expand(value: &str, prefixes: &HashMap<String, String>) -> Result<String> {
+    if let Some(iri) = value.strip_prefix('<').and_then(|v| v.strip_suffix('>')) {
+        return Ok(iri.to_string());
+    }
+    let Some((prefix, local)) = value.split_once(':') else {
+        bail!("'{}' is neither an IRI nor a compact IRI", value);
+    };
+    if let Some(namespace) = prefixes.get(prefix) {
+        return Ok(format!("{}{}", namespace, local));
+    }
+    if local.starts_with("//") {
+        return Ok(value.to_string());
+    }
+    bail!("Unknown prefix '{}'", prefix)
+}
+
+fn iri_term(iri: String) -> SimpleTerm<'static> {
+    SimpleTerm::Iri(IriRef::new_unchecked(iri.into()))
+}
+
+/// The fields inserted into a graph of the models with overrides, to find the overrides
+/// which match no field.
+#[derive(Default)]
+pub(crate) struct MappingUsage {
+    seen: HashMap<String, HashSet<String>>,
+}
+
+impl MappingUsage {
+    /// The overrides of `field` of `model` in `mapping`, if any.
+    pub(crate) fn field(
+        &mut self,
+        mapping: &Mapping,
+        model: &str,
+        field: &str,
+    ) -> Option<FieldMapping> {
+        let fields = &mapping.model(model)?.fields;
+        self.seen.entry(model.to_string()).or_default().insert(field.to_string());
+        fields.get(field).cloned()
+    }
+
+    /// The overridden fields (as `Model.field`) of the inserted models which have no such
+    /// field, i.e. most likely misspelled.
+    pub(crate) fn unknown_fields(&self, mapping: &Mapping) -> Vec<String> {
+        let mut unknown: Vec<String> = self
+            .seen
+            .iter()
+            .flat_map(|(model, seen)| {
+                let fields = &mapping.models[model].fields;
+                fields
+                    .keys()
+                    .filter(|f| !seen.contains(*f))
+                    .map(move |f| format!("{}.{}", model, f))
+            })
+            .collect();
+        unknown.sort();
+        unknown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        graph::graph_builder::{GraphBuilder, GraphConfig},
+        models::types::Batch,
+        rdf::rdf_parser::parse_turtle_to_graph,
+    };
+    use sophia_isomorphism::isomorphic_graphs;
+
+    #[test]
+    fn test_mapping_overrides_builtin_mapping() -> Result<()> {
+        let mapping = Mapping::from_yaml(
+            r#"
+prefixes:
+  ex: https://example.org/ontology#
+models:
+  Batch:
+    class: ex:Batch
+    fields:
+      optimization_type: ex:label
+      reaction_type:
+        skip: true
+      link:
+        predicate: <https://example.org/ontology#reference>
+        datatype: xsd:anyURI
+      reaction_nme: ex:reaction
+"#,
+        )?;
+        let batch: Batch = serde_json::from_str(
+            r#"{
+                "batchID": "23",
+                "optimizationType": "Yield optimization",
+                "reactionType": "N-methylation",
+                "link": "https://example.org/article"
+            }"#,
+        )?;
+
+        let mut b = GraphBuilder::with_config(GraphConfig { mapping, ..Default::default() });
+        b.insert(&batch)?;
+
+        let expected = parse_turtle_to_graph(
+            r#"
+            PREFIX ex: <https://example.org/ontology#>
+            PREFIX schema: <https://schema.org/>
+            PREFIX xsd: <http://www.w3.org/2001/XMLSchema#>
+
+            [] a ex:Batch;
+                schema:name "23";
+                ex:label "Yield optimization";
+                ex:reference "https://example.org/article"^^xsd:anyURI.
+            "#,
+        )?;
+        assert!(isomorphic_graphs(&b.graph, &expected)?);
+        assert_eq!(b.unknown_mapped_fields(), ["Batch.reaction_nme"]);
+
+        assert!(Mapping::from_yaml("models:\n  Batch:\n    class: nope:Batch").is_err());
+        assert!(Mapping::from_yaml("models:\n  Batch:\n    colour: ex:Batch").is_err());
+        Ok(())
+    }
+}
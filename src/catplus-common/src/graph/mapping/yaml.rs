@@ -0,0 +1,187 @@
+// The subset of YAML used by mapping files: nested block mappings of scalars, indented
+// with spaces, and comments. Sequences, flow collections, anchors and multi-line
+// scalars are not supported.
+use anyhow::{bail, Result};
+
+/// A YAML node of the subset.
+#[derive(Debug, PartialEq)]
+pub enum Yaml {
+    Scalar(String),
+    /// The entries of a mapping, in document order.
+    Mapping(Vec<(String, Yaml)>),
+}
+
+/// A non-empty line of the document.
+struct Line<'a> {
+    number: usize,
+    indent: usize,
+    content: &'a str,
+}
+
+/// Parses a YAML document of the subset, whose root is a mapping.
+pub fn parse(input: &str) -> Result<Yaml> {
+    let mut lines = Vec::new();
+    for (i, raw) in input.lines().enumerate() {
+        let number = i + 1;
+        let line = strip_comment(raw).trim_end();
+        let content = line.trim_start_matches(' ');
+        if content.is_empty() || (number == 1 && content == "---") {
+            continue;
+        }
+        if content.starts_with('\t') {
+            bail!("Line {}: indent with spaces, not tabs", number);
+        }
+        lines.push(Line { number, indent: line.len() - content.len(), content });
+    }
+
+    let mut pos = 0;
+    let root = parse_mapping(&lines, &mut pos, 0)?;
+    if let Some(line) = lines.get(pos) {
+        bail!("Line {}: unexpected indentation", line.number);
+    }
+    Ok(root)
+}
+
+/// Parses the entries of the mapping starting at `lines[*pos]`, indented by `indent`.
+fn parse_mapping(lines: &[Line], pos: &mut usize, indent: usize) -> Result<Yaml> {
+    let mut entries: Vec<(String, Yaml)> = Vec::new();
+    while let Some(line) = lines.get(*pos) {
+        if line.indent < indent {
+            break;
+        }
+        if line.indent > indent {
+            bail!("Line {}: unexpected indentation", line.number);
+        }
+        let Some((key, value)) = split_entry(line.content) else {
+            bail!("Line {}: expected `key: value`", line.number);
+        };
+        let key = scalar(key, line.number)?;
+        if entries.iter().any(|(k, _)| *k == key) {
+            bail!("Line {}: duplicate key '{}'", line.number, key);
+        }
+        *pos += 1;
+
+        let value = if !value.is_empty() {
+            Yaml::Scalar(scalar(value, line.number)?)
+        } else {
+            match lines.get(*pos) {
+                Some(next) if next.indent > indent => parse_mapping(lines, pos, next.indent)?,
+                _ => Yaml::Scalar(String::new()),
+            }
+        };
+        entries.push((key, value));
+    }
+    Ok(Yaml::Mapping(entries))
+}
+
+/// Splits `key: value` at the first colon outside quotes followed by a space or the end
+/// of the line.
+fn split_entry(content: &str) -> Option<(&str, &str)> {
+    let mut quote = None;
+    for (i, c) in content.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') if i == 0 => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, ':') => {
+                let rest = &content[i + 1..];
+                if rest.is_empty() || rest.starts_with(' ') {
+                    return Some((content[..i].trim(), rest.trim()));
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The value of a plain or quoted scalar.
+fn scalar(value: &str, number: usize) -> Result<String> {
+    if let Some(inner) = value.strip_prefix('\'') {
+        let Some(inner) = inner.strip_suffix('\'') else {
+            bail!("Line {}: unterminated quoted string", number);
+        };
+        return Ok(inner.replace("''", "'"));
+    }
+    if let Some(inner) = value.strip_prefix('"') {
+        let Some(inner) = inner.strip_suffix('"') else {
+            bail!("Line {}: unterminated quoted string", number);
+        };
+        let mut unescaped = String::new();
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                unescaped.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('n') => unescaped.push('\n'),
+                Some('t') => unescaped.push('\t'),
+                Some(c @ ('"' | '\\' | '/')) => unescaped.push(c),
+                _ => bail!("Line {}: invalid escape sequence", number),
+            }
+        }
+        return Ok(unescaped);
+    }
+    if value.starts_with(['[', '{', '-', '&', '*', '|', '>']) {
+        bail!("Line {}: only mappings of plain or quoted scalars are supported", number);
+    }
+    Ok(value.to_string())
+}
+
+/// The line without its comment, i.e. from a `#` at its start or after a space, outside
+/// quotes.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut previous = ' ';
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') if previous == ' ' => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '#') if previous == ' ' || previous == '\t' => return &line[..i],
+            _ => {}
+        }
+        previous = c;
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_nested_mappings() -> Result<()> {
+        let yaml = parse(
+            r#"
+# Mapping of the batches
+Batch:
+  class: ex:Batch   # a comment
+  fields:
+    batch_name: "ex:label # not a comment"
+    link: 'https://example.org/it''s'
+    empty:
+"#,
+        )?;
+        let scalar = |s: &str| Yaml::Scalar(s.to_string());
+        let expected = Yaml::Mapping(vec![(
+            "Batch".to_string(),
+            Yaml::Mapping(vec![
+                ("class".to_string(), scalar("ex:Batch")),
+                (
+                    "fields".to_string(),
+                    Yaml::Mapping(vec![
+                        ("batch_name".to_string(), scalar("ex:label # not a comment")),
+                        ("link".to_string(), scalar("https://example.org/it's")),
+                        ("empty".to_string(), scalar("")),
+                    ]),
+                ),
+            ]),
+        )]);
+        assert_eq!(yaml, expected);
+
+        assert!(parse("a: b\n  c: d").is_err());
+        assert!(parse("a: b\na: c").is_err());
+        assert!(parse("a:\n  - b").is_err());
+        Ok(())
+    }
+}
@@ -1,5 +1,9 @@
+pub mod cat_namespace;
+pub mod chemical_registry;
+pub mod from_graph;
 pub mod graph_builder;
 pub mod insert_into;
+pub mod mapping;
 pub mod namespaces;
 pub mod prefix_map;
 pub mod utils;
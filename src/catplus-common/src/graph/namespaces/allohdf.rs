@@ -3,6 +3,7 @@ use sophia::api::ns::Namespace;
 use sophia_api::namespace;
 namespace! {
     "http://purl.allotrope.org/ontologies/hdf5/1.8#",
+    Dataset,
     HardLink
 }
 lazy_static! {
@@ -4,16 +4,25 @@ use sophia_api::namespace;
 namespace! {
     "http://example.org/cat#",
     AddAction,
+    arrayPath,
+    basePeakIntensity,
+    basePeakMz,
     Batch,
+    batchName,
     Campaign,
     campaignClass,
     campaignType,
     casNumber,
     chemicalName,
+    Chromatogram,
+    chromatographyColumn,
+    ChromatographyRun,
+    chunkShape,
     containerBarcode,
     containerID,
     ContainerPositionAndQuantity,
     criteria,
+    dataType,
     dispenseType,
     errorMargin,
     expectedDatum,
@@ -23,29 +32,59 @@ namespace! {
     hasBatch,
     hasCampaign,
     hasChemical,
+    hasChromatogram,
     hasContainerPositionAndQuantity,
+    hasDataset,
+    hasMassSpectrum,
     hasObjective,
+    hasParameter,
     hasSample,
+    InfraredSpectrum,
+    injectionVolume,
+    instrumentalParameters,
     internalBarCode,
+    MassSpectrometryRun,
+    MassSpectrum,
+    MeasureAction,
     measuredQuantity,
+    msLevel,
     Objective,
     Observation,
     optimizationType,
+    origin,
+    owner,
+    polarity,
+    reactionName,
     reactionSubType,
     reactionType,
     role,
     Sample,
+    sampleName,
+    scanIndex,
+    scanStartTime,
     SetPressureAction,
     SetTemperatureAction,
     SetVacuumAction,
     ShakeAction,
+    shape,
+    signalName,
+    sourceFile,
+    Spectrum,
     speedInRPM,
     speedTumbleStirrerShape,
+    storePath,
     subEquipmentName,
     swissCatNumber,
     temperatureShakerShape,
     temperatureTumbleStirrerShape,
-    vialShape
+    totalIonCurrent,
+    UVVisSpectrum,
+    vacuum,
+    vialShape,
+    xAxisLabel,
+    xUnit,
+    yAxisLabel,
+    yUnit
 }
 lazy_static! {
     pub static ref ns: Namespace<&'static str> = Namespace::new(PREFIX.as_str()).unwrap();
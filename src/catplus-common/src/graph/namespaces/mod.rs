@@ -8,4 +8,5 @@ pub mod obo;
 pub mod purl;
 pub mod qudt;
 pub mod schema;
+pub mod sh;
 pub mod unit;
@@ -5,7 +5,8 @@ namespace! {
     "https://schema.org/",
     description,
     keywords,
-    name
+    name,
+    position
 }
 lazy_static! {
     pub static ref ns: Namespace<&'static str> = Namespace::new(PREFIX.as_str()).unwrap();
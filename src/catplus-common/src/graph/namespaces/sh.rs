@@ -0,0 +1,64 @@
+use lazy_static::lazy_static;
+use sophia::api::ns::Namespace;
+use sophia_api::namespace;
+namespace! {
+    "http://www.w3.org/ns/shacl#",
+    alternativePath,
+    and,
+    BlankNode,
+    BlankNodeOrIRI,
+    BlankNodeOrLiteral,
+    class,
+    closed,
+    datatype,
+    deactivated,
+    disjoint,
+    equals,
+    flags,
+    hasValue,
+    ignoredProperties,
+    Info,
+    inversePath,
+    IRI,
+    IRIOrLiteral,
+    languageIn,
+    lessThan,
+    lessThanOrEquals,
+    Literal,
+    maxCount,
+    maxExclusive,
+    maxInclusive,
+    maxLength,
+    message,
+    minCount,
+    minExclusive,
+    minInclusive,
+    minLength,
+    node,
+    NodeShape,
+    not,
+    oneOrMorePath,
+    or,
+    path,
+    pattern,
+    property,
+    PropertyShape,
+    qualifiedMaxCount,
+    qualifiedMinCount,
+    qualifiedValueShape,
+    severity,
+    targetClass,
+    targetNode,
+    targetObjectsOf,
+    targetSubjectsOf,
+    uniqueLang,
+    Violation,
+    Warning,
+    xone,
+    zeroOrMorePath,
+    zeroOrOnePath;
+    in_, "in"
+}
+lazy_static! {
+    pub static ref ns: Namespace<&'static str> = Namespace::new(PREFIX.as_str()).unwrap();
+}
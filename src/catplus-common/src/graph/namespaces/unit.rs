@@ -9,11 +9,20 @@ namespace! {
     "https://qudt.org/vocab/unit/",
     Bar,
     DegC,
+    GM,
     GMPerMilliL,
     GMPerMol,
+    MicroL,
+    MicroM,
     MilliGM,
+    MIN,
     MolPerL,
-    RevPerMin
+    NanoM,
+    Percent,
+    PerCentiM,
+    RevPerMin,
+    SEC,
+    Unitless
 }
 
 lazy_static! {
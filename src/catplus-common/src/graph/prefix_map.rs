@@ -1,5 +1,6 @@
-use crate::graph::namespaces::{
-    allocom, allohdf, alloproc, alloqual, allores, cat, obo, purl, qudt, schema, unit,
+use crate::graph::{
+    cat_namespace::CatNamespace,
+    namespaces::{allocom, allohdf, alloproc, alloqual, allores, obo, purl, qudt, schema, unit},
 };
 use sophia_api::{prefix::Prefix, prelude::Iri};
 
@@ -33,22 +34,33 @@ macro_rules! ns_entries_module { // For the other modules
     };
 }
 
-pub fn generate_prefix_map() -> Vec<(Prefix<Box<str>>, Iri<Box<str>>)> {
+/// A list of (prefix, namespace IRI) pairs, as expected by sophia serializers.
+pub type PrefixMap = Vec<(Prefix<Box<str>>, Iri<Box<str>>)>;
+
+/// The prefix map of the graphs of the built-in Cat+ namespace.
+pub fn generate_prefix_map() -> PrefixMap {
+    generate_prefix_map_for(&CatNamespace::default())
+}
+
+/// The prefix map of the graphs of the Cat+ namespace `cat_namespace`, to which the
+/// `cat` prefix maps, see [super::cat_namespace].
+pub fn generate_prefix_map_for(cat_namespace: &CatNamespace) -> PrefixMap {
     let msg = "Namespace URI should always be valid";
-    ns_entries_direct!(msg, rdf, xsd) // Correct call for rdf and xsd
+    let mut entries: Vec<(&str, String)> = ns_entries_direct!(msg, rdf, xsd) // Correct call for rdf and xsd
+        .into_iter()
+        .chain(ns_entries_module!(
+            // Correct call for the other modules
+            msg, schema, unit, allores, alloproc, allocom, allohdf, qudt, alloqual, purl, obo
+        ))
+        .map(|(prefix, iri)| (prefix, iri.to_string()))
+        .collect();
+    entries.insert(2, ("cat", cat_namespace.as_str().to_string()));
+    entries
         .into_iter()
-        .chain(
-            ns_entries_module!(
-                // Correct call for the other modules
-                msg, cat, schema, unit, allores, alloproc, allocom, allohdf, qudt, alloqual, purl,
-                obo
-            )
-            .into_iter(),
-        )
         .map(|(prefix, iri)| {
             (
                 Prefix::new(prefix.to_string().into_boxed_str()).expect("Invalid prefix"),
-                Iri::new(iri.to_string().into_boxed_str()).expect("Invalid IRI"),
+                Iri::new(iri.into_boxed_str()).expect("Invalid IRI"),
             )
         })
         .collect()
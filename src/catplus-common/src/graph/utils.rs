@@ -1,12 +1,97 @@
+use anyhow::{bail, Context, Result};
 use sophia_api::{
     prelude::*,
     term::{bnode_id::BnodeId, SimpleTerm},
 };
 use uuid::Uuid;
 
+/// How [crate::graph::graph_builder::GraphBuilder] names the nodes of a graph.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum IriStrategy {
+    /// Every node is a blank node with a random label.
+    #[default]
+    Random,
+    /// Nodes with identifying fields (batches, chemicals, samples...) are named
+    /// `<base><kind>/<uuid>`, the UUID (v5) being derived from the base, the kind and the
+    /// identifying fields. Other nodes are blank nodes labelled in insertion order, so that
+    /// converting the same input twice yields the same output.
+    ContentDerived { base: String },
+}
+
+impl IriStrategy {
+    /// [IriStrategy::ContentDerived] under `base`, e.g. `https://example.org/catplus/`.
+    pub fn content_derived(base: &str) -> Result<Self> {
+        check_namespace(base).context("Invalid base IRI")?;
+        Ok(Self::ContentDerived { base: base.to_string() })
+    }
+}
+
+/// Checks that `iri` is an absolute IRI ending with `#` or `/`, under which terms can be
+/// named.
+pub fn check_namespace(iri: &str) -> Result<()> {
+    if IriRef::new(iri).is_err() || !iri.contains(':') {
+        bail!("'{}' is not a valid namespace IRI", iri);
+    }
+    if !iri.ends_with(['#', '/']) {
+        bail!("The namespace '{}' should end with '#' or '/'", iri);
+    }
+    Ok(())
+}
+
+/// A blank node with a random label, the nodes of [IriStrategy::Random].
 pub fn generate_bnode_term() -> SimpleTerm<'static> {
-    let identifier = Uuid::new_v4().to_string();
-    let bnode = BnodeId::new_unchecked(identifier);
+    bnode_term(Uuid::new_v4().to_string())
+}
+
+/// A blank node labelled `label`.
+pub(crate) fn bnode_term(label: String) -> SimpleTerm<'static> {
+    let bnode = BnodeId::new_unchecked(label);
 
     bnode.try_into_term().expect("Failed to convert BnodeId to SimpleTerm")
 }
+
+/// Names a node of type `kind` (e.g. `batch`) identified by `key` under `base`, see
+/// [IriStrategy::ContentDerived].
+pub(crate) fn content_derived_iri(
+    base: &str,
+    kind: &str,
+    key: &[&str],
+) -> Result<SimpleTerm<'static>> {
+    let namespace = Uuid::new_v5(&Uuid::NAMESPACE_URL, format!("{}{}", base, kind).as_bytes());
+    // The fields are arbitrary strings: they are hashed as a JSON array, so that different
+    // keys (e.g. `["a,b"]` and `["a", "b"]`) never hash the same bytes.
+    let fields = serde_json::to_string(key).context("Failed to encode the key of a node")?;
+    let uuid = Uuid::new_v5(&namespace, fields.as_bytes());
+    let iri = IriRef::new(format!("{}{}/{}", base, kind, uuid))
+        .with_context(|| format!("Invalid IRI for a {} node", kind))?;
+
+    Ok(iri.into_term())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_derived_iri() -> Result<()> {
+        let iri = content_derived_iri("http://example.org/", "batch", &["23"])?;
+
+        assert_eq!(iri, content_derived_iri("http://example.org/", "batch", &["23"])?);
+        assert_ne!(iri, content_derived_iri("http://example.org/", "batch", &["24"])?);
+        assert_ne!(iri, content_derived_iri("http://example.org/", "chemical", &["23"])?);
+        assert_ne!(
+            content_derived_iri("http://example.org/", "sample", &["a\u{1f}b", "c"])?,
+            content_derived_iri("http://example.org/", "sample", &["a", "b\u{1f}c"])?
+        );
+        assert_ne!(
+            content_derived_iri("http://example.org/", "sample", &["a\",\"b"])?,
+            content_derived_iri("http://example.org/", "sample", &["a", "b"])?
+        );
+        assert!(iri.iri().unwrap().as_str().starts_with("http://example.org/batch/"));
+
+        assert!(IriStrategy::content_derived("http://example.org/").is_ok());
+        assert!(IriStrategy::content_derived("not an iri/").is_err());
+        assert!(IriStrategy::content_derived("http://example.org").is_err());
+        Ok(())
+    }
+}
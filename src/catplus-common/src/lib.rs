@@ -1,3 +1,9 @@
+// The code generated by `#[derive(InsertIntoGraph)]` refers to this crate by name.
+extern crate self as catplus_common;
+
 pub mod graph;
 pub mod models;
+pub mod parsers;
 pub mod rdf;
+pub mod shacl;
+pub mod zarr;
@@ -0,0 +1,60 @@
+// Chromatography runs as exported by HPLC instruments (e.g. Agilent ChemStation / OpenLab).
+// The run metadata is mapped to the graph, while the signal traces and the peak tables
+// are written to the Zarr store and referenced from the graph.
+use crate::{
+    graph::{
+        insert_into::InsertIntoGraph,
+        namespaces::{allores, cat},
+    },
+    models::{
+        dataset::Dataset,
+        types::{Batch, Observation},
+    },
+    zarr::{insert_into_store::InsertIntoStore, zarr_store::ZarrStore},
+};
+use sophia::api::ns::xsd;
+
+#[derive(Clone, Debug, InsertIntoGraph)]
+#[rdf(type = "cat::ChromatographyRun")]
+pub struct ChromatographyRun {
+    /// The Cat+ batch the injected sample belongs to.
+    #[rdf(pred = "cat::hasBatch")]
+    pub has_batch: Option<Batch>,
+    #[rdf(pred = "cat::sampleName")]
+    pub sample_name: Option<String>,
+    #[rdf(pred = "allores::AFR_0001606")]
+    pub method_name: Option<String>,
+    #[rdf(pred = "allores::AFR_0001723")]
+    pub instrument_name: Option<String>,
+    #[rdf(pred = "cat::injectionVolume")]
+    pub injection_volume: Option<Observation>,
+    #[rdf(pred = "cat::chromatographyColumn")]
+    pub column: Option<String>,
+    /// Acquisition start, as an `xsd:dateTime` lexical value.
+    #[rdf(pred = "allores::AFX_0000622", datatype = "xsd::dateTime")]
+    pub acquisition_time: Option<String>,
+    #[rdf(pred = "cat::hasChromatogram")]
+    pub has_chromatogram: Vec<Chromatogram>,
+}
+
+impl InsertIntoStore for ChromatographyRun {
+    fn insert_into_store(&mut self, store: &ZarrStore) -> anyhow::Result<()> {
+        self.has_chromatogram.insert_into_store(store)
+    }
+}
+
+/// A detector signal of a run, with its trace and peak table stored as datasets.
+#[derive(Clone, Debug, InsertIntoGraph)]
+#[rdf(type = "cat::Chromatogram")]
+pub struct Chromatogram {
+    #[rdf(pred = "cat::signalName")]
+    pub signal_name: String,
+    #[rdf(pred = "cat::hasDataset")]
+    pub has_dataset: Vec<Dataset>,
+}
+
+impl InsertIntoStore for Chromatogram {
+    fn insert_into_store(&mut self, store: &ZarrStore) -> anyhow::Result<()> {
+        self.has_dataset.insert_into_store(store)
+    }
+}
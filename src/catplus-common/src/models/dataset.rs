@@ -0,0 +1,176 @@
+use crate::{
+    graph::{
+        graph_builder::GraphBuilder,
+        insert_into::{InsertIntoGraph, Link},
+        namespaces::{allohdf, cat},
+    },
+    zarr::{
+        data_reference::DataReference,
+        insert_into_store::InsertIntoStore,
+        zarr_array::{ArrayConfig, NdArray},
+        zarr_store::ZarrStore,
+    },
+};
+use sophia::api::ns::{rdf, xsd};
+use sophia_api::{
+    graph::MutableGraph,
+    term::{SimpleTerm, Term},
+};
+
+/// A numeric array produced by an [crate::models::Action] or a [crate::models::Sample].
+///
+/// The array is written to the Zarr store by [InsertIntoStore] and represented in the
+/// graph by a [DataReference] node.
+#[derive(Clone, Debug)]
+pub struct Dataset {
+    /// Path of the array inside the Zarr store.
+    pub path: String,
+    pub array: NdArray,
+    pub config: ArrayConfig,
+    /// Set once the array has been written to a store.
+    pub written: Option<DataReference>,
+}
+
+impl Dataset {
+    pub fn new(path: impl Into<String>, array: NdArray, config: ArrayConfig) -> Self {
+        Self { path: path.into(), array, config, written: None }
+    }
+
+    /// The reference to the array: the written location if any, otherwise the
+    /// layout the array will have in a store.
+    pub fn reference(&self) -> anyhow::Result<DataReference> {
+        if let Some(written) = &self.written {
+            return Ok(written.clone());
+        }
+        Ok(DataReference {
+            store_path: None,
+            array_path: self.path.trim_matches('/').to_string(),
+            shape: self.array.shape.clone(),
+            data_type: self.array.data_type(),
+            chunk_shape: self.config.resolve_chunk_shape(&self.array.shape)?,
+        })
+    }
+}
+
+impl InsertIntoStore for Dataset {
+    fn insert_into_store(&mut self, store: &ZarrStore) -> anyhow::Result<()> {
+        self.written = Some(store.write_array(&self.path, &self.array, &self.config)?);
+        Ok(())
+    }
+}
+
+impl InsertIntoGraph for Dataset {
+    fn insert_into(&self, builder: &mut GraphBuilder, iri: SimpleTerm) -> anyhow::Result<()> {
+        self.reference()?.insert_into(builder, iri)
+    }
+}
+
+/// A reference is inserted as an `allohdf:Dataset` node. The Cat+ ontology has no terms
+/// for the layout of a Zarr array yet, so the node uses these converter terms:
+/// * `cat:storePath`: the file name of the Zarr store, next to the output file
+/// * `cat:arrayPath`: the path of the array inside the store
+/// * `cat:shape` and `cat:chunkShape`: RDF lists of the dimensions, as `xsd:integer`
+/// * `cat:dataType`: the Zarr v3 data type, e.g. `float64`
+///
+/// The arrays of a node are attached to it with `cat:hasDataset`.
+impl InsertIntoGraph for DataReference {
+    fn insert_into(&self, builder: &mut GraphBuilder, iri: SimpleTerm) -> anyhow::Result<()> {
+        for (pred, value) in [
+            (rdf::type_, &allohdf::Dataset.as_simple() as &dyn InsertIntoGraph),
+            (cat::storePath, &self.store_path.as_ref().map(|s| s.as_simple())),
+            (cat::arrayPath, &self.array_path.as_simple()),
+            (cat::shape, &integer_list(builder, &self.shape)?),
+            (cat::dataType, &self.data_type.name().as_simple()),
+            (cat::chunkShape, &integer_list(builder, &self.chunk_shape)?),
+        ] {
+            value.attach_into(
+                builder,
+                Link {
+                    source_iri: iri.clone(),
+                    pred: pred.as_simple(),
+                    target_iri: None,
+                    position: None,
+                },
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Inserts the RDF list of `values`, as `xsd:integer` literals, into the graph of
+/// `builder`.
+///
+/// # Returns
+/// The head of the list, `rdf:nil` for an empty list.
+fn integer_list(builder: &mut GraphBuilder, values: &[u64]) -> anyhow::Result<SimpleTerm<'static>> {
+    let mut head = rdf::nil.into_term();
+    for value in values.iter().rev() {
+        let node = builder.generate_bnode_term();
+        let literal: SimpleTerm = (value.to_string().as_str() * xsd::integer).into_term();
+        builder.graph.insert(&node, rdf::first, literal)?;
+        builder.graph.insert(&node, rdf::rest, &head)?;
+        head = node;
+    }
+    Ok(head)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        graph::graph_builder::GraphBuilder, models::Action, rdf::rdf_parser::parse_turtle_to_graph,
+        zarr::zarr_array::ArrayValues,
+    };
+    use sophia_isomorphism::isomorphic_graphs;
+
+    #[test]
+    fn test_dataset_reference_attached_to_action() -> anyhow::Result<()> {
+        let mut action: Action = serde_json::from_str(
+            r#"{
+                "actionName": "filtrateAction",
+                "startTime": "2024-07-25T12:15:23",
+                "endingTime": "2024-07-25T12:16:50",
+                "methodName": "filtrate",
+                "equipmentName": "Chemspeed SWING XL",
+                "subEquipmentName": "Filtration unit"
+            }"#,
+        )?;
+        let array = NdArray::vector(ArrayValues::Float64(vec![0.1, 0.2, 0.3]));
+        action.has_dataset.push(Dataset::new(
+            "filtrate/signal",
+            array,
+            ArrayConfig::default().with_chunk_shape(vec![2]),
+        ));
+
+        let dir = tempfile::tempdir()?;
+        let store = ZarrStore::create(dir.path().join("batch.zarr"))?;
+        action.insert_into_store(&store)?;
+
+        let mut b = GraphBuilder::new();
+        b.insert(&action)?;
+        let expected_ttl = r#"
+            PREFIX cat: <http://example.org/cat#>
+            PREFIX allores: <http://purl.allotrope.org/ontologies/result#>
+            PREFIX allohdf: <http://purl.allotrope.org/ontologies/hdf5/1.8#>
+            PREFIX xsd: <http://www.w3.org/2001/XMLSchema#>
+
+            [] a cat:FiltrateAction;
+            cat:subEquipmentName "Filtration unit";
+            allores:AFR_0001606 "filtrate";
+            allores:AFR_0001723 "Chemspeed SWING XL";
+            allores:AFR_0002423 "2024-07-25T12:16:50"^^xsd:dateTime;
+            allores:AFX_0000622 "2024-07-25T12:15:23"^^xsd:dateTime;
+            cat:hasDataset [ a allohdf:Dataset;
+                cat:storePath "batch.zarr";
+                cat:arrayPath "filtrate/signal";
+                cat:shape (3);
+                cat:dataType "float64";
+                cat:chunkShape (2)].
+            "#;
+        let expected_graph = parse_turtle_to_graph(expected_ttl)?;
+        let result_graph = parse_turtle_to_graph(&b.serialize_to_turtle()?)?;
+        assert!(isomorphic_graphs(&result_graph, &expected_graph)?);
+
+        Ok(())
+    }
+}
@@ -1,9 +1,25 @@
-use crate::graph::namespaces::{cat, unit};
+use crate::{
+    graph::{
+        from_graph::FromGraph,
+        graph_builder::GraphBuilder,
+        insert_into::{InsertIntoGraph, Link},
+        namespaces::{cat, unit},
+    },
+    shacl::shapes::display_term,
+};
+use anyhow::Context;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use sophia_api::ns::NsTerm;
-use std::fmt;
+use serde_json::Value;
+use sophia::{api::ns::xsd, inmem::graph::LightGraph};
+use sophia_api::{
+    ns::NsTerm,
+    term::{SimpleTerm, Term},
+};
+use std::{fmt, str::FromStr};
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+#[schemars(transform = unit_aliases)]
 #[allow(non_snake_case, non_camel_case_types)]
 pub enum Unit {
     #[serde(rename = "bar")]
@@ -12,6 +28,8 @@ pub enum Unit {
     DegC,
     #[serde(rename = "mg")]
     MilliGM,
+    #[serde(rename = "g")]
+    GM,
     #[serde(rename = "g/mL")]
     GMPerMilliL,
     #[serde(rename = "g/mol")]
@@ -20,18 +38,83 @@ pub enum Unit {
     MolPerL,
     #[serde(rename = "rpm")]
     RevPerMin,
+    #[serde(rename = "µL", alias = "uL")]
+    MicroL,
+    #[serde(rename = "1/cm")]
+    PerCentiM,
+    #[serde(rename = "nm")]
+    NanoM,
+    #[serde(rename = "µm", alias = "um")]
+    MicroM,
+    // The dimensionless units are units of the instrument files only, see
+    // `Unit::from_label`.
+    #[serde(rename = "%", skip_deserializing)]
+    Percent,
+    #[serde(rename = "1", skip_deserializing)]
+    Unitless,
+    #[serde(rename = "min")]
+    Minute,
+    #[serde(rename = "s")]
+    Second,
+}
+
+/// Adds the aliases of the units, which schemars leaves out, to the strings of the JSON
+/// Schema of [Unit].
+fn unit_aliases(schema: &mut schemars::Schema) {
+    if let Some(Value::Array(names)) = schema.get_mut("enum") {
+        names.extend(["uL", "um"].map(Value::from));
+    }
 }
 
 impl Unit {
+    /// The unit of a label of an instrument file, e.g. `nm`: the units of the input files
+    /// and the dimensionless units of the instruments (`%`, and `1` or `(unitless)`),
+    /// which the input files do not accept.
+    pub fn from_label(label: &str) -> Option<Self> {
+        match label.trim() {
+            "%" => Some(Unit::Percent),
+            "1" | "(unitless)" => Some(Unit::Unitless),
+            label => serde_json::from_value(Value::from(label)).ok(),
+        }
+    }
+
+    const ALL: [Unit; 16] = [
+        Unit::Bar,
+        Unit::DegC,
+        Unit::MilliGM,
+        Unit::GM,
+        Unit::GMPerMilliL,
+        Unit::GMPerMol,
+        Unit::MolPerL,
+        Unit::RevPerMin,
+        Unit::MicroL,
+        Unit::PerCentiM,
+        Unit::NanoM,
+        Unit::MicroM,
+        Unit::Percent,
+        Unit::Unitless,
+        Unit::Minute,
+        Unit::Second,
+    ];
+
     pub fn display_name(&self) -> &'static str {
         match self {
             Unit::Bar => "Bar",
             Unit::DegC => "DEG-C",
             Unit::MilliGM => "MilliGM",
+            Unit::GM => "GM",
             Unit::GMPerMilliL => "GM-PER-MilliL",
             Unit::GMPerMol => "GM-PER-MOL",
             Unit::MolPerL => "MOL-PER-L",
             Unit::RevPerMin => "REV-PER-MIN",
+            Unit::MicroL => "MicroL",
+            Unit::PerCentiM => "PER-CentiM",
+            Unit::NanoM => "NanoM",
+            Unit::MicroM => "MicroM",
+            Unit::Percent => "PERCENT",
+            Unit::Unitless => "UNITLESS",
+            Unit::Minute => "MIN",
+            Unit::Second => "SEC",
         }
     }
     pub fn iri(&self) -> NsTerm<'_> {
@@ -39,13 +122,34 @@ impl Unit {
     }
 }
 
+/// Units are attached as their IRI.
+impl InsertIntoGraph for Unit {
+    fn insert_into(&self, _builder: &mut GraphBuilder, _iri: SimpleTerm) -> anyhow::Result<()> {
+        unimplemented!("cannot insert unit {} into graph, use `attach_into`", &self)
+    }
+
+    fn attach_into(&self, builder: &mut GraphBuilder, attach: Link) -> anyhow::Result<()> {
+        self.iri().as_simple().attach_into(builder, attach)
+    }
+}
+
+/// Reads the unit of an IRI of [Unit::iri].
+impl FromGraph for Unit {
+    fn from_graph(_graph: &LightGraph, term: &SimpleTerm) -> anyhow::Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|u| Term::eq(&u.iri(), term))
+            .with_context(|| format!("Unknown unit {}", display_term(&term.into_term())))
+    }
+}
+
 impl fmt::Display for Unit {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "<{}>", self.iri().to_string())
+        write!(f, "<{}>", self.iri())
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 #[allow(non_snake_case, non_camel_case_types)]
 pub enum ActionName {
     AddAction,
@@ -54,16 +158,28 @@ pub enum ActionName {
     shakeAction,
     setVacuumAction,
     setPressureAction,
+    /// A measurement or analysis step, e.g. an AnIML `ExperimentStep`.
+    measureAction,
 }
 
 impl fmt::Display for ActionName {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "<{}>", self.iri().to_string())
+        write!(f, "<{}>", self.iri())
     }
 }
 
 impl ActionName {
-    pub fn iri(&self) -> NsTerm {
+    const ALL: [ActionName; 7] = [
+        Self::AddAction,
+        Self::setTemperatureAction,
+        Self::filtrateAction,
+        Self::shakeAction,
+        Self::setVacuumAction,
+        Self::setPressureAction,
+        Self::measureAction,
+    ];
+
+    pub fn iri(&self) -> NsTerm<'_> {
         match self {
             Self::AddAction => cat::AddAction,
             Self::setTemperatureAction => cat::SetTemperatureAction,
@@ -71,6 +187,165 @@ impl ActionName {
             Self::shakeAction => cat::ShakeAction,
             Self::setVacuumAction => cat::SetVacuumAction,
             Self::filtrateAction => cat::FiltrateAction,
+            Self::measureAction => cat::MeasureAction,
         }
     }
 }
+
+/// Actions are attached as their class IRI.
+impl InsertIntoGraph for ActionName {
+    fn insert_into(&self, _builder: &mut GraphBuilder, _iri: SimpleTerm) -> anyhow::Result<()> {
+        unimplemented!("cannot insert action {} into graph, use `attach_into`", &self)
+    }
+
+    fn attach_into(&self, builder: &mut GraphBuilder, attach: Link) -> anyhow::Result<()> {
+        self.iri().as_simple().attach_into(builder, attach)
+    }
+}
+
+/// Reads the action of a class IRI of [ActionName::iri].
+impl FromGraph for ActionName {
+    fn from_graph(_graph: &LightGraph, term: &SimpleTerm) -> anyhow::Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|a| Term::eq(&a.iri(), term))
+            .with_context(|| format!("Unknown action {}", display_term(&term.into_term())))
+    }
+}
+
+/// Kind of spectrum, as given by the `DATA TYPE` of a JCAMP-DX file.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum SpectrumType {
+    Infrared,
+    UvVis,
+    Other,
+}
+
+impl SpectrumType {
+    pub fn iri(&self) -> NsTerm<'_> {
+        match self {
+            Self::Infrared => cat::InfraredSpectrum,
+            Self::UvVis => cat::UVVisSpectrum,
+            Self::Other => cat::Spectrum,
+        }
+    }
+}
+
+/// Spectrum types are attached as their class IRI.
+impl InsertIntoGraph for SpectrumType {
+    fn insert_into(&self, _builder: &mut GraphBuilder, _iri: SimpleTerm) -> anyhow::Result<()> {
+        unimplemented!("cannot insert spectrum type {:?} into graph, use `attach_into`", &self)
+    }
+
+    fn attach_into(&self, builder: &mut GraphBuilder, attach: Link) -> anyhow::Result<()> {
+        self.iri().as_simple().attach_into(builder, attach)
+    }
+}
+
+/// The number of an [crate::models::Observation], which remembers whether the input wrote
+/// it as an integer so that `25` is not read back as `25.0`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Number {
+    Integer(i64),
+    Float(f64),
+}
+
+impl Number {
+    pub fn as_f64(&self) -> f64 {
+        match *self {
+            Self::Integer(value) => value as f64,
+            Self::Float(value) => value,
+        }
+    }
+}
+
+impl From<f64> for Number {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<i64> for Number {
+    fn from(value: i64) -> Self {
+        Self::Integer(value)
+    }
+}
+
+/// Parses an integer if `s` has no decimal point or exponent, and a float otherwise.
+impl FromStr for Number {
+    type Err = std::num::ParseFloatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse() {
+            Ok(value) => Ok(Self::Integer(value)),
+            Err(_) => s.parse().map(Self::Float),
+        }
+    }
+}
+
+/// Integers are written without a decimal point and floats with one, e.g. `25` and `25.0`.
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Integer(value) => write!(f, "{}", value),
+            Self::Float(value) => write!(f, "{:?}", value),
+        }
+    }
+}
+
+impl Serialize for Number {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            Self::Integer(value) => serializer.serialize_i64(value),
+            Self::Float(value) => serializer.serialize_f64(value),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Number {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct NumberVisitor;
+
+        impl serde::de::Visitor<'_> for NumberVisitor {
+            type Value = Number;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a number")
+            }
+
+            fn visit_i64<E>(self, value: i64) -> Result<Number, E> {
+                Ok(Number::Integer(value))
+            }
+
+            fn visit_u64<E>(self, value: u64) -> Result<Number, E> {
+                Ok(i64::try_from(value).map_or(Number::Float(value as f64), Number::Integer))
+            }
+
+            fn visit_f64<E>(self, value: f64) -> Result<Number, E> {
+                Ok(Number::Float(value))
+            }
+        }
+
+        deserializer.deserialize_any(NumberVisitor)
+    }
+}
+
+/// Numbers are attached as `xsd:double` literals, whose lexical form tells integers apart.
+impl InsertIntoGraph for Number {
+    fn insert_into(&self, _builder: &mut GraphBuilder, _iri: SimpleTerm) -> anyhow::Result<()> {
+        unimplemented!("cannot insert number {} into graph, use `attach_into`", &self)
+    }
+
+    fn attach_into(&self, builder: &mut GraphBuilder, attach: Link) -> anyhow::Result<()> {
+        let literal: SimpleTerm = (self.to_string().as_str() * xsd::double).into_term();
+        literal.attach_into(builder, attach)
+    }
+}
+
+/// Reads a number from its lexical form, see [Number::from_str].
+impl FromGraph for Number {
+    fn from_graph(graph: &LightGraph, term: &SimpleTerm) -> anyhow::Result<Self> {
+        let lexical = String::from_graph(graph, term)?;
+        lexical.parse().with_context(|| format!("Expected a number, found {:?}", lexical))
+    }
+}
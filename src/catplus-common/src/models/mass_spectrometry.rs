@@ -0,0 +1,70 @@
+// Mass spectrometry runs (LC-MS, GC-MS) as read from mzML files.
+// The run and scan metadata is mapped to the graph, while the peak lists of all scans are
+// written to the Zarr store as ragged arrays and referenced from the graph.
+use crate::{
+    graph::{
+        insert_into::InsertIntoGraph,
+        namespaces::{allores, cat, purl},
+    },
+    models::{
+        dataset::Dataset,
+        types::{Batch, Observation},
+    },
+    zarr::{insert_into_store::InsertIntoStore, zarr_store::ZarrStore},
+};
+use sophia::api::ns::xsd;
+
+#[derive(Clone, Debug, InsertIntoGraph)]
+#[rdf(type = "cat::MassSpectrometryRun")]
+pub struct MassSpectrometryRun {
+    #[rdf(pred = "purl::identifier")]
+    pub run_id: String,
+    /// The Cat+ batch the analysed sample belongs to.
+    #[rdf(pred = "cat::hasBatch")]
+    pub has_batch: Option<Batch>,
+    #[rdf(pred = "cat::sampleName")]
+    pub sample_name: Option<String>,
+    #[rdf(pred = "allores::AFR_0001723")]
+    pub instrument_name: Option<String>,
+    #[rdf(pred = "cat::sourceFile")]
+    pub source_file: Option<String>,
+    /// Acquisition start, as an `xsd:dateTime` lexical value.
+    #[rdf(pred = "allores::AFX_0000622", datatype = "xsd::dateTime")]
+    pub start_time: Option<String>,
+    #[rdf(pred = "cat::hasMassSpectrum")]
+    pub has_mass_spectrum: Vec<MassSpectrum>,
+    /// The m/z and intensity values of all scans, concatenated, and the offsets of each
+    /// scan in them.
+    #[rdf(pred = "cat::hasDataset")]
+    pub has_dataset: Vec<Dataset>,
+}
+
+impl InsertIntoStore for MassSpectrometryRun {
+    fn insert_into_store(&mut self, store: &ZarrStore) -> anyhow::Result<()> {
+        self.has_dataset.insert_into_store(store)
+    }
+}
+
+/// A scan of a run. Its peaks are found in the run datasets, between the offsets at
+/// `scan_index` and `scan_index + 1`.
+#[derive(Clone, Debug, InsertIntoGraph)]
+#[rdf(type = "cat::MassSpectrum")]
+pub struct MassSpectrum {
+    #[rdf(pred = "purl::identifier")]
+    pub scan_id: String,
+    #[rdf(pred = "cat::scanIndex")]
+    pub scan_index: usize,
+    #[rdf(pred = "cat::msLevel")]
+    pub ms_level: Option<i32>,
+    /// `positive` or `negative`.
+    #[rdf(pred = "cat::polarity")]
+    pub polarity: Option<String>,
+    #[rdf(pred = "cat::scanStartTime")]
+    pub scan_start_time: Option<Observation>,
+    #[rdf(pred = "cat::totalIonCurrent")]
+    pub total_ion_current: Option<f64>,
+    #[rdf(pred = "cat::basePeakMz")]
+    pub base_peak_mz: Option<f64>,
+    #[rdf(pred = "cat::basePeakIntensity")]
+    pub base_peak_intensity: Option<f64>,
+}
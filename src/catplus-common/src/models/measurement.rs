@@ -0,0 +1,108 @@
+// Measure actions as read from instrument files (AnIML, ASM).
+// Instrument files describe less than the Synth input: a measurement has no ending time
+// nor sub-equipment, and its samples may lack a container or vial type. These fields are
+// required in [Action] and [Sample] since a Synth file must have them (they are checked
+// when the input is read and by its JSON Schema), so the measurements have their own
+// types rather than loosening the Synth models. They are built from the same parts
+// ([Batch], [ContainerInfo], [Observation], [Dataset]) and write the same classes and
+// predicates, so that a measurement reads like a Synth action in the graph. The
+// measurements are linked to their batch like the actions of a Synth batch, while their
+// values are written to the Zarr store and referenced from the graph.
+use crate::{
+    graph::{
+        graph_builder::GraphBuilder,
+        insert_into::InsertIntoGraph,
+        namespaces::{allores, cat, schema},
+    },
+    models::{
+        dataset::Dataset,
+        enums::ActionName,
+        types::{Batch, ContainerInfo, Observation},
+    },
+    zarr::{insert_into_store::InsertIntoStore, zarr_store::ZarrStore},
+};
+use sophia::api::ns::{rdf, xsd};
+use sophia_api::term::SimpleTerm;
+
+/// A batch with the measurements of an instrument file.
+#[derive(Clone, Debug, InsertIntoGraph)]
+#[rdf(get_uri = "Self::uri")]
+pub struct MeasurementBatch {
+    #[rdf(flatten)]
+    pub batch: Batch,
+    #[rdf(pred = "cat::hasBatch", reverse)]
+    pub measurements: Vec<Measurement>,
+}
+
+impl MeasurementBatch {
+    pub fn new(batch_id: String) -> Self {
+        Self {
+            batch: Batch {
+                batch_id,
+                actions: None,
+                batch_name: None,
+                reaction_type: None,
+                reaction_name: None,
+                optimization_type: None,
+                link: None,
+            },
+            measurements: Vec::new(),
+        }
+    }
+
+    fn uri(&self, builder: &mut GraphBuilder) -> anyhow::Result<SimpleTerm<'static>> {
+        self.batch.get_uri(builder)
+    }
+}
+
+impl InsertIntoStore for MeasurementBatch {
+    fn insert_into_store(&mut self, store: &ZarrStore) -> anyhow::Result<()> {
+        self.measurements.insert_into_store(store)
+    }
+}
+
+#[derive(Clone, Debug, InsertIntoGraph)]
+pub struct Measurement {
+    #[rdf(pred = "rdf::type_")]
+    pub action_name: ActionName,
+    #[rdf(pred = "allores::AFX_0000622", datatype = "xsd::dateTime")]
+    pub start_time: String,
+    #[rdf(pred = "allores::AFR_0001606")]
+    pub method_name: String,
+    #[rdf(pred = "allores::AFR_0001723")]
+    pub equipment_name: Option<String>,
+    #[rdf(pred = "cat::hasSample")]
+    pub has_sample: Vec<MeasuredSample>,
+    #[rdf(pred = "cat::hasParameter")]
+    pub has_parameter: Vec<Parameter>,
+    #[rdf(pred = "cat::hasDataset")]
+    pub has_dataset: Vec<Dataset>,
+}
+
+impl InsertIntoStore for Measurement {
+    fn insert_into_store(&mut self, store: &ZarrStore) -> anyhow::Result<()> {
+        self.has_dataset.insert_into_store(store)
+    }
+}
+
+#[derive(Clone, Debug, InsertIntoGraph)]
+#[rdf(type = "cat::Sample")]
+pub struct MeasuredSample {
+    #[rdf(flatten)]
+    pub container: Option<ContainerInfo>,
+    #[rdf(pred = "allores::AFR_0002464")]
+    pub vial_id: String,
+    #[rdf(pred = "cat::vialShape")]
+    pub vial_type: Option<String>,
+    #[rdf(pred = "cat::role")]
+    pub role: Option<String>,
+}
+
+/// A named [Observation], such as an instrument setting or a measured condition.
+#[derive(Clone, Debug, InsertIntoGraph)]
+pub struct Parameter {
+    #[rdf(pred = "schema::name")]
+    pub name: String,
+    #[rdf(flatten)]
+    pub observation: Observation,
+}
@@ -1,6 +1,16 @@
+pub mod chromatography;
+pub mod dataset;
 pub mod enums;
+pub mod mass_spectrometry;
+pub mod measurement;
+pub mod spectroscopy;
 pub mod types;
 
 // Re-export all models;
+pub use chromatography::*;
+pub use dataset::*;
 pub use enums::*;
+pub use mass_spectrometry::*;
+pub use measurement::*;
+pub use spectroscopy::*;
 pub use types::*;
@@ -0,0 +1,51 @@
+// Spectra (IR, UV-Vis) as read from JCAMP-DX files.
+// The acquisition metadata is mapped to the graph, while the abscissa and ordinate
+// values are written to the Zarr store and referenced from the graph.
+use crate::{
+    graph::{
+        insert_into::InsertIntoGraph,
+        namespaces::{allores, cat, schema},
+    },
+    models::{
+        dataset::Dataset,
+        enums::{SpectrumType, Unit},
+    },
+    zarr::{insert_into_store::InsertIntoStore, zarr_store::ZarrStore},
+};
+use sophia::api::ns::{rdf, xsd};
+
+#[derive(Clone, Debug, InsertIntoGraph)]
+pub struct Spectrum {
+    #[rdf(pred = "schema::name")]
+    pub title: String,
+    #[rdf(pred = "rdf::type_")]
+    pub spectrum_type: SpectrumType,
+    #[rdf(pred = "cat::origin")]
+    pub origin: Option<String>,
+    #[rdf(pred = "cat::owner")]
+    pub owner: Option<String>,
+    #[rdf(pred = "allores::AFR_0001723")]
+    pub instrument_name: Option<String>,
+    #[rdf(pred = "cat::instrumentalParameters")]
+    pub instrumental_parameters: Option<String>,
+    /// Acquisition start, as an `xsd:dateTime` lexical value.
+    #[rdf(pred = "allores::AFX_0000622", datatype = "xsd::dateTime")]
+    pub acquisition_time: Option<String>,
+    /// Axis labels as written in the source file (e.g. `1/CM`, `ABSORBANCE`).
+    #[rdf(pred = "cat::xAxisLabel")]
+    pub x_label: String,
+    #[rdf(pred = "cat::yAxisLabel")]
+    pub y_label: String,
+    #[rdf(pred = "cat::xUnit")]
+    pub x_unit: Option<Unit>,
+    #[rdf(pred = "cat::yUnit")]
+    pub y_unit: Option<Unit>,
+    #[rdf(pred = "cat::hasDataset")]
+    pub has_dataset: Vec<Dataset>,
+}
+
+impl InsertIntoStore for Spectrum {
+    fn insert_into_store(&mut self, store: &ZarrStore) -> anyhow::Result<()> {
+        self.has_dataset.insert_into_store(store)
+    }
+}
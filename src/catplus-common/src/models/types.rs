@@ -4,12 +4,17 @@
 // https://github.com/sdsc-ordes/catplus-ontology/tree/96091fd2e75e03de8a4c4d66ad502b2db27998bd/json-file/1-Synth
 use crate::{
     graph::{
+        chemical_registry::find_or_register,
+        from_graph::{find_instance, non_empty, sort_list, FromGraph, Node},
+        graph_builder::GraphBuilder,
         insert_into::{InsertIntoGraph, Link},
         namespaces::{alloproc, allocom, allohdf, alloqual, allores, cat, obo, purl, qudt, schema},
     },
-    models::enums::{ActionName, Unit},
+    models::{dataset::Dataset, enums::{ActionName, Number, Unit}},
+    zarr::{insert_into_store::InsertIntoStore, zarr_store::ZarrStore},
 };
 use anyhow;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use sophia::{
     api::ns::{rdf, xsd},
@@ -20,395 +25,605 @@ use sophia_api::{
     term::{SimpleTerm, Term},
 };
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, InsertIntoGraph)]
 #[serde(rename_all = "camelCase")]
+#[rdf(type = "cat::Campaign")]
 pub struct Campaign {
+    #[rdf(pred = "schema::name")]
     pub campaign_name: String,
+    #[rdf(pred = "schema::description")]
     pub description: String,
     #[serde(rename = "objective")]
+    #[rdf(pred = "cat::genericObjective")]
     pub generic_objective: String,
+    #[rdf(pred = "cat::campaignClass")]
     pub campaign_class: String,
     #[serde(rename = "type")]
+    #[rdf(pred = "cat::campaignType")]
     pub campaign_type: String,
+    #[rdf(pred = "allores::AFR_0002764")]
     pub reference: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rdf(pred = "cat::hasObjective")]
     pub has_objective: Option<Objective>,
+    #[rdf(pred = "cat::hasBatch")]
     pub has_batch: Batch,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rdf(pred = "cat::hasChemical", ordered)]
     pub has_chemical: Option<Vec<Chemical>>,
 }
 
-impl InsertIntoGraph for Campaign {
-    fn insert_into(&self, graph: &mut LightGraph, iri: SimpleTerm) -> anyhow::Result<()> {
-        for (pred, value) in [
-            (rdf::type_, &cat::Campaign.as_simple() as &dyn InsertIntoGraph),
-            (schema::name, &self.campaign_name.as_simple()),
-            (schema::description, &self.description.as_simple()),
-            (cat::genericObjective, &self.generic_objective.as_simple()),
-            (cat::campaignClass, &self.campaign_class.as_simple()),
-            (cat::campaignType, &self.campaign_type.as_simple()),
-            (allores::AFR_0002764, &self.reference.as_simple()),
-            (cat::hasObjective, &self.has_objective),
-            (cat::hasBatch, &self.has_batch),
-            (cat::hasChemical, &self.has_chemical),
-        ] {
-            value.attach_into(
-                graph,
-                Link { source_iri: iri.clone(), pred: pred.as_simple(), target_iri: None },
-            )?;
-        }
-        Ok(())
+impl FromGraph for Campaign {
+    fn from_graph(graph: &LightGraph, iri: &SimpleTerm) -> anyhow::Result<Self> {
+        let node = Node::new(graph, iri);
+        let has_chemical =
+            node.read_list(cat::hasChemical, |a: &Chemical, b| a.chemical_id.cmp(&b.chemical_id))?;
+        Ok(Self {
+            campaign_name: node.read(schema::name)?,
+            description: node.read(schema::description)?,
+            generic_objective: node.read(cat::genericObjective)?,
+            campaign_class: node.read(cat::campaignClass)?,
+            campaign_type: node.read(cat::campaignType)?,
+            reference: node.read(allores::AFR_0002764)?,
+            has_objective: node.read_optional(cat::hasObjective)?,
+            has_batch: node.read(cat::hasBatch)?,
+            has_chemical: non_empty(has_chemical),
+        })
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, InsertIntoGraph)]
 #[serde(rename_all = "camelCase")]
+#[rdf(type = "obo::IAO_0000005")]
 pub struct Objective {
+    #[rdf(pred = "cat::criteria")]
     pub criteria: String,
+    #[rdf(pred = "allocom::AFC_0000090")]
     pub condition: String,
+    #[rdf(pred = "schema::description")]
     pub description: String,
+    #[rdf(pred = "schema::name")]
     pub objective_name: String,
 }
 
-impl InsertIntoGraph for Objective {
-    fn insert_into(&self, graph: &mut LightGraph, iri: SimpleTerm) -> anyhow::Result<()> {
-        for (pred, value) in [
-            (rdf::type_, &obo::IAO_0000005.as_simple()),
-            (schema::name, &self.objective_name.as_simple()),
-            (schema::description, &self.description.as_simple()),
-            (cat::criteria, &self.criteria.as_simple()),
-            (allocom::AFC_0000090, &self.condition.as_simple()),
-        ] {
-            value.attach_into(
-                graph,
-                Link { source_iri: iri.clone(), pred: pred.as_simple(), target_iri: None },
-            )?;
-        }
-        Ok(())
+impl FromGraph for Objective {
+    fn from_graph(graph: &LightGraph, iri: &SimpleTerm) -> anyhow::Result<Self> {
+        let node = Node::new(graph, iri);
+        Ok(Self {
+            criteria: node.read(cat::criteria)?,
+            condition: node.read(allocom::AFC_0000090)?,
+            description: node.read(schema::description)?,
+            objective_name: node.read(schema::name)?,
+        })
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema, InsertIntoGraph)]
 pub struct CampaignWrapper {
     #[serde(rename = "hasCampaign")]
+    #[rdf(flatten)]
     pub has_campaign: Campaign,
 }
-impl InsertIntoGraph for CampaignWrapper {
-    fn insert_into(&self, graph: &mut LightGraph, iri: SimpleTerm) -> anyhow::Result<()> {
-        self.has_campaign.insert_into(graph, iri)
+
+/// Reads the only campaign of the graph.
+impl FromGraph for CampaignWrapper {
+    fn from_graph(graph: &LightGraph, iri: &SimpleTerm) -> anyhow::Result<Self> {
+        Ok(Self { has_campaign: Campaign::from_graph(graph, iri)? })
+    }
+
+    fn find_root(graph: &LightGraph) -> anyhow::Result<SimpleTerm<'static>> {
+        find_instance(graph, cat::Campaign)
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+impl InsertIntoStore for CampaignWrapper {
+    fn insert_into_store(&mut self, store: &ZarrStore) -> anyhow::Result<()> {
+        self.has_campaign.has_batch.insert_into_store(store)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, InsertIntoGraph)]
 #[serde(rename_all = "camelCase")]
+#[rdf(type = "cat::Batch", get_uri = "Self::uri")]
 pub struct Batch {
     #[serde(rename = "batchID")]
+    #[rdf(pred = "schema::name")]
     pub batch_id: String,
     #[serde(rename = "Actions")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rdf(pred = "cat::hasBatch", reverse, ordered)]
     pub actions: Option<Vec<Action>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rdf(pred = "cat::batchName")]
     pub batch_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rdf(pred = "cat::reactionType")]
     pub reaction_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rdf(pred = "cat::reactionName")]
     pub reaction_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rdf(pred = "cat::optimizationType")]
     pub optimization_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rdf(pred = "allohdf::HardLink")]
     pub link: Option<String>,
 }
 
-impl InsertIntoGraph for Batch {
-    fn insert_into(&self, graph: &mut LightGraph, iri: SimpleTerm) -> anyhow::Result<()> {
-        for (pred, value) in [
-            (rdf::type_, &cat::Batch.as_simple() as &dyn InsertIntoGraph),
-            (schema::name, &self.batch_id.as_simple()),
-            (allohdf::HardLink, &self.link.as_ref().clone().map(|s| s.as_simple())),
-            (cat::reactionType, &self.reaction_type.as_ref().clone().map(|s| s.as_simple())),
-            (
-                cat::optimizationType,
-                &self.optimization_type.as_ref().clone().map(|s| s.as_simple()),
-            ),
+impl Batch {
+    fn uri(&self, builder: &mut GraphBuilder) -> anyhow::Result<SimpleTerm<'static>> {
+        builder.generate_iri_term("batch", &[&self.batch_id])
+    }
+
+    /// Merges `other`, another description of the same batch (e.g. the Synth file of the
+    /// batch of a campaign), into this one: its actions are appended and its properties
+    /// fill the missing ones. Properties set in both with different values keep the value
+    /// of this batch and are reported as conflicts, e.g. "batchName is 'a', ignoring 'b'".
+    pub fn merge(&mut self, other: Batch) -> anyhow::Result<Vec<String>> {
+        if self.batch_id != other.batch_id {
+            anyhow::bail!("Cannot merge batch '{}' into batch '{}'", other.batch_id, self.batch_id);
+        }
+        if let Some(actions) = other.actions {
+            self.actions.get_or_insert_with(Vec::new).extend(actions);
+        }
+        let mut conflicts = Vec::new();
+        for (property, kept, value) in [
+            ("batchName", &mut self.batch_name, other.batch_name),
+            ("reactionType", &mut self.reaction_type, other.reaction_type),
+            ("reactionName", &mut self.reaction_name, other.reaction_name),
+            ("optimizationType", &mut self.optimization_type, other.optimization_type),
+            ("link", &mut self.link, other.link),
         ] {
-            value.attach_into(
-                graph,
-                Link { source_iri: iri.clone(), pred: pred.as_simple(), target_iri: None },
-            )?;
+            match (kept.as_ref(), value) {
+                (None, value) => *kept = value,
+                (Some(kept), Some(value)) if *kept != value => {
+                    conflicts.push(format!("{} is '{}', ignoring '{}'", property, kept, value))
+                }
+                _ => {}
+            }
         }
+        Ok(conflicts)
+    }
+}
 
+/// Reads the only batch of the graph, with the actions linked to it in the order of their
+/// position, or by start and ending time without one.
+impl FromGraph for Batch {
+    fn from_graph(graph: &LightGraph, iri: &SimpleTerm) -> anyhow::Result<Self> {
+        let node = Node::new(graph, iri);
         // NOTE: for actions, the direction is reversed (action hasbatch batch)
-        if let Some(actions) = &self.actions {
-            for action in actions {
-                let action_uri = action.get_uri();
-                graph.insert(&action_uri, cat::hasBatch.as_simple(), iri.clone())?;
-                action.insert_into(graph, action_uri)?;
-            }
-        }
+        let is_action = |subject: &&SimpleTerm| {
+            let types = Node::new(graph, subject).objects(rdf::type_);
+            types.iter().any(|t| ActionName::from_graph(graph, t).is_ok())
+        };
+        let actions = node
+            .subjects(cat::hasBatch)
+            .into_iter()
+            .filter(|subject| is_action(&subject))
+            .map(|subject| Ok((Action::from_graph(graph, &subject)?, subject)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let actions = sort_list(graph, actions, |a, b| {
+            (&a.start_time, &a.ending_time).cmp(&(&b.start_time, &b.ending_time))
+        });
+        Ok(Self {
+            batch_id: node.read(schema::name)?,
+            actions: non_empty(actions),
+            batch_name: node.read_optional(cat::batchName)?,
+            reaction_type: node.read_optional(cat::reactionType)?,
+            reaction_name: node.read_optional(cat::reactionName)?,
+            optimization_type: node.read_optional(cat::optimizationType)?,
+            link: node.read_optional(allohdf::HardLink)?,
+        })
+    }
 
-        Ok(())
+    fn find_root(graph: &LightGraph) -> anyhow::Result<SimpleTerm<'static>> {
+        find_instance(graph, cat::Batch)
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+impl InsertIntoStore for Batch {
+    fn insert_into_store(&mut self, store: &ZarrStore) -> anyhow::Result<()> {
+        self.actions.insert_into_store(store)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, InsertIntoGraph)]
 #[serde(rename_all = "camelCase")]
 pub struct Action {
+    #[rdf(pred = "rdf::type_")]
     pub action_name: ActionName,
+    #[rdf(pred = "allores::AFX_0000622", datatype = "xsd::dateTime")]
     pub start_time: String,
+    #[rdf(pred = "allores::AFR_0002423", datatype = "xsd::dateTime")]
     pub ending_time: String,
+    #[rdf(pred = "allores::AFR_0001606")]
     pub method_name: String,
+    #[rdf(pred = "allores::AFR_0001723")]
     pub equipment_name: String,
+    #[rdf(pred = "cat::subEquipmentName")]
     pub sub_equipment_name: String,
     #[serde(flatten)]
+    #[rdf(flatten)]
     pub container_info: Option<ContainerInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rdf(pred = "cat::speedInRPM")]
     pub speed_shaker: Option<Observation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rdf(pred = "cat::hasContainerPositionAndQuantity", ordered)]
     pub has_container_position_and_quantity: Option<Vec<ContainerPositionQuantityItem>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rdf(pred = "alloqual::AFQ_0000111")]
     pub dispense_state: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rdf(pred = "cat::dispenseType")]
     pub dispense_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rdf(pred = "cat::hasSample")]
     pub has_sample: Option<Sample>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rdf(pred = "cat::speedTumbleStirrerShape")]
     pub speed_tumble_stirrer: Option<Observation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rdf(pred = "cat::temperatureTumbleStirrerShape")]
     pub temperature_tumble_stirrer: Option<Observation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rdf(pred = "cat::temperatureShakerShape")]
     pub temperature_shaker: Option<Observation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rdf(pred = "alloproc::AFP_0002677")]
     pub pressure_measurement: Option<Observation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rdf(pred = "cat::vacuum")]
+    pub vacuum: Option<Observation>,
+    #[serde(skip)]
+    #[rdf(pred = "cat::hasDataset")]
+    pub has_dataset: Vec<Dataset>,
 }
 
-impl InsertIntoGraph for Action {
-    fn insert_into(&self, graph: &mut LightGraph, iri: SimpleTerm) -> anyhow::Result<()> {
-        for (pred, value) in [
-            (rdf::type_, &self.action_name.iri().as_simple() as &dyn InsertIntoGraph),
-            (allores::AFX_0000622, &(self.start_time.as_str() * xsd::dateTime).as_simple()),
-            (allores::AFR_0002423, &(self.ending_time.as_str() * xsd::dateTime).as_simple()),
-            (allores::AFR_0001606, &self.method_name.as_simple()),
-            (allores::AFR_0001723, &self.equipment_name.as_simple()),
-            (cat::subEquipmentName, &self.sub_equipment_name.as_simple()),
-            (cat::speedInRPM, &self.speed_shaker),
-            (cat::temperatureTumbleStirrerShape, &self.temperature_tumble_stirrer),
-            (cat::speedTumbleStirrerShape, &self.speed_tumble_stirrer),
-            (cat::temperatureShakerShape, &self.temperature_shaker),
-            (alloproc::AFP_0002677, &self.pressure_measurement),
-            (cat::hasSample, &self.has_sample),
-            (cat::hasContainerPositionAndQuantity, &self.has_container_position_and_quantity),
-            (alloqual::AFQ_0000111, &self.dispense_state.as_ref().clone().map(|s| s.as_simple())),
-            (cat::dispenseType, &self.dispense_type.as_ref().clone().map(|s| s.as_simple())),
-        ] {
-            value.attach_into(
-                graph,
-                Link { source_iri: iri.clone(), pred: pred.as_simple(), target_iri: None },
-            )?;
-        }
-
-        // NOTE: for container_info, we attach triples directly to the action
-        let _ = &self.container_info.insert_into(graph, iri.clone())?;
+/// Reads an action. The datasets of the action describe arrays of a Zarr store and are
+/// not read back, as they are not part of the JSON input.
+impl FromGraph for Action {
+    fn from_graph(graph: &LightGraph, iri: &SimpleTerm) -> anyhow::Result<Self> {
+        let node = Node::new(graph, iri);
+        let has_container_position_and_quantity = node.read_list(
+            cat::hasContainerPositionAndQuantity,
+            |a: &ContainerPositionQuantityItem, b| {
+                (&a.container_id, &a.position).cmp(&(&b.container_id, &b.position))
+            },
+        )?;
+        Ok(Self {
+            action_name: node.read(rdf::type_)?,
+            start_time: node.read(allores::AFX_0000622)?,
+            ending_time: node.read(allores::AFR_0002423)?,
+            method_name: node.read(allores::AFR_0001606)?,
+            equipment_name: node.read(allores::AFR_0001723)?,
+            sub_equipment_name: node.read(cat::subEquipmentName)?,
+            // NOTE: for container_info, the triples are attached directly to the action
+            container_info: ContainerInfo::read_inline(&node)?,
+            speed_shaker: node.read_optional(cat::speedInRPM)?,
+            has_container_position_and_quantity: non_empty(has_container_position_and_quantity),
+            dispense_state: node.read_optional(alloqual::AFQ_0000111)?,
+            dispense_type: node.read_optional(cat::dispenseType)?,
+            has_sample: node.read_optional(cat::hasSample)?,
+            speed_tumble_stirrer: node.read_optional(cat::speedTumbleStirrerShape)?,
+            temperature_tumble_stirrer: node.read_optional(cat::temperatureTumbleStirrerShape)?,
+            temperature_shaker: node.read_optional(cat::temperatureShakerShape)?,
+            pressure_measurement: node.read_optional(alloproc::AFP_0002677)?,
+            vacuum: node.read_optional(cat::vacuum)?,
+            has_dataset: Vec::new(),
+        })
+    }
+}
 
-        Ok(())
+impl InsertIntoStore for Action {
+    fn insert_into_store(&mut self, store: &ZarrStore) -> anyhow::Result<()> {
+        self.has_dataset.insert_into_store(store)?;
+        self.has_sample.insert_into_store(store)
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, InsertIntoGraph)]
 #[serde(rename_all = "camelCase")]
 pub struct ContainerInfo {
     #[serde(rename = "containerID")]
+    #[rdf(pred = "cat::containerID")]
     pub container_id: String,
+    #[rdf(pred = "cat::containerBarcode")]
     pub container_barcode: String,
 }
 
-impl InsertIntoGraph for ContainerInfo {
-    fn insert_into(&self, graph: &mut LightGraph, iri: SimpleTerm) -> anyhow::Result<()> {
-        for (prop, value) in [
-            (&cat::containerID, &self.container_id.as_simple() as &dyn InsertIntoGraph),
-            (&cat::containerBarcode, &self.container_barcode.as_simple()),
-        ] {
-            value.attach_into(
-                graph,
-                Link { source_iri: iri.clone(), pred: prop.as_simple(), target_iri: None },
-            )?;
+impl ContainerInfo {
+    /// Reads the container info attached directly to `node`, if any.
+    fn read_inline(node: &Node) -> anyhow::Result<Option<Self>> {
+        if !node.has(cat::containerID) {
+            return Ok(None);
         }
-        Ok(())
+        Ok(Some(Self {
+            container_id: node.read(cat::containerID)?,
+            container_barcode: node.read(cat::containerBarcode)?,
+        }))
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, InsertIntoGraph)]
 #[serde(rename_all = "camelCase")]
+#[rdf(type = "cat::Observation")]
 pub struct Observation {
-    pub value: f64,
+    #[schemars(with = "f64")]
+    #[rdf(pred = "qudt::value")]
+    pub value: Number,
+    #[rdf(pred = "qudt::unit")]
     pub unit: Unit,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rdf(pred = "cat::errorMargin")]
     pub error_margin: Option<ErrorMargin>,
 }
 
-/// Implementation for concrete [Observation].
-impl InsertIntoGraph for Observation {
-    fn insert_into(&self, graph: &mut LightGraph, iri: SimpleTerm) -> anyhow::Result<()> {
-        for (prop, value) in [
-            (rdf::type_, &cat::Observation.as_simple() as &dyn InsertIntoGraph),
-            (qudt::unit, &self.unit.iri().as_simple() as &dyn InsertIntoGraph),
-            (qudt::value, &self.value.as_simple()),
-            (cat::errorMargin, &self.error_margin),
-        ] {
-            value.attach_into(
-                graph,
-                Link { source_iri: iri.clone(), pred: prop.as_simple(), target_iri: None },
-            )?;
+impl Observation {
+    /// The observation as a comparable string, e.g. `104.15 g/mol ± 0.1 g/mol`.
+    pub(crate) fn key(&self) -> String {
+        match &self.error_margin {
+            Some(e) => format!(
+                "{} {} ± {} {}",
+                self.value,
+                self.unit.display_name(),
+                e.value,
+                e.unit.display_name()
+            ),
+            None => format!("{} {}", self.value, self.unit.display_name()),
         }
+    }
+}
 
-        Ok(())
+impl FromGraph for Observation {
+    fn from_graph(graph: &LightGraph, iri: &SimpleTerm) -> anyhow::Result<Self> {
+        let node = Node::new(graph, iri);
+        Ok(Self {
+            value: node.read(qudt::value)?,
+            unit: node.read(qudt::unit)?,
+            error_margin: node.read_optional(cat::errorMargin)?,
+        })
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, InsertIntoGraph)]
+#[rdf(type = "cat::errorMargin")]
 pub struct ErrorMargin {
-    pub value: f64,
+    #[schemars(with = "f64")]
+    #[rdf(pred = "qudt::value")]
+    pub value: Number,
+    #[rdf(pred = "qudt::unit")]
     pub unit: Unit,
 }
 
-/// Implementation for concrete [Observation].
-impl InsertIntoGraph for ErrorMargin {
-    fn insert_into(&self, graph: &mut LightGraph, iri: SimpleTerm) -> anyhow::Result<()> {
-        for (prop, value) in [
-            (rdf::type_, &cat::errorMargin.as_simple() as &dyn InsertIntoGraph),
-            (qudt::unit, &self.unit.iri().as_simple() as &dyn InsertIntoGraph),
-            (qudt::value, &self.value.as_simple()),
-        ] {
-            value.attach_into(
-                graph,
-                Link { source_iri: iri.clone(), pred: prop.as_simple(), target_iri: None },
-            )?;
-        }
-
-        Ok(())
+impl FromGraph for ErrorMargin {
+    fn from_graph(graph: &LightGraph, iri: &SimpleTerm) -> anyhow::Result<Self> {
+        let node = Node::new(graph, iri);
+        Ok(Self { value: node.read(qudt::value)?, unit: node.read(qudt::unit)? })
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, InsertIntoGraph)]
 #[serde(rename_all = "camelCase")]
+#[rdf(type = "cat::Sample")]
 pub struct Sample {
     #[serde(flatten)]
+    #[rdf(flatten)]
     pub container: ContainerInfo,
     #[serde(rename = "vialID")]
+    #[rdf(pred = "allores::AFR_0002464")]
     pub vial_id: String,
+    #[rdf(pred = "cat::vialShape")]
     pub vial_type: String,
+    #[rdf(pred = "cat::role")]
     pub role: String,
+    #[rdf(pred = "cat::expectedDatum")]
     pub expected_datum: Observation,
+    #[rdf(pred = "cat::hasSample", ordered)]
     pub has_sample: Vec<SampleItem>,
+    #[serde(skip)]
+    #[rdf(pred = "cat::hasDataset")]
+    pub has_dataset: Vec<Dataset>,
 }
 
-impl InsertIntoGraph for Sample {
-    fn insert_into(&self, graph: &mut LightGraph, iri: SimpleTerm) -> anyhow::Result<()> {
-        for (prop, value) in [
-            (rdf::type_, &cat::Sample.as_simple() as &dyn InsertIntoGraph),
-            (cat::role, &self.role.as_simple()),
-            (cat::vialShape, &self.vial_type.as_simple()),
-            (allores::AFR_0002464, &self.vial_id.as_simple()),
-            (cat::expectedDatum, &self.expected_datum),
-            (cat::hasSample, &self.has_sample),
-        ] {
-            value.attach_into(
-                graph,
-                Link { source_iri: iri.clone(), pred: prop.as_simple(), target_iri: None },
-            )?;
-        }
-
-        // NOTE: for container_info, we attach triples directly to the sample
-        let _ = &self.container.insert_into(graph, iri.clone())?;
+/// Reads a sample, with its items in the order of their position, or by identifier
+/// without one. The datasets are not read back, see [Action].
+impl FromGraph for Sample {
+    fn from_graph(graph: &LightGraph, iri: &SimpleTerm) -> anyhow::Result<Self> {
+        let node = Node::new(graph, iri);
+        let has_sample =
+            node.read_list(cat::hasSample, |a: &SampleItem, b| a.sample_id.cmp(&b.sample_id))?;
+        Ok(Self {
+            // NOTE: for container_info, the triples are attached directly to the sample
+            container: ContainerInfo {
+                container_id: node.read(cat::containerID)?,
+                container_barcode: node.read(cat::containerBarcode)?,
+            },
+            vial_id: node.read(allores::AFR_0002464)?,
+            vial_type: node.read(cat::vialShape)?,
+            role: node.read(cat::role)?,
+            expected_datum: node.read(cat::expectedDatum)?,
+            has_sample,
+            has_dataset: Vec::new(),
+        })
+    }
+}
 
-        Ok(())
+impl InsertIntoStore for Sample {
+    fn insert_into_store(&mut self, store: &ZarrStore) -> anyhow::Result<()> {
+        self.has_dataset.insert_into_store(store)
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, InsertIntoGraph)]
 #[serde(rename_all = "camelCase")]
+#[rdf(type = "cat::Sample", get_uri = "Self::uri")]
 pub struct SampleItem {
     #[serde(rename = "sampleID")]
+    #[rdf(pred = "purl::identifier")]
     pub sample_id: String,
+    #[rdf(pred = "cat::role")]
     pub role: String,
+    #[rdf(pred = "cat::internalBarCode")]
     pub internal_bar_code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rdf(pred = "cat::expectedDatum")]
     pub expected_datum: Option<Observation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rdf(pred = "cat::measuredQuantity")]
     pub measured_quantity: Option<Observation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rdf(pred = "allores::AFR_0002036")]
     pub concentration: Option<Observation>,
+    #[rdf(pred = "alloqual::AFQ_0000111")]
     pub physical_state: String,
+    #[rdf(pred = "cat::hasChemical")]
     pub has_chemical: Chemical,
 }
 
-impl InsertIntoGraph for SampleItem {
-    fn insert_into(&self, graph: &mut LightGraph, iri: SimpleTerm) -> anyhow::Result<()> {
-        for (prop, value) in [
-            (rdf::type_, &cat::Sample.as_simple() as &dyn InsertIntoGraph),
-            (purl::identifier, &self.sample_id.as_simple()),
-            (cat::role, &self.role.as_simple()),
-            (cat::internalBarCode, &self.internal_bar_code.as_simple()),
-            (alloqual::AFQ_0000111, &self.physical_state.as_simple()),
-            (cat::expectedDatum, &self.expected_datum),
-            (cat::measuredQuantity, &self.measured_quantity),
-            (allores::AFR_0002036, &self.concentration),
-            (cat::hasChemical, &self.has_chemical),
-        ] {
-            value.attach_into(
-                graph,
-                Link { source_iri: iri.clone(), pred: prop.as_simple(), target_iri: None },
-            )?;
-        }
+impl SampleItem {
+    /// The same sample can be listed by several actions with different roles or
+    /// quantities, so that all the properties are part of the identity of the node.
+    fn uri(&self, builder: &mut GraphBuilder) -> anyhow::Result<SimpleTerm<'static>> {
+        let observation =
+            |o: &Option<Observation>| o.as_ref().map(Observation::key).unwrap_or_default();
+        builder.generate_iri_term(
+            "sample",
+            &[
+                &self.sample_id,
+                &self.role,
+                &self.internal_bar_code,
+                &self.physical_state,
+                &self.has_chemical.chemical_id,
+                &observation(&self.expected_datum),
+                &observation(&self.measured_quantity),
+                &observation(&self.concentration),
+            ],
+        )
+    }
+}
 
-        Ok(())
+impl FromGraph for SampleItem {
+    fn from_graph(graph: &LightGraph, iri: &SimpleTerm) -> anyhow::Result<Self> {
+        let node = Node::new(graph, iri);
+        Ok(Self {
+            sample_id: node.read(purl::identifier)?,
+            role: node.read(cat::role)?,
+            internal_bar_code: node.read(cat::internalBarCode)?,
+            expected_datum: node.read_optional(cat::expectedDatum)?,
+            measured_quantity: node.read_optional(cat::measuredQuantity)?,
+            concentration: node.read_optional(allores::AFR_0002036)?,
+            physical_state: node.read(alloqual::AFQ_0000111)?,
+            has_chemical: node.read(cat::hasChemical)?,
+        })
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, InsertIntoGraph)]
 #[serde(rename_all = "camelCase")]
+#[rdf(type = "obo::CHEBI_25367", get_uri = "Self::uri", attach_into = "Self::attach")]
 pub struct Chemical {
     #[serde(rename = "chemicalID")]
+    #[rdf(pred = "purl::identifier")]
     pub chemical_id: String,
+    #[rdf(pred = "cat::chemicalName")]
     pub chemical_name: String,
     #[serde(rename = "CASNumber")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rdf(pred = "cat::casNumber")]
     pub cas_number: Option<String>,
+    #[rdf(pred = "allores::AFR_0002294")]
     pub molecular_mass: Observation,
+    #[rdf(pred = "allores::AFR_0002295")]
     pub smiles: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rdf(pred = "cat::swissCatNumber")]
     pub swiss_cat_number: Option<String>,
     #[serde(rename = "Inchi")]
+    #[rdf(pred = "allores::AFR_0002296")]
     pub inchi: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rdf(pred = "schema::keywords")]
     pub keywords: Option<String>,
+    #[rdf(pred = "allores::AFR_0001952")]
     pub molecular_formula: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rdf(pred = "obo::PATO_0001019")]
     pub density: Option<Observation>,
 }
 
-impl InsertIntoGraph for Chemical {
-    fn insert_into(&self, graph: &mut LightGraph, iri: SimpleTerm) -> anyhow::Result<()> {
-        for (prop, value) in [
-            (rdf::type_, &obo::CHEBI_25367.as_simple() as &dyn InsertIntoGraph),
-            (purl::identifier, &self.chemical_id.as_simple()),
-            (cat::chemicalName, &self.chemical_name.as_simple()),
-            (allores::AFR_0001952, &self.molecular_formula.as_simple()),
-            (allores::AFR_0002295, &self.smiles.as_simple()),
-            (allores::AFR_0002294, &self.molecular_mass),
-            (allores::AFR_0002296, &self.inchi.as_simple()),
-            (cat::casNumber, &self.cas_number.as_ref().clone().map(|s| s.as_simple())),
-            (cat::swissCatNumber, &self.swiss_cat_number.as_ref().clone().map(|s| s.as_simple())),
-            (schema::keywords, &self.keywords.as_ref().clone().map(|s| s.as_simple())),
-            (obo::PATO_0001019, &self.density),
-        ] {
-            value.attach_into(
-                graph,
-                Link { source_iri: iri.clone(), pred: prop.as_simple(), target_iri: None },
-            )?;
+impl Chemical {
+    fn uri(&self, builder: &mut GraphBuilder) -> anyhow::Result<SimpleTerm<'static>> {
+        let key = builder.config().chemical_key.of(self).unwrap_or(&self.chemical_id);
+        builder.generate_iri_term("chemical", &[key])
+    }
+
+    /// Links the chemical to the node of an identical chemical if one was already
+    /// inserted, see [crate::graph::chemical_registry].
+    fn attach(&self, builder: &mut GraphBuilder, attach: Link) -> anyhow::Result<()> {
+        let (iri, known) = match &attach.target_iri {
+            Some(iri) => (iri.into_term(), false),
+            None => find_or_register(builder, self)?,
+        };
+        builder.graph.insert(&attach.source_iri, &attach.pred, &iri)?;
+        attach.insert_position(builder, &iri)?;
+        if known {
+            return Ok(());
         }
+        self.insert_into(builder, iri)
+    }
+}
 
-        Ok(())
+impl FromGraph for Chemical {
+    fn from_graph(graph: &LightGraph, iri: &SimpleTerm) -> anyhow::Result<Self> {
+        let node = Node::new(graph, iri);
+        Ok(Self {
+            chemical_id: node.read(purl::identifier)?,
+            chemical_name: node.read(cat::chemicalName)?,
+            cas_number: node.read_optional(cat::casNumber)?,
+            molecular_mass: node.read(allores::AFR_0002294)?,
+            smiles: node.read(allores::AFR_0002295)?,
+            swiss_cat_number: node.read_optional(cat::swissCatNumber)?,
+            inchi: node.read(allores::AFR_0002296)?,
+            keywords: node.read_optional(schema::keywords)?,
+            molecular_formula: node.read(allores::AFR_0001952)?,
+            density: node.read_optional(obo::PATO_0001019)?,
+        })
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, InsertIntoGraph)]
+#[rdf(type = "cat::ContainerPositionAndQuantity", get_uri = "Self::uri")]
 pub struct ContainerPositionQuantityItem {
     #[serde(rename = "containerID")]
+    #[rdf(pred = "cat::containerID")]
     pub container_id: String,
+    #[rdf(pred = "allores::AFR_0002240")]
     pub position: String,
+    #[rdf(pred = "qudt::quantity")]
     pub quantity: Observation,
 }
 
-impl InsertIntoGraph for ContainerPositionQuantityItem {
-    fn insert_into(&self, graph: &mut LightGraph, iri: SimpleTerm) -> anyhow::Result<()> {
-        for (pred, value) in [
-            (rdf::type_, &cat::ContainerPositionAndQuantity.as_simple() as &dyn InsertIntoGraph),
-            (cat::containerID, &self.container_id.as_simple()),
-            (allores::AFR_0002240, &self.position.as_simple()),
-            (qudt::quantity, &self.quantity),
-        ] {
-            value.attach_into(
-                graph,
-                Link { source_iri: iri.clone(), pred: pred.as_simple(), target_iri: None },
-            )?;
-        }
+impl ContainerPositionQuantityItem {
+    /// The same container position can be filled by several actions, so that the
+    /// quantity is part of the identity of the node.
+    fn uri(&self, builder: &mut GraphBuilder) -> anyhow::Result<SimpleTerm<'static>> {
+        let quantity = self.quantity.key();
+        builder.generate_iri_term(
+            "container-position",
+            &[&self.container_id, &self.position, &quantity],
+        )
+    }
+}
 
-        Ok(())
+impl FromGraph for ContainerPositionQuantityItem {
+    fn from_graph(graph: &LightGraph, iri: &SimpleTerm) -> anyhow::Result<Self> {
+        let node = Node::new(graph, iri);
+        Ok(Self {
+            container_id: node.read(cat::containerID)?,
+            position: node.read(allores::AFR_0002240)?,
+            quantity: node.read(qudt::quantity)?,
+        })
     }
 }
 
@@ -419,23 +634,90 @@ mod tests {
     use sophia_api::term::Term;
 
     use crate::{
-        graph::{graph_builder::GraphBuilder, insert_into::InsertIntoGraph},
+        graph::{
+            graph_builder::{GraphBuilder, GraphConfig},
+            insert_into::InsertIntoGraph,
+            utils::IriStrategy,
+        },
         models::{ErrorMargin, Observation},
     };
 
     #[test]
     fn test_observation_to_triples() -> anyhow::Result<()> {
         let observation = Observation {
-            value: 42.0,
+            value: 42.0.into(),
             unit: Unit::DegC,
-            error_margin: Some(ErrorMargin { value: 0.5, unit: Unit::DegC }),
+            error_margin: Some(ErrorMargin { value: 0.5.into(), unit: Unit::DegC }),
         };
 
         let mut b = GraphBuilder::new();
         let i = IriRef::new_unchecked("http://test.com/my-obersvation");
-        observation.insert_into(&mut b.graph, i.as_simple())?;
+        observation.insert_into(&mut b, i.as_simple())?;
         println!("Graph\n{}", b.serialize_to_turtle().unwrap());
 
         Ok(())
     }
+
+    #[test]
+    fn test_sample_iris_tell_roles_apart() -> anyhow::Result<()> {
+        let sample = |role: &str| -> anyhow::Result<SampleItem> {
+            Ok(serde_json::from_value(serde_json::json!({
+                "sampleID": "24",
+                "role": role,
+                "internalBarCode": "1",
+                "physicalState": "Liquid",
+                "measuredQuantity": {"value": 2, "unit": "mg"},
+                "hasChemical": {
+                    "chemicalID": "135",
+                    "chemicalName": "Styrene",
+                    "molecularMass": {"value": 104.15, "unit": "g/mol"},
+                    "smiles": "C=CC1=CC=CC=C1",
+                    "Inchi": "1S/C8H8/c1-2-8-6-4-3-5-7-8/h2-7H,1H2",
+                    "molecularFormula": "C8H8",
+                },
+            }))?)
+        };
+        let config = GraphConfig {
+            iri_strategy: IriStrategy::content_derived("http://example.org/")?,
+            ..Default::default()
+        };
+        let mut b = GraphBuilder::with_config(config);
+
+        let reagent = sample("reagent")?.get_uri(&mut b)?;
+        assert_eq!(reagent, sample("reagent")?.get_uri(&mut b)?);
+        assert_ne!(reagent, sample("solvent")?.get_uri(&mut b)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_instrument_units_are_not_input_units() {
+        let observation = |unit: &str| {
+            serde_json::from_value::<Observation>(serde_json::json!({"value": 1, "unit": unit}))
+        };
+        assert!(observation("µL").is_ok() && observation("uL").is_ok());
+        assert!(observation("%").is_err() && observation("1").is_err());
+        assert!(matches!(Unit::from_label("(unitless)"), Some(Unit::Unitless)));
+        assert!(matches!(Unit::from_label("%"), Some(Unit::Percent)));
+    }
+
+    #[test]
+    fn test_merge_batches() -> anyhow::Result<()> {
+        let mut batch: Batch = serde_json::from_str(
+            r#"{"batchID": "23", "batchName": "20240516", "reactionType": "N-N coupling"}"#,
+        )?;
+        let synth: Batch = serde_json::from_str(
+            r#"{"batchID": "23", "batchName": "20240517", "reactionName": "Caffeine synthesis",
+                "Actions": []}"#,
+        )?;
+
+        let conflicts = batch.merge(synth)?;
+        assert_eq!(conflicts, vec!["batchName is '20240516', ignoring '20240517'"]);
+        assert_eq!(batch.batch_name.as_deref(), Some("20240516"));
+        assert_eq!(batch.reaction_name.as_deref(), Some("Caffeine synthesis"));
+        assert_eq!(batch.actions.as_ref().map(Vec::len), Some(0));
+
+        let other: Batch = serde_json::from_str(r#"{"batchID": "24"}"#)?;
+        assert!(batch.merge(other).is_err());
+        Ok(())
+    }
 }
@@ -0,0 +1,391 @@
+// Reader for Agilent ChemStation / OpenLab CDS CSV exports.
+//
+// The export starts with `key,value[,unit]` metadata lines, followed by sections
+// introduced by a bracketed header:
+//
+//   Batch ID,23
+//   Method,HPLC_gradient.M
+//   Injection Volume,5,µL
+//
+//   [Signal] DAD1 A, Sig=254,4 Ref=360,100
+//   Time (min),Signal (mAU)
+//   0.000,0.12
+//
+//   [Peaks] DAD1 A, Sig=254,4 Ref=360,100
+//   Peak,RetTime (min),Type,Width (min),Area,Height,Area%
+//   1,1.234,BB,0.05,123.4,45.6,12.3
+//
+// Peaks are matched to the signal with the same name. The cells of the signals and of the
+// peak tables are numbers, except for the textual peak columns (e.g. the peak `Type`),
+// which are kept as strings in the attributes of the peak array.
+use crate::{
+    models::{
+        chromatography::{Chromatogram, ChromatographyRun},
+        dataset::Dataset,
+        enums::Unit,
+        types::{Batch, Observation},
+    },
+    zarr::zarr_array::{ArrayConfig, ArrayValues, Compression, NdArray},
+};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, NaiveDateTime};
+use serde_json::{json, Map, Value};
+use std::str::FromStr;
+
+/// Number of points per chunk of a signal trace.
+const TRACE_CHUNK_SIZE: u64 = 16384;
+
+/// The columns of the peak tables holding text, e.g. the peak type `BB`.
+const TEXT_PEAK_COLUMNS: [&str; 3] = ["type", "name", "compound"];
+
+impl FromStr for ChromatographyRun {
+    type Err = anyhow::Error;
+
+    fn from_str(content: &str) -> Result<Self> {
+        let mut run = ChromatographyRun {
+            has_batch: None,
+            sample_name: None,
+            method_name: None,
+            instrument_name: None,
+            injection_volume: None,
+            column: None,
+            acquisition_time: None,
+            has_chromatogram: Vec::new(),
+        };
+        let mut signals: Vec<Section> = Vec::new();
+        let mut peaks: Vec<Section> = Vec::new();
+        let mut current: Option<Section> = None;
+
+        let content = content.trim_start_matches('\u{feff}');
+        for (number, line) in content.lines().enumerate().map(|(i, l)| (i + 1, l.trim())) {
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(header) = line.strip_prefix('[') {
+                let Some((kind, name)) = header.split_once(']') else {
+                    bail!("Line {}: unterminated section header '{}'", number, line);
+                };
+                let section = Section::new(kind.trim(), name.trim());
+                if let Some(done) = current.replace(section) {
+                    push_section(done, &mut signals, &mut peaks)?;
+                }
+                continue;
+            }
+
+            let fields = split_csv_line(line);
+            match current.as_mut() {
+                Some(section) if section.columns.is_empty() => section.columns = fields,
+                Some(section) => section.rows.push((number, fields)),
+                None => read_metadata(&mut run, &fields)
+                    .with_context(|| format!("Line {}: invalid metadata", number))?,
+            }
+        }
+        if let Some(done) = current {
+            push_section(done, &mut signals, &mut peaks)?;
+        }
+
+        if signals.is_empty() && peaks.is_empty() {
+            bail!("No [Signal] or [Peaks] section found in the Agilent export");
+        }
+        for signal in &signals {
+            let matching = peaks.iter().position(|p| p.name == signal.name);
+            let peak_table = matching.map(|i| peaks.remove(i));
+            let index = run.has_chromatogram.len();
+            run.has_chromatogram.push(chromatogram(index, Some(signal), peak_table.as_ref())?);
+        }
+        for peak_table in &peaks {
+            let index = run.has_chromatogram.len();
+            run.has_chromatogram.push(chromatogram(index, None, Some(peak_table))?);
+        }
+
+        Ok(run)
+    }
+}
+
+/// A bracketed section of the export: a header line followed by rows of cells, with the
+/// number of their line.
+struct Section {
+    kind: String,
+    name: String,
+    columns: Vec<String>,
+    rows: Vec<(usize, Vec<String>)>,
+}
+
+impl Section {
+    fn new(kind: &str, name: &str) -> Self {
+        Self { kind: kind.to_string(), name: name.to_string(), columns: vec![], rows: vec![] }
+    }
+
+    /// Values of the `columns` of the rows, flattened in row-major order.
+    ///
+    /// # Returns
+    /// The values, or an error if a row has another number of cells than the header or
+    /// a cell is not a number.
+    fn values(&self, columns: &[usize]) -> Result<Vec<f64>> {
+        let width = self.columns.len();
+        let mut values = Vec::with_capacity(self.rows.len() * columns.len());
+        for (number, row) in &self.rows {
+            if row.len() != width {
+                bail!(
+                    "Line {}: section [{}] {} has {} columns, found a row with {}",
+                    number,
+                    self.kind,
+                    self.name,
+                    width,
+                    row.len()
+                );
+            }
+            for &column in columns {
+                values.push(parse_cell(&row[column]).with_context(|| {
+                    format!("Line {}: invalid '{}' value", number, self.columns[column])
+                })?);
+            }
+        }
+        Ok(values)
+    }
+
+    /// Cells of the `column` of the rows.
+    fn texts(&self, column: usize) -> Vec<String> {
+        self.rows.iter().map(|(_, row)| row.get(column).cloned().unwrap_or_default()).collect()
+    }
+}
+
+fn push_section(
+    section: Section,
+    signals: &mut Vec<Section>,
+    peaks: &mut Vec<Section>,
+) -> Result<()> {
+    match section.kind.to_lowercase().as_str() {
+        "signal" => signals.push(section),
+        "peaks" => peaks.push(section),
+        other => bail!("Unknown section [{}] in the Agilent export", other),
+    }
+    Ok(())
+}
+
+fn read_metadata(run: &mut ChromatographyRun, fields: &[String]) -> Result<()> {
+    let Some((key, values)) = fields.split_first() else {
+        return Ok(());
+    };
+    let Some(value) = values.first().filter(|v| !v.is_empty()).cloned() else {
+        return Ok(());
+    };
+    match key.to_lowercase().as_str() {
+        "batch id" => {
+            run.has_batch = Some(Batch {
+                batch_id: value,
+                actions: None,
+                batch_name: None,
+                reaction_type: None,
+                reaction_name: None,
+                optimization_type: None,
+                link: None,
+            })
+        }
+        "sample name" => run.sample_name = Some(value),
+        "method" | "method name" | "acq. method" => run.method_name = Some(value),
+        "instrument" | "instrument name" => run.instrument_name = Some(value),
+        "column" | "column name" => run.column = Some(value),
+        "injection volume" | "inj. volume" | "inj volume" => {
+            let unit = values.get(1).map(String::as_str).unwrap_or("µL");
+            run.injection_volume = Some(Observation {
+                value: value.parse().context("Injection volume is not a number")?,
+                unit: serde_json::from_value::<Unit>(json!(unit))
+                    .with_context(|| format!("Unsupported injection volume unit '{}'", unit))?,
+                error_margin: None,
+            })
+        }
+        "acquired" | "injection date" | "acquisition date" => {
+            run.acquisition_time = Some(to_datetime(&values.join(","))?)
+        }
+        // Other metadata (operator, vial, ...) is not mapped to the ontology.
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Builds the chromatogram of a signal and/or peak table, stored under `chromatograms/<index>`.
+fn chromatogram(
+    index: usize,
+    signal: Option<&Section>,
+    peaks: Option<&Section>,
+) -> Result<Chromatogram> {
+    let name = signal.or(peaks).map(|s| s.name.clone()).unwrap_or_default();
+    let mut has_dataset = Vec::new();
+
+    if let Some(signal) = signal {
+        if signal.columns.len() != 2 {
+            bail!("Signal '{}' must have exactly two columns (time, signal)", signal.name);
+        }
+        let values = signal.values(&[0, 1])?;
+        let chunk_size = (signal.rows.len() as u64).clamp(1, TRACE_CHUNK_SIZE);
+        for (column, dataset_name) in [(0, "time"), (1, "intensity")] {
+            let array = NdArray::vector(ArrayValues::Float64(
+                values.iter().skip(column).step_by(2).copied().collect(),
+            ));
+            let mut config = ArrayConfig::default()
+                .with_chunk_shape(vec![chunk_size])
+                .with_compression(Compression::Gzip { level: 5 })
+                .with_dimension_names(vec!["time".to_string()])
+                .with_attribute("long_name", json!(signal.columns[column]));
+            if let Some(unit) = unit_of(&signal.columns[column]) {
+                config = config.with_attribute("units", json!(unit));
+            }
+            let path = format!("chromatograms/{}/{}", index, dataset_name);
+            has_dataset.push(Dataset::new(path, array, config));
+        }
+    }
+
+    if let Some(peaks) = peaks {
+        let (text, numeric): (Vec<usize>, Vec<usize>) = (0..peaks.columns.len())
+            .partition(|&i| TEXT_PEAK_COLUMNS.contains(&peaks.columns[i].to_lowercase().as_str()));
+        let array = NdArray::new(
+            vec![peaks.rows.len() as u64, numeric.len() as u64],
+            ArrayValues::Float64(peaks.values(&numeric)?),
+        )?;
+        let text_columns: Map<String, Value> =
+            text.iter().map(|&i| (peaks.columns[i].clone(), json!(peaks.texts(i)))).collect();
+        let config = ArrayConfig::default()
+            .with_compression(Compression::Gzip { level: 5 })
+            .with_dimension_names(vec!["peak".to_string(), "column".to_string()])
+            .with_attribute(
+                "columns",
+                json!(numeric.iter().map(|&i| &peaks.columns[i]).collect::<Vec<_>>()),
+            )
+            .with_attribute("text_columns", Value::Object(text_columns));
+        let path = format!("chromatograms/{}/peaks", index);
+        has_dataset.push(Dataset::new(path, array, config));
+    }
+
+    Ok(Chromatogram { signal_name: name, has_dataset })
+}
+
+/// Extracts the unit of a column header such as `Time (min)`.
+fn unit_of(column: &str) -> Option<&str> {
+    let (_, rest) = column.rsplit_once('(')?;
+    rest.strip_suffix(')').map(str::trim)
+}
+
+/// Parses a numeric cell; an empty cell is a missing value (`NaN`).
+fn parse_cell(cell: &str) -> Result<f64> {
+    if cell.is_empty() {
+        return Ok(f64::NAN);
+    }
+    cell.parse().ok().with_context(|| format!("'{}' is not a number", cell))
+}
+
+/// The formats of the ChemStation timestamps, e.g. `25-Jul-24, 14:00:00` or
+/// `25-Jul-2024, 2:00:00 PM` (the comma being optional).
+const CHEMSTATION_FORMATS: [&str; 4] =
+    ["%d-%b-%y %H:%M:%S", "%d-%b-%y %I:%M:%S %p", "%d-%b-%Y %H:%M:%S", "%d-%b-%Y %I:%M:%S %p"];
+
+/// Converts ISO 8601 (`2024-07-25T14:00:00`, with an optional offset) or ChemStation
+/// (`25-Jul-24, 14:00:00`) timestamps to an `xsd:dateTime` lexical value.
+fn to_datetime(value: &str) -> Result<String> {
+    let value = value.trim();
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        return Ok(datetime.to_rfc3339());
+    }
+    if let Ok(datetime) = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f") {
+        return Ok(datetime.format("%Y-%m-%dT%H:%M:%S%.f").to_string());
+    }
+    let chemstation = value.replacen(',', " ", 1).split_whitespace().collect::<Vec<_>>().join(" ");
+    CHEMSTATION_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(&chemstation, format).ok())
+        .map(|datetime| datetime.format("%Y-%m-%dT%H:%M:%S").to_string())
+        .with_context(|| format!("Unsupported acquisition date '{}'", value))
+}
+
+/// Splits a CSV line, honouring double-quoted fields.
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field).trim().to_string()),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_agilent_export() -> Result<()> {
+        let content = "\
+Batch ID,23
+Sample Name,\"Vial 1, crude\"
+Injection Volume,5,µL
+Acquired,\"25-Jul-24, 14:00:00\"
+
+[Signal] DAD1 A
+Time (min),Signal (mAU)
+0.0,0.1
+0.5,2.5
+1.0,0.3
+
+[Peaks] DAD1 A
+Peak,RetTime (min),Type,Area
+1,0.5,BB,12.5
+";
+        let run: ChromatographyRun = content.parse()?;
+
+        assert_eq!(run.has_batch.as_ref().map(|b| b.batch_id.as_str()), Some("23"));
+        assert_eq!(run.sample_name.as_deref(), Some("Vial 1, crude"));
+        assert_eq!(run.injection_volume.as_ref().map(|v| v.value.as_f64()), Some(5.0));
+        assert_eq!(run.acquisition_time.as_deref(), Some("2024-07-25T14:00:00"));
+
+        let chromatogram = &run.has_chromatogram[0];
+        assert_eq!(chromatogram.signal_name, "DAD1 A");
+        let paths: Vec<&str> = chromatogram.has_dataset.iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            ["chromatograms/0/time", "chromatograms/0/intensity", "chromatograms/0/peaks"]
+        );
+        assert_eq!(
+            chromatogram.has_dataset[1].array.values,
+            ArrayValues::Float64(vec![0.1, 2.5, 0.3])
+        );
+        let peak_table = &chromatogram.has_dataset[2];
+        assert_eq!(peak_table.array.shape, vec![1, 3]);
+        assert_eq!(peak_table.array.values, ArrayValues::Float64(vec![1.0, 0.5, 12.5]));
+        assert_eq!(peak_table.config.attributes["text_columns"], json!({"Type": ["BB"]}));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_agilent_errors() {
+        let signal = "[Signal] DAD1 A\nTime (min),Signal (mAU)\n0.0,0.1\n0.5,#REF!\n";
+        let error = signal.parse::<ChromatographyRun>().unwrap_err();
+        assert_eq!(
+            format!("{:#}", error),
+            "Line 4: invalid 'Signal (mAU)' value: '#REF!' is not a number"
+        );
+        let peaks = "[Peaks] DAD1 A\nPeak,RetTime (min),Area\n1,BB,12.5\n";
+        assert!(peaks.parse::<ChromatographyRun>().is_err());
+    }
+
+    #[test]
+    fn test_to_datetime() -> Result<()> {
+        assert_eq!(to_datetime("2024-07-25T14:00:00")?, "2024-07-25T14:00:00");
+        assert_eq!(to_datetime("2024-07-25T14:00:00+02:00")?, "2024-07-25T14:00:00+02:00");
+        assert_eq!(to_datetime("25-Jul-24, 14:00:00")?, "2024-07-25T14:00:00");
+        assert_eq!(to_datetime("25-Jul-2024, 2:00:00 PM")?, "2024-07-25T14:00:00");
+        assert!(to_datetime("2024-07-25T25:00:00").is_err());
+        assert!(to_datetime("yesterday").is_err());
+        Ok(())
+    }
+}
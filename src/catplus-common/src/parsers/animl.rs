@@ -0,0 +1,471 @@
+// Reader for AnIML (Analytical Information Markup Language) documents.
+//
+// Each `ExperimentStep` becomes a measure action of the batch, carrying the `Sample`s it
+// references, its numeric `Parameter`s (with a `Unit`) as observations, and the values of
+// its `Series` as datasets:
+//
+//   <AnIML>
+//     <SampleSet><Sample name="Vial 1" sampleID="S1" containerID="C1" barcode="B1"/></SampleSet>
+//     <ExperimentStepSet>
+//       <ExperimentStep name="UV-Vis" experimentStepID="E1">
+//         <TagSet><Tag name="batchID" value="23"/></TagSet>
+//         <Technique name="UV/Vis" uri="..."/>
+//         <Infrastructure>
+//           <SampleReferenceSet><SampleReference sampleID="S1" role="analyte"/></SampleReferenceSet>
+//           <Timestamp>2024-07-25T14:00:00</Timestamp>
+//         </Infrastructure>
+//         <Result name="Spectrum">
+//           <SeriesSet name="Spectrum" length="3">
+//             <Series name="Wavelength" seriesID="x" dependency="independent" seriesType="Float64">
+//               <AutoIncrementedValueSet>...</AutoIncrementedValueSet>
+//               <Unit label="nm"/>
+//             </Series>
+//           </SeriesSet>
+//         </Result>
+//       </ExperimentStep>
+//     </ExperimentStepSet>
+//   </AnIML>
+//
+// Elements are matched by their local name, so that documents of any AnIML schema
+// version are accepted. The batch is taken from the first `batchID` tag of the document.
+//
+// See: https://www.animl.org
+use crate::{
+    graph::{graph_builder::GraphBuilder, insert_into::InsertIntoGraph},
+    models::{
+        dataset::Dataset,
+        enums::{ActionName, Unit},
+        measurement::{MeasuredSample, Measurement, MeasurementBatch, Parameter},
+        types::{ContainerInfo, Observation},
+    },
+    parsers::xml::{child, child_text, children, decode_le, is_batch_id_name, LeNumber},
+    zarr::{
+        insert_into_store::InsertIntoStore,
+        zarr_array::{ArrayConfig, ArrayValues, Compression, NdArray},
+        zarr_store::ZarrStore,
+    },
+};
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use roxmltree::{Document, Node};
+use serde_json::json;
+use sophia_api::term::SimpleTerm;
+use std::{collections::HashMap, str::FromStr};
+
+/// A batch whose measurements were read from an AnIML document.
+pub struct AnimlDocument {
+    pub batch: MeasurementBatch,
+}
+
+impl InsertIntoGraph for AnimlDocument {
+    fn insert_into(&self, builder: &mut GraphBuilder, iri: SimpleTerm) -> Result<()> {
+        self.batch.insert_into(builder, iri)
+    }
+}
+
+impl InsertIntoStore for AnimlDocument {
+    fn insert_into_store(&mut self, store: &ZarrStore) -> Result<()> {
+        self.batch.insert_into_store(store)
+    }
+}
+
+impl FromStr for AnimlDocument {
+    type Err = anyhow::Error;
+
+    fn from_str(content: &str) -> Result<Self> {
+        let document = Document::parse(content).context("Invalid AnIML XML document")?;
+        let root = document.root_element();
+        if root.tag_name().name() != "AnIML" {
+            bail!("Expected an <AnIML> root element, found <{}>", root.tag_name().name());
+        }
+
+        let batch_id = root
+            .descendants()
+            .filter(|n| n.has_tag_name("Tag"))
+            .find(|n| n.attribute("name").is_some_and(is_batch_id_name))
+            .and_then(|n| n.attribute("value"))
+            .context("No batchID tag found in the AnIML document")?;
+
+        let samples: HashMap<&str, Node> = children(root, "SampleSet")
+            .flat_map(|set| children(set, "Sample"))
+            .filter_map(|sample| sample.attribute("sampleID").map(|id| (id, sample)))
+            .collect();
+
+        let mut batch = MeasurementBatch::new(batch_id.to_string());
+        batch.measurements = children(root, "ExperimentStepSet")
+            .flat_map(|set| children(set, "ExperimentStep"))
+            .enumerate()
+            .map(|(index, step)| measurement(index, step, &samples))
+            .collect::<Result<Vec<_>>>()?;
+        if batch.measurements.is_empty() {
+            bail!("No ExperimentStep found in the AnIML document");
+        }
+
+        Ok(AnimlDocument { batch })
+    }
+}
+
+/// Builds the measurement of the `index`-th experiment step, with its series stored under
+/// `experiment_steps/<index>/series/<n>`.
+fn measurement(index: usize, step: Node, samples: &HashMap<&str, Node>) -> Result<Measurement> {
+    let step_name = step.attribute("name").unwrap_or_default();
+    let infrastructure = child(step, "Infrastructure");
+    let start_time = infrastructure
+        .and_then(|i| child_text(i, "Timestamp"))
+        .with_context(|| format!("ExperimentStep '{}' has no Timestamp", step_name))?;
+    let method = child(step, "Method");
+    let technique = child(step, "Technique").and_then(|t| t.attribute("name"));
+    let method_name = method
+        .and_then(|m| m.attribute("name"))
+        .or(technique)
+        .with_context(|| format!("ExperimentStep '{}' has no Method or Technique", step_name))?;
+
+    let has_sample = infrastructure
+        .into_iter()
+        .flat_map(|i| children(i, "SampleReferenceSet"))
+        .flat_map(|set| children(set, "SampleReference"))
+        .map(|reference| sample(reference, samples))
+        .collect::<Result<Vec<_>>>()
+        .with_context(|| format!("Invalid SampleReference in ExperimentStep '{}'", step_name))?;
+
+    let has_parameter =
+        step.descendants().filter(|n| n.has_tag_name("Parameter")).filter_map(parameter).collect();
+
+    let mut has_dataset = Vec::new();
+    for set in step.descendants().filter(|n| n.has_tag_name("SeriesSet")) {
+        for series in children(set, "Series") {
+            let name = series.attribute("name").unwrap_or_default();
+            let values = series_values(set, series)
+                .with_context(|| format!("Invalid values in series '{}'", name))?;
+            if let Some(values) = values {
+                let path = format!("experiment_steps/{}/series/{}", index, has_dataset.len());
+                has_dataset.push(dataset(path, series, values));
+            }
+        }
+    }
+
+    Ok(Measurement {
+        action_name: ActionName::measureAction,
+        start_time: start_time.to_string(),
+        method_name: method_name.to_string(),
+        equipment_name: method
+            .and_then(|m| child(m, "Device"))
+            .and_then(|d| child_text(d, "Name"))
+            .map(str::to_string),
+        has_sample,
+        has_parameter,
+        has_dataset,
+    })
+}
+
+fn sample(reference: Node, samples: &HashMap<&str, Node>) -> Result<MeasuredSample> {
+    let sample_id = reference.attribute("sampleID").context("SampleReference without sampleID")?;
+    let role = reference
+        .attribute("role")
+        .with_context(|| format!("SampleReference to '{}' without role", sample_id))?;
+    let sample = samples
+        .get(sample_id)
+        .with_context(|| format!("SampleReference to unknown sample '{}'", sample_id))?;
+    let container = match (sample.attribute("containerID"), sample.attribute("barcode")) {
+        (Some(container_id), Some(barcode)) => Some(ContainerInfo {
+            container_id: container_id.to_string(),
+            container_barcode: barcode.to_string(),
+        }),
+        _ => None,
+    };
+    Ok(MeasuredSample {
+        container,
+        vial_id: sample_id.to_string(),
+        vial_type: sample.attribute("containerType").map(str::to_string),
+        role: Some(role.to_string()),
+    })
+}
+
+/// Maps a numeric parameter with a known unit to a [Parameter]; other parameters are
+/// not represented in the ontology and are skipped.
+fn parameter(node: Node) -> Option<Parameter> {
+    let name = node.attribute("name")?;
+    let value = node
+        .children()
+        .find(|n| ["F", "D", "I", "L"].iter().any(|tag| n.has_tag_name(*tag)))?
+        .text()?
+        .trim()
+        .parse()
+        .ok()?;
+    let unit = unit_of(child(node, "Unit")?)?;
+    Some(Parameter {
+        name: name.to_string(),
+        observation: Observation { value, unit, error_margin: None },
+    })
+}
+
+/// Maps the label of a `Unit` element (e.g. `°C`, `nm`) to a QUDT unit.
+fn unit_of(node: Node) -> Option<Unit> {
+    Unit::from_label(node.attribute("label")?)
+}
+
+fn dataset(path: String, series: Node, values: ArrayValues) -> Dataset {
+    let mut config = ArrayConfig::default()
+        .with_chunk_shape(vec![(values.len() as u64).max(1)])
+        .with_compression(Compression::Gzip { level: 5 })
+        .with_dimension_names(vec!["point".to_string()]);
+    for (attribute, key) in
+        [("name", "long_name"), ("seriesID", "series_id"), ("dependency", "dependency")]
+    {
+        if let Some(value) = series.attribute(attribute) {
+            config = config.with_attribute(key, json!(value));
+        }
+    }
+    if let Some(label) = child(series, "Unit").and_then(|u| u.attribute("label")) {
+        config = config.with_attribute("units", json!(label));
+    }
+    Dataset::new(path, NdArray::vector(values), config)
+}
+
+/// Reads the values of a numeric series, or `None` for non-numeric series (strings,
+/// booleans, embedded images...), which are not written to the store. The values are read
+/// as the type of the series, so that 64-bit integers are kept exactly.
+fn series_values(set: Node, series: Node) -> Result<Option<ArrayValues>> {
+    Ok(Some(match series.attribute("seriesType").unwrap_or("Float64") {
+        "Int32" => ArrayValues::Int32(read_series(set, series)?),
+        "Int64" => ArrayValues::Int64(read_series(set, series)?),
+        "Float32" => ArrayValues::Float32(read_series(set, series)?),
+        "Float64" => ArrayValues::Float64(read_series(set, series)?),
+        _ => return Ok(None),
+    }))
+}
+
+/// The types of the values of a numeric series.
+trait SeriesValue: LeNumber + FromStr<Err: std::error::Error + Send + Sync + 'static> {
+    /// The `index`-th value of an auto-incremented set, or `None` if it overflows.
+    fn nth(start: Self, increment: Self, index: usize) -> Option<Self>;
+}
+
+macro_rules! integer_series_value {
+    ($($type:ty),*) => {$(
+        impl SeriesValue for $type {
+            fn nth(start: Self, increment: Self, index: usize) -> Option<Self> {
+                increment.checked_mul(index.try_into().ok()?)?.checked_add(start)
+            }
+        }
+    )*};
+}
+
+macro_rules! float_series_value {
+    ($($type:ty),*) => {$(
+        impl SeriesValue for $type {
+            fn nth(start: Self, increment: Self, index: usize) -> Option<Self> {
+                Some((start as f64 + index as f64 * increment as f64) as $type)
+            }
+        }
+    )*};
+}
+
+integer_series_value!(i32, i64);
+float_series_value!(f32, f64);
+
+/// Reads the values of the value sets of `series`, in the order of the document.
+fn read_series<T: SeriesValue>(set: Node, series: Node) -> Result<Vec<T>> {
+    let mut values: Vec<T> = Vec::new();
+    for value_set in series.children().filter(Node::is_element) {
+        match value_set.tag_name().name() {
+            "IndividualValueSet" => {
+                for value in value_set.children().filter(Node::is_element) {
+                    let text = value.text().unwrap_or_default().trim();
+                    values.push(text.parse().with_context(|| format!("Invalid value '{}'", text))?);
+                }
+            }
+            "AutoIncrementedValueSet" => {
+                let length: usize = match value_set.attribute("endIndex") {
+                    Some(end) => {
+                        let start = value_set.attribute("startIndex").unwrap_or("0");
+                        (end.parse::<usize>()? + 1).checked_sub(start.parse()?).with_context(
+                            || format!("endIndex {} is before startIndex {}", end, start),
+                        )?
+                    }
+                    None => set
+                        .attribute("length")
+                        .context("AutoIncrementedValueSet without a length")?
+                        .parse()?,
+                };
+                let number = |name: &'static str| -> Result<T> {
+                    let node = child(value_set, name)
+                        .with_context(|| format!("AutoIncrementedValueSet without {}", name))?;
+                    let text = node
+                        .children()
+                        .find(Node::is_element)
+                        .and_then(|n| n.text())
+                        .unwrap_or_default()
+                        .trim();
+                    text.parse().with_context(|| format!("Invalid {} '{}'", name, text))
+                };
+                let (start, increment) = (number("StartValue")?, number("Increment")?);
+                for index in 0..length {
+                    values.push(
+                        T::nth(start, increment, index)
+                            .context("AutoIncrementedValueSet overflows its type")?,
+                    );
+                }
+            }
+            "EncodedValueSet" => {
+                let text: String =
+                    value_set.text().unwrap_or_default().split_whitespace().collect();
+                let bytes = STANDARD.decode(text).context("Invalid base64 data")?;
+                values.extend(decode_le::<T>(&bytes)?);
+            }
+            _ => {}
+        }
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_animl_document() -> Result<()> {
+        let encoded = STANDARD
+            .encode([0.5f32, 0.25].iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<_>>());
+        let content = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<AnIML xmlns="urn:org:astm:animl:schema:core:draft:0.90" version="0.90">
+  <SampleSet>
+    <Sample name="Vial 1" sampleID="S1" containerID="C1" barcode="B1" containerType="vial"/>
+  </SampleSet>
+  <ExperimentStepSet>
+    <ExperimentStep name="UV-Vis" experimentStepID="E1">
+      <TagSet><Tag name="batchID" value="23"/></TagSet>
+      <Technique name="UV/Vis" uri="https://example.org/uv-vis.atdd"/>
+      <Infrastructure>
+        <SampleReferenceSet><SampleReference sampleID="S1" role="analyte"/></SampleReferenceSet>
+        <Timestamp>2024-07-25T14:00:00</Timestamp>
+      </Infrastructure>
+      <Method name="UV scan"><Device><Name>Cary 60</Name></Device></Method>
+      <Result name="Spectrum">
+        <SeriesSet name="Spectrum" length="2">
+          <Series name="Wavelength" seriesID="x" dependency="independent" seriesType="Float64">
+            <AutoIncrementedValueSet>
+              <StartValue><D>200</D></StartValue><Increment><D>0.5</D></Increment>
+            </AutoIncrementedValueSet>
+            <Unit label="nm"/>
+          </Series>
+          <Series name="Absorbance" seriesID="y" dependency="dependent" seriesType="Float32">
+            <EncodedValueSet>{}</EncodedValueSet>
+          </Series>
+          <Series name="Flags" seriesID="f" dependency="dependent" seriesType="String">
+            <IndividualValueSet><S>ok</S><S>ok</S></IndividualValueSet>
+          </Series>
+        </SeriesSet>
+        <Category name="Conditions">
+          <Parameter name="Temperature" parameterType="Float64"><D>25</D><Unit label="°C"/></Parameter>
+          <Parameter name="Operator" parameterType="String"><S>J. Doe</S></Parameter>
+        </Category>
+      </Result>
+    </ExperimentStep>
+  </ExperimentStepSet>
+</AnIML>"#,
+            encoded
+        );
+        let document: AnimlDocument = content.parse()?;
+
+        assert_eq!(document.batch.batch.batch_id, "23");
+        let action = &document.batch.measurements[0];
+        assert_eq!(action.method_name, "UV scan");
+        assert_eq!(action.equipment_name.as_deref(), Some("Cary 60"));
+        let sample = &action.has_sample[0];
+        assert_eq!((sample.vial_id.as_str(), sample.role.as_deref()), ("S1", Some("analyte")));
+        assert_eq!(action.has_parameter.len(), 1);
+        assert_eq!(action.has_parameter[0].observation.value.as_f64(), 25.0);
+
+        let paths: Vec<&str> = action.has_dataset.iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(paths, ["experiment_steps/0/series/0", "experiment_steps/0/series/1"]);
+        assert_eq!(action.has_dataset[0].array.values, ArrayValues::Float64(vec![200.0, 200.5]));
+        assert_eq!(action.has_dataset[1].array.values, ArrayValues::Float32(vec![0.5, 0.25]));
+        Ok(())
+    }
+
+    fn step(references: &str, series: &str) -> String {
+        format!(
+            r#"<AnIML>
+  <SampleSet><Sample sampleID="S1"/><Sample sampleID="S2"/></SampleSet>
+  <ExperimentStepSet>
+    <ExperimentStep name="UV-Vis">
+      <TagSet><Tag name="batchID" value="23"/></TagSet>
+      <Technique name="UV/Vis"/>
+      <Infrastructure>
+        <SampleReferenceSet>{}</SampleReferenceSet>
+        <Timestamp>2024-07-25T14:00:00</Timestamp>
+      </Infrastructure>
+      <Result><SeriesSet name="Spectrum" length="2">{}</SeriesSet></Result>
+    </ExperimentStep>
+  </ExperimentStepSet>
+</AnIML>"#,
+            references, series
+        )
+    }
+
+    #[test]
+    fn test_parse_animl_samples_and_value_sets() -> Result<()> {
+        let encoded = STANDARD.encode(3.0f64.to_le_bytes());
+        let content = step(
+            r#"<SampleReference sampleID="S1" role="analyte"/>
+               <SampleReference sampleID="S2" role="blank"/>"#,
+            &format!(
+                r#"<Series name="y" seriesType="Float64">
+                     <IndividualValueSet><D>1</D><D>2</D></IndividualValueSet>
+                     <EncodedValueSet>{}</EncodedValueSet>
+                   </Series>"#,
+                encoded
+            ),
+        );
+        let document: AnimlDocument = content.parse()?;
+
+        let action = &document.batch.measurements[0];
+        let samples: Vec<&str> = action.has_sample.iter().map(|s| s.vial_id.as_str()).collect();
+        assert_eq!(samples, ["S1", "S2"]);
+        assert_eq!(action.has_dataset[0].array.values, ArrayValues::Float64(vec![1.0, 2.0, 3.0]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_animl_int64_series() -> Result<()> {
+        let large = (1i64 << 53) + 1;
+        let encoded = STANDARD.encode(large.to_le_bytes());
+        let content = step(
+            r#"<SampleReference sampleID="S1" role="analyte"/>"#,
+            &format!(
+                r#"<Series name="counts" seriesType="Int64">
+                     <IndividualValueSet><L>{}</L></IndividualValueSet>
+                     <EncodedValueSet>{}</EncodedValueSet>
+                     <AutoIncrementedValueSet startIndex="0" endIndex="1">
+                       <StartValue><L>{}</L></StartValue><Increment><L>2</L></Increment>
+                     </AutoIncrementedValueSet>
+                   </Series>"#,
+                large, encoded, large
+            ),
+        );
+        let document: AnimlDocument = content.parse()?;
+
+        let values = &document.batch.measurements[0].has_dataset[0].array.values;
+        assert_eq!(*values, ArrayValues::Int64(vec![large, large, large, large + 2]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_animl_errors() {
+        let reference = r#"<SampleReference sampleID="S1" role="analyte"/>"#;
+        let auto_incremented = r#"<Series name="x" seriesType="Float64">
+              <AutoIncrementedValueSet startIndex="5" endIndex="2">
+                <StartValue><D>0</D></StartValue><Increment><D>1</D></Increment>
+              </AutoIncrementedValueSet>
+            </Series>"#;
+        for (content, message) in [
+            (step(r#"<SampleReference sampleID="S1"/>"#, ""), "without role"),
+            (step(reference, auto_incremented), "endIndex 2 is before startIndex 5"),
+        ] {
+            let error = content.parse::<AnimlDocument>().err().unwrap();
+            assert!(format!("{:#}", error).contains(message), "{:#}", error);
+        }
+    }
+}
@@ -0,0 +1,383 @@
+// Reader for Allotrope Simple Model (ASM) JSON documents, e.g. the liquid chromatography,
+// plate reader or balance manifests.
+//
+// An ASM document holds a single `<technique> aggregate document`, which lists the
+// technique documents, each with a `measurement aggregate document`:
+//
+//   {
+//     "$asm.manifest": "http://purl.allotrope.org/manifests/plate-reader/...",
+//     "plate reader aggregate document": {
+//       "device system document": {"model number": "Synergy H1"},
+//       "plate reader document": [{
+//         "measurement aggregate document": {
+//           "measurement time": "2024-07-25T14:00:00",
+//           "measurement document": [{
+//             "sample document": {"sample identifier": "24", "batch identifier": "23"},
+//             "absorbance": {"value": 0.5, "unit": "(unitless)"}
+//           }]
+//         }
+//       }]
+//     }
+//   }
+//
+// Each measurement document becomes a measure action of the batch given by its sample
+// document. Its quantity values (`{"value": ..., "unit": ...}`) with a known unit become
+// parameters, and its data cubes are written to the store. Metadata that is not found
+// on the measurement document is looked up on the enclosing documents.
+use crate::{
+    graph::{graph_builder::GraphBuilder, insert_into::InsertIntoGraph},
+    models::{
+        dataset::Dataset,
+        enums::{ActionName, Number, Unit},
+        measurement::{MeasuredSample, Measurement, MeasurementBatch, Parameter},
+        types::Observation,
+    },
+    zarr::{
+        insert_into_store::InsertIntoStore,
+        zarr_array::{ArrayConfig, ArrayValues, Compression, NdArray},
+        zarr_store::ZarrStore,
+    },
+};
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Map, Value};
+use sophia_api::term::SimpleTerm;
+use std::str::FromStr;
+
+const AGGREGATE_DOCUMENT: &str = " aggregate document";
+
+/// The batches whose measurements were read from an ASM document.
+pub struct AsmDocument {
+    pub batches: Vec<MeasurementBatch>,
+}
+
+impl InsertIntoGraph for AsmDocument {
+    fn insert_into(&self, builder: &mut GraphBuilder, _iri: SimpleTerm) -> Result<()> {
+        // NOTE: each batch is a root node of its own
+        for batch in &self.batches {
+            let iri = batch.get_uri(builder)?;
+            batch.insert_into(builder, iri)?;
+        }
+        Ok(())
+    }
+}
+
+impl InsertIntoStore for AsmDocument {
+    fn insert_into_store(&mut self, store: &ZarrStore) -> Result<()> {
+        self.batches.insert_into_store(store)
+    }
+}
+
+impl FromStr for AsmDocument {
+    type Err = anyhow::Error;
+
+    fn from_str(content: &str) -> Result<Self> {
+        let document: Value = serde_json::from_str(content).context("Invalid ASM JSON document")?;
+        let (aggregate_key, aggregate) = document
+            .as_object()
+            .into_iter()
+            .flatten()
+            .find(|(key, value)| key.ends_with(AGGREGATE_DOCUMENT) && value.is_object())
+            .context("No '<technique> aggregate document' found in the ASM document")?;
+        let technique = aggregate_key.trim_end_matches(AGGREGATE_DOCUMENT);
+
+        let device = aggregate.get("device system document");
+        let equipment_name = ["model number", "device identifier", "equipment serial number"]
+            .iter()
+            .find_map(|key| device.and_then(|d| text(d, key)));
+
+        let mut batches: Vec<MeasurementBatch> = Vec::new();
+        let mut index = 0;
+        for technique_document in technique_documents(aggregate, technique) {
+            let Some(measurements) = technique_document.get("measurement aggregate document")
+            else {
+                continue;
+            };
+            let scopes = [measurements, technique_document];
+            for document in list(measurements.get("measurement document")) {
+                let (batch_id, measurement) =
+                    measurement(index, document, &scopes, technique, equipment_name)
+                        .with_context(|| format!("Invalid measurement document #{}", index))?;
+                index += 1;
+
+                let position = batches.iter().position(|b| b.batch.batch_id == batch_id);
+                let batch = match position {
+                    Some(position) => &mut batches[position],
+                    None => {
+                        batches.push(MeasurementBatch::new(batch_id));
+                        batches.last_mut().unwrap()
+                    }
+                };
+                batch.measurements.push(measurement);
+            }
+        }
+        if batches.is_empty() {
+            bail!("No measurement document found in the ASM document");
+        }
+
+        Ok(AsmDocument { batches })
+    }
+}
+
+/// The technique documents of the aggregate document, e.g. the `plate reader document` list.
+fn technique_documents<'a>(aggregate: &'a Value, technique: &str) -> Vec<&'a Value> {
+    match aggregate.get(format!("{} document", technique)) {
+        Some(documents) => list(Some(documents)),
+        // Some manifests name the list after a sub-technique; take any list of documents.
+        None => aggregate
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter(|(key, _)| key.ends_with(" document") && !key.ends_with(AGGREGATE_DOCUMENT))
+            .flat_map(|(_, value)| list(Some(value)))
+            .filter(|value| value.get("measurement aggregate document").is_some())
+            .collect(),
+    }
+}
+
+/// A single object or a list of objects, as a list.
+fn list(value: Option<&Value>) -> Vec<&Value> {
+    match value {
+        Some(Value::Array(values)) => values.iter().collect(),
+        Some(value @ Value::Object(_)) => vec![value],
+        _ => Vec::new(),
+    }
+}
+
+fn text<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    value.get(key).and_then(Value::as_str).filter(|s| !s.is_empty())
+}
+
+/// Finds the first string value of `key` in `value` or its nested documents.
+fn find_text<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    match value {
+        Value::Object(object) => text(value, key).or_else(|| {
+            object
+                .iter()
+                .filter(|(k, _)| !k.ends_with("data cube"))
+                .find_map(|(_, v)| find_text(v, key))
+        }),
+        Value::Array(values) => values.iter().find_map(|v| find_text(v, key)),
+        _ => None,
+    }
+}
+
+/// Builds the measurement of the `index`-th measurement document, with its data cubes
+/// stored under `measurements/<index>/cubes/<n>`, and returns it with the batch it
+/// belongs to.
+fn measurement(
+    index: usize,
+    measurement: &Value,
+    scopes: &[&Value],
+    technique: &str,
+    equipment_name: Option<&str>,
+) -> Result<(String, Measurement)> {
+    // The enclosing documents hold all the measurements: only their own keys are used.
+    let lookup = |key: &str| {
+        find_text(measurement, key).or_else(|| scopes.iter().find_map(|v| text(v, key)))
+    };
+
+    let sample_document = std::iter::once(measurement)
+        .chain(scopes.iter().copied())
+        .find_map(|v| v.get("sample document"))
+        .context("No sample document")?;
+    let batch_id = text(sample_document, "batch identifier").context("No batch identifier")?;
+    let sample_id = text(sample_document, "sample identifier").context("No sample identifier")?;
+    let start_time = lookup("measurement time")
+        .or_else(|| lookup("injection time"))
+        .context("No measurement time or injection time")?;
+    let method_name = ["analytical method identifier", "method identifier", "method name"]
+        .iter()
+        .find_map(|key| lookup(key))
+        .unwrap_or(technique);
+
+    let mut has_parameter = Vec::new();
+    let mut cubes = Vec::new();
+    collect(measurement, &mut has_parameter, &mut cubes);
+
+    let mut has_dataset = Vec::new();
+    for (n, (name, cube)) in cubes.into_iter().enumerate() {
+        let path = format!("measurements/{}/cubes/{}", index, n);
+        has_dataset.extend(
+            data_cube(&path, name, cube)
+                .with_context(|| format!("Invalid data cube '{}'", name))?,
+        );
+    }
+
+    let measurement = Measurement {
+        action_name: ActionName::measureAction,
+        start_time: start_time.to_string(),
+        method_name: method_name.to_string(),
+        equipment_name: equipment_name.map(str::to_string),
+        has_sample: vec![MeasuredSample {
+            container: None,
+            vial_id: sample_id.to_string(),
+            vial_type: None,
+            role: text(sample_document, "sample role type").map(str::to_string),
+        }],
+        has_parameter,
+        has_dataset,
+    };
+    Ok((batch_id.to_string(), measurement))
+}
+
+/// Collects the quantity values and the data cubes of a measurement document, walking
+/// down its nested documents (except the sample document).
+fn collect<'a>(
+    value: &'a Value,
+    parameters: &mut Vec<Parameter>,
+    cubes: &mut Vec<(&'a str, &'a Map<String, Value>)>,
+) {
+    let Value::Object(object) = value else {
+        if let Value::Array(values) = value {
+            values.iter().for_each(|v| collect(v, parameters, cubes));
+        }
+        return;
+    };
+    for (key, value) in object {
+        match value {
+            Value::Object(cube) if key.ends_with("data cube") => cubes.push((key, cube)),
+            _ if key == "sample document" => {}
+            Value::Object(_) => match quantity(value) {
+                Some(observation) => parameters.push(Parameter { name: key.clone(), observation }),
+                None => collect(value, parameters, cubes),
+            },
+            _ => collect(value, parameters, cubes),
+        }
+    }
+}
+
+/// Maps a quantity value with a known unit (e.g. `{"value": 5, "unit": "µL"}`) to an
+/// [Observation]; quantities in other units are not represented in the ontology.
+fn quantity(value: &Value) -> Option<Observation> {
+    let number = value.get("value")?;
+    let number = match number.as_i64() {
+        Some(integer) => Number::Integer(integer),
+        None => Number::Float(number.as_f64()?),
+    };
+    let unit = Unit::from_label(value.get("unit")?.as_str()?)?;
+    Some(Observation { value: number, unit, error_margin: None })
+}
+
+/// Writes each dimension and each numeric measure of a data cube as an array. Measures
+/// have the shape of the dimensions when their sizes match, and are vectors otherwise.
+fn data_cube(path: &str, name: &str, cube: &Map<String, Value>) -> Result<Vec<Dataset>> {
+    let structure = cube.get("cube-structure").context("No cube-structure")?;
+    let data = cube.get("data").context("No data")?;
+
+    let mut datasets = Vec::new();
+    let mut dimension_sizes = Vec::new();
+    for kind in ["dimensions", "measures"] {
+        let components = list(structure.get(kind));
+        let values = data.get(kind).and_then(Value::as_array).map(Vec::as_slice).unwrap_or(&[]);
+        if components.len() != values.len() {
+            bail!("{} {} declared but {} given", components.len(), kind, values.len());
+        }
+        for (n, (component, values)) in components.iter().zip(values).enumerate() {
+            let Some(values) = numbers(values) else {
+                continue;
+            };
+            let size = values.len() as u64;
+            let shape = if kind == "dimensions" {
+                dimension_sizes.push(size);
+                vec![size]
+            } else if dimension_sizes.iter().product::<u64>() == size {
+                dimension_sizes.clone()
+            } else {
+                vec![size]
+            };
+
+            let mut config = ArrayConfig::default()
+                .with_chunk_shape(shape.iter().map(|&s| s.max(1)).collect())
+                .with_compression(Compression::Gzip { level: 5 })
+                .with_attribute("cube", json!(name));
+            for key in ["concept", "unit"] {
+                if let Some(value) = component.get(key) {
+                    config = config.with_attribute(key, value.clone());
+                }
+            }
+            if let Some(label) = cube.get("label") {
+                config = config.with_attribute("label", label.clone());
+            }
+            let array = NdArray::new(shape, ArrayValues::Float64(values))?;
+            datasets.push(Dataset::new(format!("{}/{}/{}", path, kind, n), array, config));
+        }
+    }
+    Ok(datasets)
+}
+
+/// Flattens the numeric values of a data cube component, `null` being a missing value.
+/// Returns `None` for non-numeric components (strings, booleans).
+fn numbers(value: &Value) -> Option<Vec<f64>> {
+    match value {
+        Value::Array(values) => {
+            let mut flat = Vec::with_capacity(values.len());
+            for value in values {
+                match value {
+                    Value::Null => flat.push(f64::NAN),
+                    Value::Array(_) => flat.extend(numbers(value)?),
+                    _ => flat.push(value.as_f64()?),
+                }
+            }
+            Some(flat)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_asm_document() -> Result<()> {
+        let content = r#"{
+            "$asm.manifest": "http://purl.allotrope.org/manifests/liquid-chromatography/BENCHLING/2023/09/liquid-chromatography.manifest",
+            "liquid chromatography aggregate document": {
+                "device system document": {"model number": "Agilent 1260 Infinity II"},
+                "liquid chromatography document": [{
+                    "measurement aggregate document": {
+                        "measurement document": [{
+                            "measurement identifier": "M1",
+                            "sample document": {"sample identifier": "24", "batch identifier": "23"},
+                            "injection document": {
+                                "injection time": "2024-07-25T14:00:00",
+                                "autosampler injection volume setting (chromatography)": {"value": 5, "unit": "µL"}
+                            },
+                            "chromatogram data cube": {
+                                "label": "DAD1 A",
+                                "cube-structure": {
+                                    "dimensions": [{"@componentDatatype": "double", "concept": "retention time", "unit": "s"}],
+                                    "measures": [{"@componentDatatype": "double", "concept": "absorbance", "unit": "mAU"}]
+                                },
+                                "data": {"dimensions": [[0, 30, 60]], "measures": [[0.1, 2.5, null]]}
+                            }
+                        }]
+                    }
+                }]
+            }
+        }"#;
+        let document: AsmDocument = content.parse()?;
+
+        assert_eq!(document.batches.len(), 1);
+        let batch = &document.batches[0];
+        assert_eq!(batch.batch.batch_id, "23");
+        let action = &batch.measurements[0];
+        assert_eq!(action.start_time, "2024-07-25T14:00:00");
+        assert_eq!(action.method_name, "liquid chromatography");
+        assert_eq!(action.equipment_name.as_deref(), Some("Agilent 1260 Infinity II"));
+        assert_eq!(action.has_parameter.len(), 1);
+        assert_eq!(action.has_parameter[0].observation.value.as_f64(), 5.0);
+
+        let paths: Vec<&str> = action.has_dataset.iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            ["measurements/0/cubes/0/dimensions/0", "measurements/0/cubes/0/measures/0"]
+        );
+        let ArrayValues::Float64(measure) = &action.has_dataset[1].array.values else {
+            panic!("Expected float64 values");
+        };
+        assert_eq!(measure[..2], [0.1, 2.5]);
+        assert!(measure[2].is_nan());
+        Ok(())
+    }
+}
@@ -0,0 +1,413 @@
+// Reader for JCAMP-DX 4.24 / 5.x spectra (IR, UV-Vis).
+//
+// A file is a sequence of labelled data records (`##LABEL= value`). The ordinates are
+// given either as `##XYDATA=(X++(Y..Y))`, in AFFN or in the compressed ASDF forms
+// (SQZ, DIF and DUP pseudo-digits), or as `##XYPOINTS=(XY..XY)` pairs.
+//
+// See: McDonald & Wilks, "JCAMP-DX: A Standard Form for Exchange of Infrared Spectra
+// in Computer Readable Form", Applied Spectroscopy 42 (1988).
+use crate::{
+    models::{
+        dataset::Dataset,
+        enums::{SpectrumType, Unit},
+        spectroscopy::Spectrum,
+    },
+    zarr::zarr_array::{ArrayConfig, ArrayValues, Compression, NdArray},
+};
+use anyhow::{bail, Context, Result};
+use chrono::{NaiveDate, NaiveDateTime};
+use serde_json::json;
+use std::{collections::HashMap, str::FromStr};
+
+impl FromStr for Spectrum {
+    type Err = anyhow::Error;
+
+    fn from_str(content: &str) -> Result<Self> {
+        let records = read_records(content)?;
+        let get = |label: &str| records.get(label).map(|v| v.trim().to_string());
+        let factor = |label: &str| -> Result<f64> {
+            get(label).map_or(Ok(1.0), |v| {
+                v.parse().with_context(|| format!("##{}= is not a number: '{}'", label, v))
+            })
+        };
+
+        let x_label = get("XUNITS").unwrap_or_default();
+        let y_label = get("YUNITS").unwrap_or_default();
+        let (x, y) = if let Some(data) = records.get("XYDATA") {
+            let y = decode_xydata(data)?;
+            let first_x = required_number(&records, "FIRSTX")?;
+            let last_x = required_number(&records, "LASTX")?;
+            let n_points = required_number(&records, "NPOINTS")? as usize;
+            if y.len() != n_points {
+                bail!("##NPOINTS= is {} but ##XYDATA= holds {} values", n_points, y.len());
+            }
+            let y_factor = factor("YFACTOR")?;
+            let step = if n_points > 1 { (last_x - first_x) / (n_points - 1) as f64 } else { 0.0 };
+            let x: Vec<f64> = (0..n_points).map(|i| first_x + i as f64 * step).collect();
+            (x, y.into_iter().map(|v| v * y_factor).collect())
+        } else if let Some(data) = records.get("XYPOINTS") {
+            let (x_factor, y_factor) = (factor("XFACTOR")?, factor("YFACTOR")?);
+            let (x, y): (Vec<f64>, Vec<f64>) = decode_xypoints(data)?.into_iter().unzip();
+            (
+                x.into_iter().map(|v| v * x_factor).collect(),
+                y.into_iter().map(|v| v * y_factor).collect(),
+            )
+        } else {
+            bail!("No ##XYDATA= or ##XYPOINTS= record found in the JCAMP-DX file");
+        };
+
+        let has_dataset = [("x", x, &x_label), ("y", y, &y_label)]
+            .into_iter()
+            .map(|(axis, values, label)| {
+                let n_values = values.len() as u64;
+                let config = ArrayConfig::default()
+                    .with_chunk_shape(vec![n_values.max(1)])
+                    .with_compression(Compression::Gzip { level: 5 })
+                    .with_dimension_names(vec!["point".to_string()])
+                    .with_attribute("units", json!(label));
+                let array = NdArray::vector(ArrayValues::Float64(values));
+                Dataset::new(format!("spectrum/{}", axis), array, config)
+            })
+            .collect();
+
+        Ok(Spectrum {
+            title: get("TITLE").context("Missing ##TITLE= record")?,
+            spectrum_type: spectrum_type(&get("DATATYPE").unwrap_or_default()),
+            origin: get("ORIGIN"),
+            owner: get("OWNER"),
+            instrument_name: get("SPECTROMETERDATASYSTEM"),
+            instrumental_parameters: get("INSTRUMENTPARAMETERS")
+                .or_else(|| get("INSTRUMENTALPARAMETERS")),
+            acquisition_time: get("LONGDATE").map(|d| to_datetime(&d)).transpose()?,
+            x_unit: unit_of(&x_label),
+            y_unit: unit_of(&y_label),
+            x_label,
+            y_label,
+            has_dataset,
+        })
+    }
+}
+
+/// Splits the file into labelled data records, keyed by their normalized label.
+///
+/// Labels are compared without spaces, dashes, slashes and underscores, and `$$`
+/// comments are dropped. Compound files (`##BLOCKS=`) are rejected.
+fn read_records(content: &str) -> Result<HashMap<String, String>> {
+    let mut records = HashMap::new();
+    let mut current: Option<(String, String)> = None;
+
+    for line in content.lines() {
+        let line = line.split("$$").next().unwrap_or_default().trim_end();
+        if let Some(record) = line.strip_prefix("##") {
+            let Some((label, value)) = record.split_once('=') else {
+                bail!("Malformed JCAMP-DX record '{}'", line);
+            };
+            let label: String = label
+                .chars()
+                .filter(|c| !matches!(c, ' ' | '-' | '/' | '_'))
+                .collect::<String>()
+                .to_uppercase();
+            if let Some((label, value)) = current.take() {
+                records.entry(label).or_insert(value);
+            }
+            if label == "END" {
+                break;
+            }
+            current = Some((label, value.trim().to_string()));
+        } else if let Some((_, value)) = current.as_mut() {
+            value.push('\n');
+            value.push_str(line);
+        }
+    }
+    if let Some((label, value)) = current {
+        records.entry(label).or_insert(value);
+    }
+    if records.contains_key("BLOCKS") {
+        bail!("Compound JCAMP-DX files (##BLOCKS=) are not supported");
+    }
+    Ok(records)
+}
+
+fn required_number(records: &HashMap<String, String>, label: &str) -> Result<f64> {
+    let value = records.get(label).with_context(|| format!("Missing ##{}= record", label))?;
+    value.trim().parse().with_context(|| format!("##{}= is not a number: '{}'", label, value))
+}
+
+fn spectrum_type(data_type: &str) -> SpectrumType {
+    let data_type = data_type.to_uppercase();
+    if data_type.contains("INFRARED") || data_type.starts_with("IR") {
+        SpectrumType::Infrared
+    } else if data_type.contains("UV") {
+        SpectrumType::UvVis
+    } else {
+        SpectrumType::Other
+    }
+}
+
+/// Maps a JCAMP-DX `XUNITS` / `YUNITS` value to a QUDT unit.
+fn unit_of(label: &str) -> Option<Unit> {
+    match label.trim().to_uppercase().as_str() {
+        "1/CM" | "CM-1" | "CM^-1" => Some(Unit::PerCentiM),
+        "NANOMETERS" | "NM" => Some(Unit::NanoM),
+        "MICROMETERS" | "UM" | "MICRONS" => Some(Unit::MicroM),
+        "ABSORBANCE" | "TRANSMITTANCE" | "REFLECTANCE" => Some(Unit::Unitless),
+        "%T" | "%TRANSMITTANCE" | "PERCENT TRANSMITTANCE" => Some(Unit::Percent),
+        _ => None,
+    }
+}
+
+/// Converts a `##LONGDATE=` (`YYYY/MM/DD [HH:MM[:SS]]`) to an `xsd:dateTime` lexical
+/// value.
+fn to_datetime(value: &str) -> Result<String> {
+    let value = value.trim();
+    let datetime = ["%Y/%m/%d %H:%M:%S%.f", "%Y/%m/%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| Some(NaiveDate::parse_from_str(value, "%Y/%m/%d").ok()?.into()))
+        .with_context(|| format!("Invalid ##LONGDATE= '{}'", value))?;
+    Ok(datetime.format("%Y-%m-%dT%H:%M:%S").to_string())
+}
+
+/// A value of an ASDF / AFFN data line.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Token {
+    /// An absolute value (AFFN or SQZ form).
+    Value(f64),
+    /// A difference to the previous value (DIF form).
+    Dif(f64),
+    /// A repeat count of the previous token (DUP form).
+    Dup(usize),
+    Missing,
+}
+
+/// Decodes the ordinates of a `(X++(Y..Y))` table.
+fn decode_xydata(data: &str) -> Result<Vec<f64>> {
+    let mut lines = data.lines();
+    let form = lines.next().unwrap_or_default().replace(' ', "");
+    if !form.starts_with("(X++(Y..Y))") && !form.starts_with("(X++(R..R))") {
+        bail!("Unsupported ##XYDATA= form '{}'", form);
+    }
+
+    let mut values: Vec<f64> = Vec::new();
+    let mut previous_line_in_dif = false;
+    for line in lines.map(str::trim).filter(|l| !l.is_empty()) {
+        let tokens = tokenize(line).with_context(|| format!("Invalid data line '{}'", line))?;
+        // The first token is the abscissa, which is recomputed from FIRSTX / LASTX.
+        let mut ordinates = tokens.into_iter().skip(1).peekable();
+        // After a line ending in DIF form, the first ordinate repeats the last value.
+        if previous_line_in_dif {
+            ordinates.next();
+        }
+
+        let mut last: Option<Token> = None;
+        for token in ordinates {
+            match token {
+                Token::Value(v) => values.push(v),
+                Token::Missing => values.push(f64::NAN),
+                Token::Dif(d) => {
+                    let previous = values.last().copied().context("DIF value without a start")?;
+                    values.push(previous + d);
+                }
+                Token::Dup(count) => {
+                    let repeated = last.context("DUP count without a value to repeat")?;
+                    for _ in 1..count {
+                        match repeated {
+                            Token::Dif(d) => values.push(values[values.len() - 1] + d),
+                            _ => values.push(values[values.len() - 1]),
+                        }
+                    }
+                    continue;
+                }
+            }
+            last = Some(token);
+        }
+        previous_line_in_dif = matches!(last, Some(Token::Dif(_)));
+    }
+    Ok(values)
+}
+
+/// Decodes the `(XY..XY)` pairs of a `##XYPOINTS=` table.
+fn decode_xypoints(data: &str) -> Result<Vec<(f64, f64)>> {
+    let mut lines = data.lines();
+    let form = lines.next().unwrap_or_default().replace(' ', "");
+    if !form.starts_with("(XY..XY)") {
+        bail!("Unsupported ##XYPOINTS= form '{}'", form);
+    }
+    let numbers: Vec<f64> = lines
+        .flat_map(|line| line.split(|c: char| c == ',' || c == ';' || c.is_whitespace()))
+        .filter(|token| !token.is_empty())
+        .map(|token| match token {
+            "?" => Ok(f64::NAN),
+            _ => token.parse().with_context(|| format!("Invalid XYPOINTS value '{}'", token)),
+        })
+        .collect::<Result<_>>()?;
+    if numbers.len() % 2 != 0 {
+        bail!("##XYPOINTS= holds an odd number of values");
+    }
+    Ok(numbers.chunks(2).map(|pair| (pair[0], pair[1])).collect())
+}
+
+/// Splits a data line into AFFN and ASDF tokens.
+fn tokenize(line: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = line.chars().collect();
+    // The values of AFFN lines are separated, while the pseudo-digits of ASDF lines
+    // separate their values, so that an unsigned exponent (`1.5E3`) is told apart from
+    // the SQZ digit `E` (`1E3`: 1, then 53).
+    let separated = line.trim().contains(|c: char| c.is_whitespace() || c == ',' || c == ';');
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() || c == ',' || c == ';' {
+            i += 1;
+            continue;
+        }
+        if c == '?' {
+            tokens.push(Token::Missing);
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        i += 1;
+        if matches!(c, '+' | '-' | '.' | '0'..='9') {
+            // AFFN: a plain decimal number, possibly with an exponent.
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let unsigned = separated || chars[start..i].contains(&'.');
+            if let Some(end) = exponent_end(&chars, i, unsigned) {
+                i = end;
+            }
+            let number: String = chars[start..i].iter().collect();
+            tokens.push(Token::Value(number.parse()?));
+            continue;
+        }
+
+        while i < chars.len() && chars[i].is_ascii_digit() {
+            i += 1;
+        }
+        let digits: String = chars[start + 1..i].iter().collect();
+        let (kind, leading) = pseudo_digit(c).with_context(|| format!("Unexpected '{}'", c))?;
+        let magnitude: f64 = format!("{}{}", leading.unsigned_abs(), digits).parse()?;
+        let value = if leading < 0 { -magnitude } else { magnitude };
+        tokens.push(match kind {
+            PseudoDigit::Sqz => Token::Value(value),
+            PseudoDigit::Dif => Token::Dif(value),
+            PseudoDigit::Dup => Token::Dup(value as usize),
+        });
+    }
+    Ok(tokens)
+}
+
+/// The end of the exponent starting at `chars[i]` (`E` or `e`), if it is one: a signed
+/// exponent, or an `unsigned` one ending the value.
+fn exponent_end(chars: &[char], i: usize, unsigned: bool) -> Option<usize> {
+    if !matches!(chars.get(i), Some('E' | 'e')) {
+        return None;
+    }
+    let signed = matches!(chars.get(i + 1), Some('+' | '-'));
+    let digits = i + 1 + usize::from(signed);
+    let end =
+        digits + chars[digits.min(chars.len())..].iter().take_while(|c| c.is_ascii_digit()).count();
+    if end == digits {
+        return None;
+    }
+    let ends_value =
+        chars.get(end).is_none_or(|c| c.is_whitespace() || matches!(c, ',' | ';' | '?'));
+    (signed || (unsigned && ends_value)).then_some(end)
+}
+
+enum PseudoDigit {
+    Sqz,
+    Dif,
+    Dup,
+}
+
+/// Decodes an ASDF pseudo-digit into its form and its signed leading digit.
+fn pseudo_digit(c: char) -> Option<(PseudoDigit, i32)> {
+    let offset = |base: char| c as i32 - base as i32;
+    match c {
+        '@' => Some((PseudoDigit::Sqz, 0)),
+        'A'..='I' => Some((PseudoDigit::Sqz, offset('A') + 1)),
+        'a'..='i' => Some((PseudoDigit::Sqz, -(offset('a') + 1))),
+        '%' => Some((PseudoDigit::Dif, 0)),
+        'J'..='R' => Some((PseudoDigit::Dif, offset('J') + 1)),
+        'j'..='r' => Some((PseudoDigit::Dif, -(offset('j') + 1))),
+        'S'..='Z' => Some((PseudoDigit::Dup, offset('S') + 1)),
+        's' => Some((PseudoDigit::Dup, 9)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_asdf_forms() -> Result<()> {
+        // The same ordinates 1000 1000 1000 1010 990 990 1005, in AFFN, SQZ and DIF/DUP form,
+        // the DIF line being followed by a line starting with a Y check value.
+        let affn = "(X++(Y..Y))\n1 1000 1000 1000 1010\n5 990 990 1005";
+        let sqz = "(X++(Y..Y))\n1A000A000A000A010\n5I90I90A005";
+        let difdup = "(X++(Y..Y))\n1A000%TJ0\n4A010k0%J5";
+        let expected = vec![1000.0, 1000.0, 1000.0, 1010.0, 990.0, 990.0, 1005.0];
+
+        assert_eq!(decode_xydata(affn)?, expected);
+        assert_eq!(decode_xydata(sqz)?, expected);
+        assert_eq!(decode_xydata(difdup)?, expected);
+
+        let exponents = "(X++(Y..Y))\n1 1.5E3 1.5e+3 15E2,1500\n5 2E0";
+        assert_eq!(decode_xydata(exponents)?, vec![1500.0, 1500.0, 1500.0, 1500.0, 2.0]);
+        assert_eq!(decode_xydata("(X++(Y..Y))\n1E3")?, vec![53.0]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_datetime() -> Result<()> {
+        assert_eq!(to_datetime("2024/07/25 15:30:00")?, "2024-07-25T15:30:00");
+        assert_eq!(to_datetime("2024/07/25 15:30")?, "2024-07-25T15:30:00");
+        assert_eq!(to_datetime("2024/07/25")?, "2024-07-25T00:00:00");
+        assert!(to_datetime("25.07.2024").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_jcamp_spectrum() -> Result<()> {
+        let content = "\
+##TITLE= Ethanol
+##JCAMP-DX= 5.01
+##DATA TYPE= INFRARED SPECTRUM
+##ORIGIN= SwissCat+
+##OWNER= PUBLIC DOMAIN
+##LONG DATE= 2024/07/25 15:30:00
+##SPECTROMETER/DATA SYSTEM= Bruker Alpha II
+##XUNITS= 1/CM
+##YUNITS= ABSORBANCE
+##XFACTOR= 1.0
+##YFACTOR= 0.5
+##FIRSTX= 4000
+##LASTX= 3997
+##NPOINTS= 4
+##XYDATA= (X++(Y..Y))
+4000 100 200 $$ first line
+3998 300 400
+##END=
+";
+        let spectrum: Spectrum = content.parse()?;
+
+        assert_eq!(spectrum.title, "Ethanol");
+        assert!(matches!(spectrum.spectrum_type, SpectrumType::Infrared));
+        assert_eq!(spectrum.acquisition_time.as_deref(), Some("2024-07-25T15:30:00"));
+        assert!(matches!(spectrum.x_unit, Some(Unit::PerCentiM)));
+        assert_eq!(
+            spectrum.has_dataset[0].array.values,
+            ArrayValues::Float64(vec![4000.0, 3999.0, 3998.0, 3997.0])
+        );
+        assert_eq!(
+            spectrum.has_dataset[1].array.values,
+            ArrayValues::Float64(vec![50.0, 100.0, 150.0, 200.0])
+        );
+        Ok(())
+    }
+}
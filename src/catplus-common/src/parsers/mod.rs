@@ -0,0 +1,6 @@
+pub mod agilent;
+pub mod animl;
+pub mod asm;
+pub mod jcamp;
+pub mod mzml;
+mod xml;
@@ -0,0 +1,427 @@
+// Reader for mzML mass-spectrometry files (LC-MS, GC-MS).
+//
+// The run, instrument and scan metadata is read from the controlled vocabulary
+// parameters (`cvParam`) of the document, either inline or through a
+// `referenceableParamGroupRef`:
+//
+//   <mzML>
+//     <fileDescription><sourceFileList><sourceFile name="run01.raw"/></sourceFileList></fileDescription>
+//     <sampleList><sample id="S1" name="Vial 1"><userParam name="batchID" value="23"/></sample></sampleList>
+//     <instrumentConfigurationList>
+//       <instrumentConfiguration id="IC1"><cvParam accession="MS:1001911" name="Q Exactive"/></instrumentConfiguration>
+//     </instrumentConfigurationList>
+//     <run id="run01" defaultInstrumentConfigurationRef="IC1" startTimeStamp="2024-07-25T14:00:00Z">
+//       <spectrumList count="1">
+//         <spectrum index="0" id="scan=1" defaultArrayLength="3">
+//           <cvParam accession="MS:1000511" name="ms level" value="1"/>
+//           <scanList><scan><cvParam accession="MS:1000016" name="scan start time" value="0.5" unitName="minute"/></scan></scanList>
+//           <binaryDataArrayList count="2">
+//             <binaryDataArray>
+//               <cvParam accession="MS:1000523" name="64-bit float"/>
+//               <cvParam accession="MS:1000574" name="zlib compression"/>
+//               <cvParam accession="MS:1000514" name="m/z array"/>
+//               <binary>...</binary>
+//             </binaryDataArray>
+//             ...
+//           </binaryDataArrayList>
+//         </spectrum>
+//       </spectrumList>
+//     </run>
+//   </mzML>
+//
+// The peaks of all scans are concatenated into the `mass_spectra/mz` and
+// `mass_spectra/intensity` arrays, the peaks of the n-th scan lying between
+// `mass_spectra/offsets[n]` and `mass_spectra/offsets[n + 1]`. Chromatograms of the
+// run (TIC, SRM traces) are not read. The batch is taken from the first `batchID` user
+// parameter or `sample batch` (MS:1000053) parameter of the document.
+//
+// See: https://www.psidev.info/mzML
+use crate::{
+    models::{
+        dataset::Dataset,
+        enums::Unit,
+        mass_spectrometry::{MassSpectrometryRun, MassSpectrum},
+        types::{Batch, Observation},
+    },
+    parsers::xml::{child, children, decode_le, is_batch_id_name},
+    zarr::zarr_array::{ArrayConfig, ArrayValues, Compression, NdArray},
+};
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use flate2::read::ZlibDecoder;
+use roxmltree::{Document, Node};
+use serde_json::json;
+use std::{collections::HashMap, io::Read, str::FromStr};
+
+/// Number of peaks per chunk of the concatenated m/z and intensity arrays.
+const PEAK_CHUNK_SIZE: u64 = 65536;
+
+const SAMPLE_BATCH: &str = "MS:1000053";
+const INSTRUMENT_SERIAL_NUMBER: &str = "MS:1000529";
+const MS_LEVEL: &str = "MS:1000511";
+const POSITIVE_SCAN: &str = "MS:1000130";
+const NEGATIVE_SCAN: &str = "MS:1000129";
+const TOTAL_ION_CURRENT: &str = "MS:1000285";
+const BASE_PEAK_MZ: &str = "MS:1000504";
+const BASE_PEAK_INTENSITY: &str = "MS:1000505";
+const SCAN_START_TIME: &str = "MS:1000016";
+const FLOAT_32: &str = "MS:1000521";
+const FLOAT_64: &str = "MS:1000523";
+const NO_COMPRESSION: &str = "MS:1000576";
+const ZLIB_COMPRESSION: &str = "MS:1000574";
+const MZ_ARRAY: &str = "MS:1000514";
+const INTENSITY_ARRAY: &str = "MS:1000515";
+
+impl FromStr for MassSpectrometryRun {
+    type Err = anyhow::Error;
+
+    fn from_str(content: &str) -> Result<Self> {
+        let document = Document::parse(content).context("Invalid mzML document")?;
+        // Indexed files wrap the document in an <indexedmzML> element.
+        let root = document
+            .descendants()
+            .find(|n| n.has_tag_name("mzML"))
+            .context("No <mzML> element found in the document")?;
+        let groups: HashMap<&str, Node> = children(root, "referenceableParamGroupList")
+            .flat_map(|list| children(list, "referenceableParamGroup"))
+            .filter_map(|group| group.attribute("id").map(|id| (id, group)))
+            .collect();
+        let run = child(root, "run").context("No <run> element found in the mzML document")?;
+
+        let batch_id = root.descendants().find_map(|n| {
+            if n.has_tag_name("userParam") && n.attribute("name").is_some_and(is_batch_id_name)
+                || n.has_tag_name("cvParam") && n.attribute("accession") == Some(SAMPLE_BATCH)
+            {
+                n.attribute("value").filter(|v| !v.is_empty())
+            } else {
+                None
+            }
+        });
+
+        let sample = children(root, "sampleList").flat_map(|list| children(list, "sample")).next();
+        let source_file = children(root, "fileDescription")
+            .flat_map(|d| children(d, "sourceFileList"))
+            .flat_map(|list| children(list, "sourceFile"))
+            .find_map(|file| file.attribute("name"));
+        let instrument = children(root, "instrumentConfigurationList")
+            .flat_map(|list| children(list, "instrumentConfiguration"))
+            .find(|config| {
+                run.attribute("defaultInstrumentConfigurationRef")
+                    .is_none_or(|id| config.attribute("id") == Some(id))
+            });
+
+        let mut has_mass_spectrum = Vec::new();
+        let mut mz = Vec::new();
+        let mut intensity = Vec::new();
+        let mut offsets = vec![0i64];
+        for spectrum in children(run, "spectrumList").flat_map(|list| children(list, "spectrum")) {
+            let scan_index = has_mass_spectrum.len();
+            let scan_id = spectrum.attribute("id").unwrap_or_default().to_string();
+            let (scan_mz, scan_intensity) = peaks(spectrum, &groups)
+                .with_context(|| format!("Invalid binary data in spectrum '{}'", scan_id))?;
+            mz.extend(scan_mz);
+            intensity.extend(scan_intensity);
+            offsets.push(mz.len() as i64);
+            has_mass_spectrum.push(mass_spectrum(scan_index, scan_id, spectrum, &groups)?);
+        }
+        if has_mass_spectrum.is_empty() {
+            bail!("No spectrum found in the mzML document");
+        }
+
+        Ok(MassSpectrometryRun {
+            run_id: run.attribute("id").unwrap_or_default().to_string(),
+            has_batch: batch_id.map(|batch_id| Batch {
+                batch_id: batch_id.to_string(),
+                actions: None,
+                batch_name: None,
+                reaction_type: None,
+                reaction_name: None,
+                optimization_type: None,
+                link: None,
+            }),
+            sample_name: sample
+                .and_then(|s| s.attribute("name").or(s.attribute("id")))
+                .map(str::to_string),
+            instrument_name: instrument.and_then(|config| instrument_model(config, &groups)),
+            source_file: source_file.map(str::to_string),
+            start_time: run.attribute("startTimeStamp").map(str::to_string),
+            has_mass_spectrum,
+            has_dataset: datasets(mz, intensity, offsets),
+        })
+    }
+}
+
+/// The `cvParam`s of `node`, followed by those of the parameter groups it references.
+fn cv_params<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    groups: &HashMap<&str, Node<'a, 'input>>,
+) -> Vec<Node<'a, 'input>> {
+    let referenced = children(node, "referenceableParamGroupRef")
+        .filter_map(|r| r.attribute("ref"))
+        .filter_map(|id| groups.get(id).copied());
+    std::iter::once(node).chain(referenced).flat_map(|n| children(n, "cvParam")).collect()
+}
+
+fn cv_param<'a, 'input: 'a>(
+    params: &[Node<'a, 'input>],
+    accession: &str,
+) -> Option<Node<'a, 'input>> {
+    params.iter().find(|p| p.attribute("accession") == Some(accession)).copied()
+}
+
+fn cv_value<T: FromStr>(params: &[Node], accession: &str) -> Result<Option<T>> {
+    let Some(param) = cv_param(params, accession) else {
+        return Ok(None);
+    };
+    let value = param.attribute("value").unwrap_or_default();
+    let name = param.attribute("name").unwrap_or(accession);
+    value.parse().map(Some).map_err(|_| anyhow::anyhow!("Invalid {} '{}'", name, value))
+}
+
+/// The instrument model is the first parameter of the configuration that is not its
+/// serial number, the model terms being too many to be listed here.
+fn instrument_model(config: Node, groups: &HashMap<&str, Node>) -> Option<String> {
+    cv_params(config, groups)
+        .into_iter()
+        .find(|p| p.attribute("accession") != Some(INSTRUMENT_SERIAL_NUMBER))
+        .and_then(|p| p.attribute("name"))
+        .map(str::to_string)
+}
+
+fn mass_spectrum(
+    scan_index: usize,
+    scan_id: String,
+    spectrum: Node,
+    groups: &HashMap<&str, Node>,
+) -> Result<MassSpectrum> {
+    let context = || format!("Invalid metadata in spectrum '{}'", scan_id);
+    let params = cv_params(spectrum, groups);
+    let polarity = if cv_param(&params, POSITIVE_SCAN).is_some() {
+        Some("positive".to_string())
+    } else if cv_param(&params, NEGATIVE_SCAN).is_some() {
+        Some("negative".to_string())
+    } else {
+        None
+    };
+
+    let scan_start_time = children(spectrum, "scanList")
+        .flat_map(|list| children(list, "scan"))
+        .find_map(|scan| cv_param(&cv_params(scan, groups), SCAN_START_TIME))
+        .map(|param| -> Result<Observation> {
+            let value = param.attribute("value").unwrap_or_default();
+            let unit = match (param.attribute("unitAccession"), param.attribute("unitName")) {
+                (Some("UO:0000031"), _) | (_, Some("minute")) => Unit::Minute,
+                (Some("UO:0000010"), _) | (_, Some("second")) => Unit::Second,
+                (accession, name) => bail!(
+                    "Unsupported scan start time unit '{}'",
+                    name.or(accession).unwrap_or_default()
+                ),
+            };
+            Ok(Observation {
+                value: value
+                    .parse()
+                    .with_context(|| format!("Invalid scan start time '{}'", value))?,
+                unit,
+                error_margin: None,
+            })
+        })
+        .transpose()
+        .with_context(context)?;
+
+    Ok(MassSpectrum {
+        ms_level: cv_value(&params, MS_LEVEL).with_context(context)?,
+        polarity,
+        scan_start_time,
+        total_ion_current: cv_value(&params, TOTAL_ION_CURRENT).with_context(context)?,
+        base_peak_mz: cv_value(&params, BASE_PEAK_MZ).with_context(context)?,
+        base_peak_intensity: cv_value(&params, BASE_PEAK_INTENSITY).with_context(context)?,
+        scan_id,
+        scan_index,
+    })
+}
+
+/// Decodes the m/z and intensity arrays of a spectrum. Spectra without binary data
+/// (e.g. empty scans) have no peaks.
+fn peaks(spectrum: Node, groups: &HashMap<&str, Node>) -> Result<(Vec<f64>, Vec<f64>)> {
+    let mut mz = None;
+    let mut intensity = None;
+    for array in
+        children(spectrum, "binaryDataArrayList").flat_map(|list| children(list, "binaryDataArray"))
+    {
+        let params = cv_params(array, groups);
+        let target = if cv_param(&params, MZ_ARRAY).is_some() {
+            &mut mz
+        } else if cv_param(&params, INTENSITY_ARRAY).is_some() {
+            &mut intensity
+        } else {
+            // Other arrays (charge, noise, ...) are not stored.
+            continue;
+        };
+        let text = child(array, "binary").and_then(|b| b.text()).unwrap_or_default();
+        *target = Some(decode_binary(text, &params)?);
+    }
+
+    let (mz, intensity) = (mz.unwrap_or_default(), intensity.unwrap_or_default());
+    if mz.len() != intensity.len() {
+        bail!("{} m/z values but {} intensities", mz.len(), intensity.len());
+    }
+    Ok((mz, intensity))
+}
+
+/// Decodes a base64 encoded, optionally zlib-compressed, array of little-endian floats.
+fn decode_binary(text: &str, params: &[Node]) -> Result<Vec<f64>> {
+    let text: String = text.split_whitespace().collect();
+    let mut bytes = STANDARD.decode(text).context("Invalid base64 data")?;
+    if cv_param(params, ZLIB_COMPRESSION).is_some() {
+        let mut decompressed = Vec::new();
+        ZlibDecoder::new(bytes.as_slice())
+            .read_to_end(&mut decompressed)
+            .context("Invalid zlib compressed data")?;
+        bytes = decompressed;
+    } else if cv_param(params, NO_COMPRESSION).is_none() {
+        bail!("Unsupported binary compression (only zlib and no compression are supported)");
+    }
+
+    Ok(if cv_param(params, FLOAT_64).is_some() {
+        decode_le::<f64>(&bytes)?
+    } else if cv_param(params, FLOAT_32).is_some() {
+        decode_le::<f32>(&bytes)?.into_iter().map(f64::from).collect()
+    } else {
+        bail!("Unsupported binary data type (only 32-bit and 64-bit floats are supported)");
+    })
+}
+
+/// Builds the ragged peak arrays of the run, stored under `mass_spectra/`.
+fn datasets(mz: Vec<f64>, intensity: Vec<f64>, offsets: Vec<i64>) -> Vec<Dataset> {
+    let chunk_size = (mz.len() as u64).clamp(1, PEAK_CHUNK_SIZE);
+    let peak_config = |long_name: &str| {
+        ArrayConfig::default()
+            .with_chunk_shape(vec![chunk_size])
+            .with_compression(Compression::Gzip { level: 5 })
+            .with_dimension_names(vec!["peak".to_string()])
+            .with_attribute("long_name", json!(long_name))
+    };
+    let offsets_config = ArrayConfig::default()
+        .with_chunk_shape(vec![offsets.len() as u64])
+        .with_compression(Compression::Gzip { level: 5 })
+        .with_dimension_names(vec!["scan_boundary".to_string()])
+        .with_attribute(
+            "description",
+            json!("The peaks of the n-th scan are the elements [offsets[n], offsets[n + 1])"),
+        );
+    vec![
+        Dataset::new(
+            "mass_spectra/mz",
+            NdArray::vector(ArrayValues::Float64(mz)),
+            peak_config("m/z").with_attribute("units", json!("m/z")),
+        ),
+        Dataset::new(
+            "mass_spectra/intensity",
+            NdArray::vector(ArrayValues::Float64(intensity)),
+            peak_config("intensity"),
+        ),
+        Dataset::new(
+            "mass_spectra/offsets",
+            NdArray::vector(ArrayValues::Int64(offsets)),
+            offsets_config,
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::ZlibEncoder, Compression as ZlibCompression};
+    use std::io::Write;
+
+    fn encode_zlib(values: &[f64]) -> String {
+        let mut encoder = ZlibEncoder::new(Vec::new(), ZlibCompression::default());
+        encoder
+            .write_all(&values.iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<_>>())
+            .unwrap();
+        STANDARD.encode(encoder.finish().unwrap())
+    }
+
+    #[test]
+    fn test_parse_mzml_document() -> Result<()> {
+        let intensities =
+            STANDARD.encode([10f32, 20.0].iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<_>>());
+        let content = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<indexedmzML xmlns="http://psi.hupo.org/ms/mzml">
+<mzML xmlns="http://psi.hupo.org/ms/mzml" version="1.1.0">
+  <fileDescription>
+    <sourceFileList count="1"><sourceFile id="RAW1" name="run01.raw" location="file:///data"/></sourceFileList>
+  </fileDescription>
+  <referenceableParamGroupList count="1">
+    <referenceableParamGroup id="CommonInstrumentParams">
+      <cvParam cvRef="MS" accession="MS:1001911" name="Q Exactive" value=""/>
+      <cvParam cvRef="MS" accession="MS:1000529" name="instrument serial number" value="123"/>
+    </referenceableParamGroup>
+  </referenceableParamGroupList>
+  <sampleList count="1">
+    <sample id="S1" name="Vial 1"><userParam name="batchID" value="23"/></sample>
+  </sampleList>
+  <instrumentConfigurationList count="1">
+    <instrumentConfiguration id="IC1"><referenceableParamGroupRef ref="CommonInstrumentParams"/></instrumentConfiguration>
+  </instrumentConfigurationList>
+  <run id="run01" defaultInstrumentConfigurationRef="IC1" startTimeStamp="2024-07-25T14:00:00Z">
+    <spectrumList count="2">
+      <spectrum index="0" id="scan=1" defaultArrayLength="2">
+        <cvParam cvRef="MS" accession="MS:1000511" name="ms level" value="1"/>
+        <cvParam cvRef="MS" accession="MS:1000130" name="positive scan" value=""/>
+        <cvParam cvRef="MS" accession="MS:1000285" name="total ion current" value="30"/>
+        <scanList count="1"><scan>
+          <cvParam cvRef="MS" accession="MS:1000016" name="scan start time" value="0.5" unitAccession="UO:0000031" unitName="minute"/>
+        </scan></scanList>
+        <binaryDataArrayList count="2">
+          <binaryDataArray>
+            <cvParam cvRef="MS" accession="MS:1000523" name="64-bit float" value=""/>
+            <cvParam cvRef="MS" accession="MS:1000574" name="zlib compression" value=""/>
+            <cvParam cvRef="MS" accession="MS:1000514" name="m/z array" value=""/>
+            <binary>{}</binary>
+          </binaryDataArray>
+          <binaryDataArray>
+            <cvParam cvRef="MS" accession="MS:1000521" name="32-bit float" value=""/>
+            <cvParam cvRef="MS" accession="MS:1000576" name="no compression" value=""/>
+            <cvParam cvRef="MS" accession="MS:1000515" name="intensity array" value=""/>
+            <binary>{}</binary>
+          </binaryDataArray>
+        </binaryDataArrayList>
+      </spectrum>
+      <spectrum index="1" id="scan=2" defaultArrayLength="0">
+        <cvParam cvRef="MS" accession="MS:1000511" name="ms level" value="2"/>
+      </spectrum>
+    </spectrumList>
+  </run>
+</mzML>
+</indexedmzML>"#,
+            encode_zlib(&[100.5, 200.25]),
+            intensities
+        );
+        let run: MassSpectrometryRun = content.parse()?;
+
+        assert_eq!(run.run_id, "run01");
+        assert_eq!(run.has_batch.as_ref().map(|b| b.batch_id.as_str()), Some("23"));
+        assert_eq!(run.sample_name.as_deref(), Some("Vial 1"));
+        assert_eq!(run.instrument_name.as_deref(), Some("Q Exactive"));
+        assert_eq!(run.source_file.as_deref(), Some("run01.raw"));
+
+        let scan = &run.has_mass_spectrum[0];
+        assert_eq!((scan.ms_level, scan.polarity.as_deref()), (Some(1), Some("positive")));
+        assert_eq!(scan.total_ion_current, Some(30.0));
+        assert_eq!(scan.scan_start_time.as_ref().map(|t| t.value.as_f64()), Some(0.5));
+        assert_eq!(run.has_mass_spectrum[1].ms_level, Some(2));
+
+        let values: Vec<&ArrayValues> = run.has_dataset.iter().map(|d| &d.array.values).collect();
+        assert_eq!(
+            values,
+            [
+                &ArrayValues::Float64(vec![100.5, 200.25]),
+                &ArrayValues::Float64(vec![10.0, 20.0]),
+                &ArrayValues::Int64(vec![0, 2, 2]),
+            ]
+        );
+        Ok(())
+    }
+}
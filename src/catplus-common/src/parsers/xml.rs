@@ -0,0 +1,77 @@
+// Helpers shared by the readers of XML instrument files (AnIML, mzML): element lookup by
+// local name, so that documents of any schema version are accepted, and decoding of the
+// little-endian binary arrays they embed.
+use anyhow::{bail, Result};
+use roxmltree::Node;
+
+/// Whether `name` names the batch of a document, e.g. `batchID`, `Batch ID` or `batch_id`.
+pub(crate) fn is_batch_id_name(name: &str) -> bool {
+    name.replace([' ', '_'], "").eq_ignore_ascii_case("batchID")
+}
+
+/// Child elements of `node` with the local name `name`.
+pub(crate) fn children<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    name: &'static str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children().filter(move |n| n.has_tag_name(name))
+}
+
+pub(crate) fn child<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    name: &'static str,
+) -> Option<Node<'a, 'input>> {
+    children(node, name).next()
+}
+
+/// The trimmed text of the child `name` of `node`, if not empty.
+pub(crate) fn child_text<'a>(node: Node<'a, '_>, name: &'static str) -> Option<&'a str> {
+    child(node, name).and_then(|n| n.text()).map(str::trim).filter(|t| !t.is_empty())
+}
+
+/// The numbers of the binary arrays, encoded as little-endian bytes.
+pub(crate) trait LeNumber: Copy {
+    /// The size of an encoded number, in bytes.
+    const SIZE: usize;
+
+    fn from_le_slice(bytes: &[u8]) -> Self;
+}
+
+macro_rules! le_number {
+    ($($type:ty),*) => {$(
+        impl LeNumber for $type {
+            const SIZE: usize = std::mem::size_of::<$type>();
+
+            fn from_le_slice(bytes: &[u8]) -> Self {
+                <$type>::from_le_bytes(bytes.try_into().expect("a slice of SIZE bytes"))
+            }
+        }
+    )*};
+}
+
+le_number!(i32, i64, f32, f64);
+
+/// Decodes the little-endian numbers of `bytes`.
+pub(crate) fn decode_le<T: LeNumber>(bytes: &[u8]) -> Result<Vec<T>> {
+    if !bytes.len().is_multiple_of(T::SIZE) {
+        bail!("Binary data is not a multiple of {} bytes", T::SIZE);
+    }
+    Ok(bytes.chunks_exact(T::SIZE).map(T::from_le_slice).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_le() -> Result<()> {
+        let bytes: Vec<u8> = [1i64 << 60, -2].iter().flat_map(|v| v.to_le_bytes()).collect();
+        assert_eq!(decode_le::<i64>(&bytes)?, [1i64 << 60, -2]);
+        assert_eq!(decode_le::<f32>(&1.5f32.to_le_bytes())?, [1.5]);
+        assert!(decode_le::<f64>(&bytes[..12]).is_err());
+
+        assert!(is_batch_id_name("Batch ID") && is_batch_id_name("batch_id"));
+        assert!(!is_batch_id_name("batch"));
+        Ok(())
+    }
+}
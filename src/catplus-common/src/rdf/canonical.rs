@@ -0,0 +1,79 @@
+// Canonical form of the graphs, so that converting the same input twice yields
+// byte-identical files that can be compared with `git diff`.
+//
+// The blank nodes are relabelled with RDF Dataset Canonicalization
+// (RDFC-1.0, https://www.w3.org/TR/rdf-canon/), which names them `c14n0`, `c14n1`...
+// from the content of the graph rather than from their random labels, and the triples
+// are sorted, so that the serializers walk the graph in the same order on every run.
+use anyhow::{anyhow, Result};
+use sophia::{c14n::rdfc10, inmem::graph::LightGraph};
+use sophia_api::{
+    graph::{Graph, MutableGraph},
+    term::{SimpleTerm, Term},
+};
+
+/// Copies `graph` with the canonical labels of RDFC-1.0 for its blank nodes, inserting
+/// the triples sorted by subject, predicate and object.
+///
+/// # Returns
+/// A `Result` containing the canonical graph, or an error if the blank nodes of the
+/// graph are too entangled to be labelled in a reasonable time.
+pub fn canonicalize_graph(graph: &LightGraph) -> Result<LightGraph> {
+    let dataset = graph.as_dataset();
+    let (quads, _) = rdfc10::relabel(&dataset)
+        .map_err(|e| anyhow!("Failed to canonicalize the graph: {}", e))?;
+    let mut triples: Vec<[SimpleTerm; 3]> = quads
+        .into_iter()
+        .map(|([s, p, o], _)| [s.into_term(), p.into_term(), o.into_term()])
+        .collect();
+    triples.sort();
+
+    let mut canonical = LightGraph::new();
+    for [s, p, o] in triples {
+        canonical.insert(s, p, o)?;
+    }
+    Ok(canonical)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        graph::prefix_map::generate_prefix_map,
+        rdf::{
+            rdf_parser::parse_turtle_to_graph,
+            rdf_serializers::{serialize_graph_to_ntriples, serialize_graph_to_turtle},
+        },
+    };
+    use sophia_isomorphism::isomorphic_graphs;
+
+    #[test]
+    fn test_canonicalize_graph() -> Result<()> {
+        let turtle = |batch: &str, dataset: &str| {
+            format!(
+                r#"
+                PREFIX cat: <http://example.org/cat#>
+                PREFIX schema: <https://schema.org/>
+
+                _:{batch} a cat:Batch;
+                    schema:name "23";
+                    cat:hasDataset _:{dataset}.
+                _:{dataset} schema:name "first", "second".
+                <http://example.org/action/1> cat:hasBatch _:{batch}.
+                "#
+            )
+        };
+        let graph = parse_turtle_to_graph(&turtle("x", "y"))?;
+        let relabelled = parse_turtle_to_graph(&turtle("dataset", "batch"))?;
+
+        let canonical = canonicalize_graph(&graph)?;
+        assert!(isomorphic_graphs(&canonical, &graph)?);
+        let output = serialize_graph_to_turtle(&canonical, &generate_prefix_map())?;
+        assert_eq!(
+            output,
+            serialize_graph_to_turtle(&canonicalize_graph(&relabelled)?, &generate_prefix_map())?
+        );
+        assert!(serialize_graph_to_ntriples(&canonical)?.contains("_:c14n0 "));
+        Ok(())
+    }
+}
@@ -0,0 +1,299 @@
+// The differences between two graphs, e.g. two conversions of the same batch.
+//
+// IRIs and literals are the same in both graphs, but blank nodes are not: their labels
+// are random unless the graphs are canonical. The blank nodes of the old graph are
+// therefore matched with those of the new graph by structure before comparing the
+// triples: a pair of nodes is matched when they share more triples with the IRIs,
+// literals and already matched nodes around them than with any other node. Starting
+// from the IRIs and literals, the matching spreads through the graph, so that a node
+// whose value changed is still matched by its neighbours and only the changed triple
+// is reported.
+use crate::shacl::shapes::{display_term, OwnedTerm};
+use anyhow::Result;
+use serde_json::{json, Value};
+use sophia::inmem::graph::LightGraph;
+use sophia_api::{
+    graph::Graph,
+    term::{BnodeId, SimpleTerm, Term},
+    triple::Triple,
+};
+use sophia_isomorphism::isomorphic_graphs;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+};
+
+pub type OwnedTriple = [OwnedTerm; 3];
+
+/// The triples removed from and added to a graph.
+///
+/// The blank nodes matched in both graphs carry their label of the new graph, the
+/// others their own label (prefixed with `old_` in the old graph when the label is
+/// also used by the new graph).
+#[derive(Debug, Default)]
+pub struct GraphDiff {
+    /// The triples of the old graph missing from the new one.
+    pub removed: Vec<OwnedTriple>,
+    /// The triples of the new graph missing from the old one.
+    pub added: Vec<OwnedTriple>,
+}
+
+impl GraphDiff {
+    /// Whether the graphs are the same, up to the labels of their blank nodes.
+    pub fn is_empty(&self) -> bool {
+        self.removed.is_empty() && self.added.is_empty()
+    }
+
+    /// The machine-readable form of the differences: the `removed` and `added` triples
+    /// as objects of `subject`, `predicate` and `object` in N-Triples syntax.
+    pub fn to_json(&self) -> Value {
+        let triples = |triples: &[OwnedTriple]| -> Vec<Value> {
+            triples
+                .iter()
+                .map(|[s, p, o]| {
+                    json!({
+                        "subject": display_term(s),
+                        "predicate": display_term(p),
+                        "object": display_term(o),
+                    })
+                })
+                .collect()
+        };
+        json!({"removed": triples(&self.removed), "added": triples(&self.added)})
+    }
+}
+
+/// The human-readable form of the differences: one line per triple, starting with `-`
+/// when it was removed and `+` when it was added, sorted by subject and predicate.
+impl fmt::Display for GraphDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut lines: Vec<(String, char)> = self
+            .removed
+            .iter()
+            .map(|t| (display_triple(t), '-'))
+            .chain(self.added.iter().map(|t| (display_triple(t), '+')))
+            .collect();
+        lines.sort();
+        for (triple, sign) in lines {
+            writeln!(f, "{} {}", sign, triple)?;
+        }
+        Ok(())
+    }
+}
+
+fn display_triple([s, p, o]: &OwnedTriple) -> String {
+    format!("{} {} {} .", display_term(s), display_term(p), display_term(o))
+}
+
+/// The triples removed from `old` and added to `new`, the blank nodes of both graphs
+/// being matched by structure.
+pub fn diff_graphs(old: &LightGraph, new: &LightGraph) -> Result<GraphDiff> {
+    if isomorphic_graphs(old, new)? {
+        return Ok(GraphDiff::default());
+    }
+    let old = Side::new(old)?;
+    let new = Side::new(new)?;
+    let matching = match_blank_nodes(&old, &new);
+
+    let new_labels: HashSet<&str> = new.bnodes.keys().map(String::as_str).collect();
+    let rename = |term: &OwnedTerm| -> OwnedTerm {
+        let Some(label) = term.bnode_id() else {
+            return term.clone();
+        };
+        match matching.get(label.as_str()) {
+            Some(matched) => bnode(matched),
+            None if new_labels.contains(label.as_str()) => {
+                bnode(&format!("old_{}", label.as_str()))
+            }
+            None => term.clone(),
+        }
+    };
+    let old_triples: Vec<OwnedTriple> =
+        old.triples.iter().map(|[s, p, o]| [rename(s), p.clone(), rename(o)]).collect();
+    let old_set: HashSet<&OwnedTriple> = old_triples.iter().collect();
+    let new_set: HashSet<&OwnedTriple> = new.triples.iter().collect();
+
+    Ok(GraphDiff {
+        removed: old_triples.iter().filter(|t| !new_set.contains(t)).cloned().collect(),
+        added: new.triples.iter().filter(|t| !old_set.contains(t)).cloned().collect(),
+    })
+}
+
+fn bnode(label: &str) -> OwnedTerm {
+    SimpleTerm::BlankNode(BnodeId::new_unchecked(label.to_string().into()))
+}
+
+/// The triples of a graph, with the triples around each of its blank nodes.
+struct Side {
+    triples: Vec<OwnedTriple>,
+    /// The indices of the triples of each blank node (by label), as subject or object.
+    bnodes: BTreeMap<String, Vec<usize>>,
+}
+
+impl Side {
+    fn new(graph: &LightGraph) -> Result<Self> {
+        let mut triples: Vec<OwnedTriple> = Vec::new();
+        for t in graph.triples() {
+            let t = t?;
+            triples.push([t.s().into_term(), t.p().into_term(), t.o().into_term()]);
+        }
+        let mut bnodes: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for (i, [s, _, o]) in triples.iter().enumerate() {
+            for label in [s.bnode_id(), o.bnode_id()].into_iter().flatten() {
+                let indices = bnodes.entry(label.as_str().to_string()).or_default();
+                if indices.last() != Some(&i) {
+                    indices.push(i);
+                }
+            }
+        }
+        Ok(Self { triples, bnodes })
+    }
+
+    /// The triples of blank node `label` as edges, whose other end is named by `name`
+    /// (the edges to unnamed blank nodes are left out).
+    fn edges(&self, label: &str, name: impl Fn(&OwnedTerm) -> Option<String>) -> Vec<Edge> {
+        self.bnodes[label]
+            .iter()
+            .flat_map(|&i| {
+                let [s, p, o] = &self.triples[i];
+                let predicate = display_term(p);
+                let outgoing = (s.bnode_id().is_some_and(|id| id.as_str() == label))
+                    .then(|| name(o).map(|o| Edge(true, predicate.clone(), o)));
+                let incoming = (o.bnode_id().is_some_and(|id| id.as_str() == label))
+                    .then(|| name(s).map(|s| Edge(false, predicate.clone(), s)));
+                [outgoing.flatten(), incoming.flatten()]
+            })
+            .flatten()
+            .collect()
+    }
+}
+
+/// A triple seen from one of its blank nodes: whether the node is the subject, the
+/// predicate and the name of the other end.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct Edge(bool, String, String);
+
+/// Matches the blank nodes of `old` with those of `new`, as a map of their labels.
+///
+/// Every round scores the unmatched pairs by the number of edges they share, and
+/// matches the pairs that prefer each other to any other node. When no pair does, the
+/// nodes are indistinguishable so far and the best pairs are matched in order.
+fn match_blank_nodes(old: &Side, new: &Side) -> HashMap<String, String> {
+    let mut matching: HashMap<String, String> = HashMap::new();
+    let mut matched: HashSet<String> = HashSet::new();
+    loop {
+        let name_old = |term: &OwnedTerm| match term.bnode_id() {
+            Some(label) => matching.get(label.as_str()).map(|m| format!("_:{}", m)),
+            None => Some(display_term(term)),
+        };
+        let name_new = |term: &OwnedTerm| match term.bnode_id() {
+            Some(label) => matched.contains(label.as_str()).then(|| display_term(term)),
+            None => Some(display_term(term)),
+        };
+        let mut index: HashMap<Edge, Vec<&str>> = HashMap::new();
+        for label in new.bnodes.keys().filter(|label| !matched.contains(*label)) {
+            for edge in new.edges(label, name_new) {
+                index.entry(edge).or_default().push(label);
+            }
+        }
+        let mut scores: Vec<(usize, &str, &str)> = Vec::new();
+        for label in old.bnodes.keys().filter(|label| !matching.contains_key(*label)) {
+            let mut shared: BTreeMap<&str, usize> = BTreeMap::new();
+            for edge in old.edges(label, name_old) {
+                for candidate in index.get(&edge).into_iter().flatten() {
+                    *shared.entry(candidate).or_default() += 1;
+                }
+            }
+            scores.extend(
+                shared.into_iter().map(|(candidate, score)| (score, label.as_str(), candidate)),
+            );
+        }
+        if scores.is_empty() {
+            return matching;
+        }
+
+        let mut best_old: HashMap<&str, (usize, usize)> = HashMap::new();
+        let mut best_new: HashMap<&str, (usize, usize)> = HashMap::new();
+        for &(score, o, n) in &scores {
+            for (best, label) in [(&mut best_old, o), (&mut best_new, n)] {
+                let entry = best.entry(label).or_insert((0, 0));
+                if score > entry.0 {
+                    *entry = (score, 1);
+                } else if score == entry.0 {
+                    entry.1 += 1;
+                }
+            }
+        }
+        let mut pairs: Vec<(&str, &str)> = scores
+            .iter()
+            .filter(|(score, o, n)| best_old[o] == (*score, 1) && best_new[n] == (*score, 1))
+            .map(|&(_, o, n)| (o, n))
+            .collect();
+        if pairs.is_empty() {
+            scores.sort_by_key(|&(score, o, n)| (std::cmp::Reverse(score), o, n));
+            let mut taken: HashSet<&str> = HashSet::new();
+            for &(_, o, n) in &scores {
+                if !taken.contains(o) && !taken.contains(n) {
+                    taken.extend([o, n]);
+                    pairs.push((o, n));
+                }
+            }
+        }
+        let pairs: Vec<(String, String)> =
+            pairs.into_iter().map(|(o, n)| (o.to_string(), n.to_string())).collect();
+        for (o, n) in pairs {
+            matched.insert(n.clone());
+            matching.insert(o, n);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rdf::rdf_parser::parse_turtle_to_graph;
+
+    #[test]
+    fn test_diff_graphs() -> Result<()> {
+        let old = parse_turtle_to_graph(
+            r#"
+            PREFIX cat: <http://example.org/cat#>
+            PREFIX schema: <https://schema.org/>
+
+            _:batch a cat:Batch; schema:name "23".
+            _:add a cat:AddAction; cat:hasBatch _:batch; cat:speedInRPM _:speed;
+                cat:temperature _:temperature.
+            _:speed cat:value "100".
+            _:temperature cat:value "25".
+            _:shake a cat:ShakeAction; cat:hasBatch _:batch; cat:speedInRPM _:slow.
+            _:slow cat:value "100".
+            "#,
+        )?;
+        let new = parse_turtle_to_graph(
+            r#"
+            PREFIX cat: <http://example.org/cat#>
+            PREFIX schema: <https://schema.org/>
+
+            _:b0 a cat:Batch; schema:name "23".
+            _:b1 a cat:AddAction; cat:hasBatch _:b0; cat:speedInRPM _:b2;
+                cat:temperature _:b3.
+            _:b2 cat:value "100".
+            _:b3 cat:value "30".
+            _:b4 a cat:ShakeAction; cat:hasBatch _:b0; cat:speedInRPM _:b5.
+            _:b5 cat:value "100".
+            "#,
+        )?;
+
+        assert!(diff_graphs(&old, &old)?.is_empty());
+        let diff = diff_graphs(&old, &new)?;
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(
+            diff.to_string(),
+            "- _:b3 <http://example.org/cat#value> \"25\" .\n\
+             + _:b3 <http://example.org/cat#value> \"30\" .\n"
+        );
+        assert_eq!(diff.to_json()["added"][0]["object"], "\"30\"");
+        Ok(())
+    }
+}
@@ -0,0 +1,525 @@
+use crate::graph::prefix_map::PrefixMap;
+use anyhow::{bail, Result};
+use serde_json::{json, map::Entry, Map, Value};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+/// How [compact_jsonld_with_options] shapes the compacted document.
+#[derive(Clone, Debug, Default)]
+pub struct CompactionOptions {
+    /// The class (a compact or full IRI, e.g. `cat:Batch`) of the nodes the document is
+    /// framed on: the other nodes are embedded into them, instead of being listed side by
+    /// side under `@graph`.
+    pub frame: Option<String>,
+    /// The URL of the published prefix context (see [prefix_context]), referenced by the
+    /// document instead of inlining the prefixes.
+    pub context_url: Option<String>,
+}
+
+/// Compacts an expanded, flattened JSON-LD document (as produced by sophia) with a
+/// context made of the prefixes in `prefix_map`.
+///
+/// IRIs are shortened to compact IRIs (e.g. `cat:Batch`), single values are unwrapped
+/// from their arrays and plain string literals become JSON strings. The nodes are
+/// returned under `@graph`, next to the generated `@context`.
+///
+/// # Parameters
+/// - `expanded`: The expanded JSON-LD document, a JSON array of node objects.
+/// - `prefix_map`: The prefixes used to build the `@context`.
+///
+/// # Returns
+/// A `Result` containing the compacted document, or an error if `expanded` is not an
+/// array of node objects.
+pub fn compact_jsonld(expanded: Value, prefix_map: &PrefixMap) -> Result<Value> {
+    compact_jsonld_with_options(expanded, prefix_map, &CompactionOptions::default())
+}
+
+/// Compacts an expanded, flattened JSON-LD document like [compact_jsonld], optionally
+/// framed and referencing a published context as requested by `options`.
+///
+/// The `@context` also defines a term for each property whose local name is unique
+/// (e.g. `hasBatch` for `cat:hasBatch`), coercing its values to node references or to
+/// their datatype when all of them agree, so that the values are plain JSON strings.
+///
+/// # Returns
+/// A `Result` containing the compacted document, or an error if `expanded` is not an
+/// array of node objects or has no node of the class of the frame.
+pub fn compact_jsonld_with_options(
+    expanded: Value,
+    prefix_map: &PrefixMap,
+    options: &CompactionOptions,
+) -> Result<Value> {
+    let Value::Array(nodes) = expanded else {
+        bail!("Expanded JSON-LD must be an array of node objects");
+    };
+    let nodes = nodes
+        .into_iter()
+        .map(|node| match node {
+            Value::Object(node) => Ok(node),
+            _ => bail!("Expanded JSON-LD must be an array of node objects"),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let prefixes: Vec<(&str, &str)> =
+        prefix_map.iter().map(|(prefix, iri)| (prefix.as_str(), iri.as_str())).collect();
+    let compactor = Compactor { terms: terms(&nodes, &prefixes), prefixes: &prefixes };
+
+    let framed = options.frame.is_some();
+    let nodes = match &options.frame {
+        Some(class) => frame(nodes, &expand_iri(class, &prefixes))?,
+        None => nodes,
+    };
+    let mut graph: Vec<Value> =
+        nodes.into_iter().map(|node| Value::Object(compactor.node(node))).collect();
+
+    let mut document = Map::new();
+    document.insert("@context".to_string(), compactor.context(options.context_url.as_deref()));
+    match graph.pop() {
+        Some(Value::Object(root)) if framed && graph.is_empty() => document.extend(root),
+        last => {
+            graph.extend(last);
+            document.insert("@graph".to_string(), Value::Array(graph));
+        }
+    }
+    Ok(Value::Object(document))
+}
+
+/// The context made of the prefixes in `prefix_map`, as a JSON-LD document to publish
+/// for [CompactionOptions::context_url].
+pub fn prefix_context(prefix_map: &PrefixMap) -> Value {
+    let context: Map<String, Value> = prefix_map
+        .iter()
+        .map(|(prefix, iri)| (prefix.to_string(), Value::String(iri.to_string())))
+        .collect();
+    json!({ "@context": context })
+}
+
+/// A term of the context, defined for a property.
+struct TermDefinition {
+    name: String,
+    /// The compact IRI of the property.
+    id: String,
+    /// `@id`, or the compact IRI of the datatype of the values.
+    coercion: Option<String>,
+}
+
+/// The kind of the values of a property, to coerce them in its term definition.
+#[derive(PartialEq)]
+enum ValueKind {
+    Reference,
+    Typed(String),
+    Other,
+}
+
+/// The term definitions of the properties of `nodes`, by property IRI.
+fn terms(
+    nodes: &[Map<String, Value>],
+    prefixes: &[(&str, &str)],
+) -> BTreeMap<String, TermDefinition> {
+    let mut kinds: BTreeMap<&str, ValueKind> = BTreeMap::new();
+    for (property, values) in nodes.iter().flatten().filter(|(key, _)| !key.starts_with('@')) {
+        for value in values.as_array().into_iter().flatten() {
+            let kind = match value {
+                Value::Object(o) if o.len() == 1 && o.contains_key("@id") => ValueKind::Reference,
+                Value::Object(o) if o.len() == 2 && o.contains_key("@value") => match &o["@type"] {
+                    Value::String(datatype) => ValueKind::Typed(datatype.clone()),
+                    _ => ValueKind::Other,
+                },
+                _ => ValueKind::Other,
+            };
+            match kinds.get(property.as_str()) {
+                Some(known) if *known != kind => _ = kinds.insert(property, ValueKind::Other),
+                Some(_) => {}
+                None => _ = kinds.insert(property, kind),
+            }
+        }
+    }
+
+    // Readable local names, e.g. not the identifiers of the Allotrope ontologies.
+    let local_name = |id: &str| -> Option<String> {
+        let (_, local) = id.split_once(':')?;
+        let mut chars = local.chars();
+        let simple = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+            && local.chars().any(|c| c.is_ascii_lowercase());
+        let prefix = prefixes.iter().any(|(prefix, _)| *prefix == local);
+        (simple && !prefix).then(|| local.to_string())
+    };
+    let ids: BTreeMap<&str, String> = kinds
+        .keys()
+        .map(|iri| (*iri, compact_iri(iri, prefixes)))
+        .filter(|(iri, id)| iri != id)
+        .collect();
+    let mut names: BTreeMap<String, usize> = BTreeMap::new();
+    for name in ids.values().filter_map(|id| local_name(id)) {
+        *names.entry(name).or_default() += 1;
+    }
+    kinds
+        .into_iter()
+        .filter_map(|(iri, kind)| {
+            let id = ids.get(iri)?.clone();
+            let name = local_name(&id).filter(|name| names[name] == 1).unwrap_or(id.clone());
+            let coercion = match kind {
+                ValueKind::Reference => Some("@id".to_string()),
+                ValueKind::Typed(datatype) => Some(compact_iri(&datatype, prefixes)),
+                ValueKind::Other => None,
+            };
+            Some((iri.to_string(), TermDefinition { name, id, coercion }))
+        })
+        .collect()
+}
+
+/// Embeds the nodes into the nodes of type `class`, the roots of the document.
+///
+/// A node is embedded once, into the first node referencing it, or else into a node it
+/// references (under `@reverse`, e.g. the actions of a batch). Blank nodes are left
+/// without identifier when they are not referenced anywhere else. The nodes that cannot
+/// be reached from the roots are listed after them.
+fn frame(nodes: Vec<Map<String, Value>>, class: &str) -> Result<Vec<Map<String, Value>>> {
+    let id =
+        |node: &Map<String, Value>| node.get("@id").and_then(Value::as_str).map(str::to_string);
+    let mut order = Vec::new();
+    let mut by_id = HashMap::new();
+    for node in nodes {
+        let Some(node_id) = id(&node) else {
+            bail!("Expanded JSON-LD nodes must have an @id");
+        };
+        order.push(node_id.clone());
+        by_id.insert(node_id, node);
+    }
+
+    let mut references: HashMap<&str, Vec<&str>> = HashMap::new();
+    let mut referenced_by: HashMap<&str, Vec<(&str, &str)>> = HashMap::new();
+    for (node_id, node) in &by_id {
+        for (property, values) in node.iter().filter(|(key, _)| !key.starts_with('@')) {
+            for value in values.as_array().into_iter().flatten() {
+                let Some(target) = reference(value).filter(|target| by_id.contains_key(*target))
+                else {
+                    continue;
+                };
+                references.entry(node_id).or_default().push(target);
+                referenced_by.entry(target).or_default().push((node_id, property));
+            }
+        }
+    }
+    // Deterministic order, as the nodes are indexed by a hash map.
+    let position: HashMap<&str, usize> =
+        order.iter().enumerate().map(|(i, id)| (id.as_str(), i)).collect();
+    referenced_by.values_mut().for_each(|r| r.sort_by_key(|(source, _)| position[source]));
+
+    let is_root = |node: &Map<String, Value>| {
+        node.get("@type")
+            .and_then(Value::as_array)
+            .is_some_and(|types| types.iter().any(|t| t.as_str() == Some(class)))
+    };
+    let roots: Vec<&str> =
+        order.iter().map(String::as_str).filter(|id| is_root(&by_id[*id])).collect();
+    if roots.is_empty() {
+        bail!("The graph has no node of type <{}> to frame the document on", class);
+    }
+
+    let mut placed: HashSet<&str> = roots.iter().copied().collect();
+    let mut queue: VecDeque<&str> = roots.iter().copied().collect();
+    let mut embedded: HashMap<&str, HashSet<&str>> = HashMap::new();
+    let mut reverse: HashMap<&str, Vec<(&str, &str)>> = HashMap::new();
+    while let Some(node_id) = queue.pop_front() {
+        for target in references.get(node_id).into_iter().flatten() {
+            if placed.insert(target) {
+                embedded.entry(node_id).or_default().insert(target);
+                queue.push_back(target);
+            }
+        }
+        for (source, property) in referenced_by.get(node_id).into_iter().flatten() {
+            if placed.insert(source) {
+                reverse.entry(node_id).or_default().push((property, source));
+                queue.push_back(source);
+            }
+        }
+    }
+
+    let mut framer = Framer {
+        nodes: by_id.clone(),
+        embedded,
+        reverse,
+        reference_counts: referenced_by.iter().map(|(id, r)| (*id, r.len())).collect(),
+    };
+    let mut framed: Vec<_> = roots.iter().map(|root| framer.build(root, 0)).collect();
+    framed.extend(
+        order.iter().filter(|id| !placed.contains(id.as_str())).map(|id| by_id[id].clone()),
+    );
+    Ok(framed)
+}
+
+/// The target of a node reference `{"@id": ...}`.
+fn reference(value: &Value) -> Option<&str> {
+    match value {
+        Value::Object(o) if o.len() == 1 => o.get("@id")?.as_str(),
+        _ => None,
+    }
+}
+
+/// Builds the trees of the framed nodes, see [frame].
+struct Framer<'a> {
+    nodes: HashMap<String, Map<String, Value>>,
+    embedded: HashMap<&'a str, HashSet<&'a str>>,
+    reverse: HashMap<&'a str, Vec<(&'a str, &'a str)>>,
+    reference_counts: HashMap<&'a str, usize>,
+}
+
+impl Framer<'_> {
+    /// The node `node_id` with its embedded nodes, `references` being the number of its
+    /// references represented by its position in the tree.
+    fn build(&mut self, node_id: &str, references: usize) -> Map<String, Value> {
+        let mut node = self.nodes.remove(node_id).unwrap_or_default();
+        let mut embedded = self.embedded.remove(node_id).unwrap_or_default();
+        for (_, values) in node.iter_mut().filter(|(key, _)| !key.starts_with('@')) {
+            for value in values.as_array_mut().into_iter().flatten() {
+                let Some(target) = reference(value).map(str::to_string) else {
+                    continue;
+                };
+                if embedded.remove(target.as_str()) {
+                    *value = Value::Object(self.build(&target, 1));
+                }
+            }
+        }
+        let reverse = self.reverse.remove(node_id).unwrap_or_default();
+        let mut reversed: Map<String, Value> = Map::new();
+        for (property, source) in &reverse {
+            let mut source = self.build(source, 0);
+            // The triple is represented by the position of the source in the tree.
+            if let Some(Value::Array(values)) = source.get_mut(*property) {
+                if let Some(i) = values.iter().position(|v| reference(v) == Some(node_id)) {
+                    values.remove(i);
+                }
+                if values.is_empty() {
+                    source.remove(*property);
+                }
+            }
+            match reversed.entry(*property) {
+                Entry::Occupied(mut values) => {
+                    values.get_mut().as_array_mut().unwrap().push(Value::Object(source))
+                }
+                Entry::Vacant(entry) => _ = entry.insert(json!([source])),
+            }
+        }
+        if !reversed.is_empty() {
+            node.insert("@reverse".to_string(), Value::Object(reversed));
+        }
+        let count = self.reference_counts.get(node_id).copied().unwrap_or(0);
+        if node_id.starts_with("_:") && count == references + reverse.len() {
+            node.remove("@id");
+        }
+        node
+    }
+}
+
+/// Compacts nodes with the prefixes and the term definitions of the context.
+struct Compactor<'a> {
+    prefixes: &'a [(&'a str, &'a str)],
+    terms: BTreeMap<String, TermDefinition>,
+}
+
+impl Compactor<'_> {
+    fn context(&self, context_url: Option<&str>) -> Value {
+        let mut terms = Map::new();
+        for term in self.terms.values() {
+            let definition = match &term.coercion {
+                Some(coercion) => json!({ "@id": term.id, "@type": coercion }),
+                None if term.name == term.id => continue,
+                None => Value::String(term.id.clone()),
+            };
+            terms.insert(term.name.clone(), definition);
+        }
+        terms.sort_keys();
+        match context_url {
+            Some(url) if terms.is_empty() => Value::String(url.to_string()),
+            Some(url) => json!([url, terms]),
+            None => {
+                let mut context = Map::new();
+                for (prefix, iri) in self.prefixes {
+                    context.insert(prefix.to_string(), Value::String(iri.to_string()));
+                }
+                context.extend(terms);
+                Value::Object(context)
+            }
+        }
+    }
+
+    fn node(&self, node: Map<String, Value>) -> Map<String, Value> {
+        let mut compacted = Map::new();
+        for (key, value) in node {
+            let (key, value) = match key.as_str() {
+                "@id" => (key, value),
+                "@type" => {
+                    (key, unwrap_single(map_array(value, |t| compact_string(t, self.prefixes))))
+                }
+                "@reverse" => {
+                    let Value::Object(properties) = value else {
+                        continue;
+                    };
+                    let properties = properties
+                        .into_iter()
+                        .map(|(property, values)| self.property(property, values))
+                        .collect();
+                    (key, Value::Object(properties))
+                }
+                _ => self.property(key, value),
+            };
+            compacted.insert(key, value);
+        }
+        compacted
+    }
+
+    fn property(&self, property: String, values: Value) -> (String, Value) {
+        let term = self.terms.get(&property);
+        let coercion = term.and_then(|t| t.coercion.as_deref());
+        let values = unwrap_single(map_array(values, |v| self.value(v, coercion)));
+        match term {
+            Some(term) => (term.name.clone(), values),
+            None => (compact_iri(&property, self.prefixes), values),
+        }
+    }
+
+    /// Compacts a value object (`@value`), a node reference (`@id`) or an embedded node.
+    fn value(&self, value: Value, coercion: Option<&str>) -> Value {
+        let Value::Object(mut object) = value else {
+            return value;
+        };
+        if reference(&Value::Object(object.clone())).is_some() {
+            let id = compact_string(object.remove("@id").unwrap(), self.prefixes);
+            return match coercion {
+                Some("@id") => id,
+                _ => json!({ "@id": id }),
+            };
+        }
+        if !object.contains_key("@value") {
+            return Value::Object(self.node(object));
+        }
+        if let Some(datatype) = object.remove("@type") {
+            let datatype = compact_string(datatype, self.prefixes);
+            if coercion.is_some() && datatype.as_str() == coercion && object.len() == 1 {
+                return object.remove("@value").unwrap();
+            }
+            object.insert("@type".to_string(), datatype);
+        }
+        if object.len() == 1 {
+            if let Some(Value::String(literal)) = object.get("@value") {
+                return Value::String(literal.clone());
+            }
+        }
+        Value::Object(object)
+    }
+}
+
+/// Expands a compact IRI (e.g. `cat:Batch`) with `prefixes`, other IRIs being kept.
+fn expand_iri(iri: &str, prefixes: &[(&str, &str)]) -> String {
+    iri.split_once(':')
+        .and_then(|(prefix, local)| {
+            let (_, namespace) = prefixes.iter().find(|(p, _)| *p == prefix)?;
+            Some(format!("{}{}", namespace, local))
+        })
+        .unwrap_or_else(|| iri.to_string())
+}
+
+/// Shortens `iri` with the longest matching namespace of `prefixes`.
+pub fn compact_iri(iri: &str, prefixes: &[(&str, &str)]) -> String {
+    prefixes
+        .iter()
+        .filter(|(_, ns)| iri.len() > ns.len() && iri.starts_with(ns))
+        .max_by_key(|(_, ns)| ns.len())
+        .map(|(prefix, ns)| (prefix, &iri[ns.len()..]))
+        .filter(|(_, suffix)| !suffix.starts_with("//"))
+        .map(|(prefix, suffix)| format!("{}:{}", prefix, suffix))
+        .unwrap_or_else(|| iri.to_string())
+}
+
+fn compact_string(value: Value, prefixes: &[(&str, &str)]) -> Value {
+    match value {
+        Value::String(iri) if !iri.starts_with("_:") => Value::String(compact_iri(&iri, prefixes)),
+        other => other,
+    }
+}
+
+fn map_array(value: Value, f: impl Fn(Value) -> Value) -> Value {
+    match value {
+        Value::Array(values) => Value::Array(values.into_iter().map(f).collect()),
+        other => f(other),
+    }
+}
+
+fn unwrap_single(value: Value) -> Value {
+    match value {
+        Value::Array(mut values) if values.len() == 1 => values.remove(0),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::prefix_map::generate_prefix_map;
+    use serde_json::json;
+
+    #[test]
+    fn test_compact_jsonld() -> Result<()> {
+        let expanded = json!([{
+            "@id": "_:b0",
+            "@type": ["http://example.org/cat#Batch"],
+            "https://schema.org/name": [{"@value": "23"}],
+            "http://purl.allotrope.org/ontologies/result#AFR_0002423": [{
+                "@value": "2024-07-25T12:00:02",
+                "@type": "http://www.w3.org/2001/XMLSchema#dateTime"
+            }],
+            "http://example.org/cat#hasDataset": [{"@id": "_:b1"}, {"@id": "_:b2"}]
+        }]);
+
+        let compacted = compact_jsonld(expanded, &generate_prefix_map())?;
+
+        let context = &compacted["@context"];
+        assert_eq!(context["cat"], "http://example.org/cat#");
+        assert_eq!(context["name"], "schema:name");
+        assert_eq!(context["hasDataset"], json!({"@id": "cat:hasDataset", "@type": "@id"}));
+        assert_eq!(
+            context["allores:AFR_0002423"],
+            json!({"@id": "allores:AFR_0002423", "@type": "xsd:dateTime"})
+        );
+        assert_eq!(
+            compacted["@graph"][0],
+            json!({
+                "@id": "_:b0",
+                "@type": "cat:Batch",
+                "name": "23",
+                "allores:AFR_0002423": "2024-07-25T12:00:02",
+                "hasDataset": ["_:b1", "_:b2"]
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_frame_jsonld() -> Result<()> {
+        let expanded = json!([
+            {
+                "@id": "_:action",
+                "@type": ["http://example.org/cat#AddAction"],
+                "http://example.org/cat#hasBatch": [{"@id": "_:batch"}]
+            },
+            {
+                "@id": "_:batch",
+                "@type": ["http://example.org/cat#Batch"],
+                "https://schema.org/name": [{"@value": "23"}]
+            }
+        ]);
+        let options = CompactionOptions {
+            frame: Some("cat:Batch".to_string()),
+            context_url: Some("https://example.org/catplus-context.jsonld".to_string()),
+        };
+
+        let framed = compact_jsonld_with_options(expanded, &generate_prefix_map(), &options)?;
+
+        assert_eq!(framed["@context"][0], "https://example.org/catplus-context.jsonld");
+        assert_eq!(framed["@type"], "cat:Batch");
+        assert_eq!(framed["name"], "23");
+        assert_eq!(framed["@reverse"]["hasBatch"], json!({"@type": "cat:AddAction"}));
+        assert!(framed.get("@id").is_none());
+        Ok(())
+    }
+}
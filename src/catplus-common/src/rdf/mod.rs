@@ -1,2 +1,6 @@
+pub mod canonical;
+pub mod graph_diff;
+pub mod jsonld_compaction;
 pub mod rdf_parser;
 pub mod rdf_serializers;
+pub mod rdf_xml;
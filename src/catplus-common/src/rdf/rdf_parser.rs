@@ -1,7 +1,13 @@
 use anyhow::Result;
-use sophia::inmem::graph::LightGraph;
-use sophia_api::{parser::TripleParser, prelude::TripleSource};
-use sophia_turtle::parser::turtle::TurtleParser;
+use sophia::{inmem::graph::LightGraph, jsonld::JsonLdParser};
+use sophia_api::{
+    parser::{QuadParser, TripleParser},
+    prelude::{QuadSource, TripleSource},
+    quad::Quad,
+};
+use sophia_turtle::parser::{
+    nq::NQuadsParser, nt::NTriplesParser, trig::TriGParser, turtle::TurtleParser,
+};
 
 /// Parses a Turtle string into an RDF graph.
 ///
@@ -22,3 +28,83 @@ pub fn parse_turtle_to_graph(turtle_input: &str) -> Result<LightGraph> {
 
     Ok(graph)
 }
+
+/// Parses a JSON-LD string into an RDF graph, ignoring named graphs.
+///
+/// Remote contexts are not loaded, so that the document must be self-contained.
+///
+/// # Parameters
+/// - `jsonld_input`: The JSON-LD content as a string slice.
+///
+/// # Returns
+/// - `Result<LightGraph>`: The parsed RDF graph on success, or an error on failure.
+pub fn parse_jsonld_to_graph(jsonld_input: &str) -> Result<LightGraph> {
+    let mut graph = LightGraph::new();
+
+    JsonLdParser::new()
+        .parse_str(jsonld_input)
+        .filter_quads(|q| q.g().is_none())
+        .to_triples()
+        .add_to_graph(&mut graph)
+        .map_err(|e| anyhow::anyhow!("Failed to parse JSON-LD input: {}", e))?;
+
+    Ok(graph)
+}
+
+/// Parses an N-Triples string into an RDF graph.
+///
+/// # Parameters
+/// - `ntriples_input`: The N-Triples content as a string slice.
+///
+/// # Returns
+/// - `Result<LightGraph>`: The parsed RDF graph on success, or an error on failure.
+pub fn parse_ntriples_to_graph(ntriples_input: &str) -> Result<LightGraph> {
+    let mut graph = LightGraph::new();
+
+    NTriplesParser {}
+        .parse_str(ntriples_input)
+        .add_to_graph(&mut graph)
+        .map_err(|e| anyhow::anyhow!("Failed to parse N-Triples input: {}", e))?;
+
+    Ok(graph)
+}
+
+/// Parses an N-Quads string into an RDF graph, ignoring named graphs.
+///
+/// # Parameters
+/// - `nquads_input`: The N-Quads content as a string slice.
+///
+/// # Returns
+/// - `Result<LightGraph>`: The parsed RDF graph on success, or an error on failure.
+pub fn parse_nquads_to_graph(nquads_input: &str) -> Result<LightGraph> {
+    let mut graph = LightGraph::new();
+
+    NQuadsParser {}
+        .parse_str(nquads_input)
+        .filter_quads(|q| q.g().is_none())
+        .to_triples()
+        .add_to_graph(&mut graph)
+        .map_err(|e| anyhow::anyhow!("Failed to parse N-Quads input: {}", e))?;
+
+    Ok(graph)
+}
+
+/// Parses a TriG string into an RDF graph, ignoring named graphs.
+///
+/// # Parameters
+/// - `trig_input`: The TriG content as a string slice.
+///
+/// # Returns
+/// - `Result<LightGraph>`: The parsed RDF graph on success, or an error on failure.
+pub fn parse_trig_to_graph(trig_input: &str) -> Result<LightGraph> {
+    let mut graph = LightGraph::new();
+
+    TriGParser::default()
+        .parse_str(trig_input)
+        .filter_quads(|q| q.g().is_none())
+        .to_triples()
+        .add_to_graph(&mut graph)
+        .map_err(|e| anyhow::anyhow!("Failed to parse TriG input: {}", e))?;
+
+    Ok(graph)
+}
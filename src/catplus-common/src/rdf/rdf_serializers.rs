@@ -1,4 +1,10 @@
-use crate::graph::prefix_map::generate_prefix_map;
+use crate::{
+    graph::prefix_map::PrefixMap,
+    rdf::{
+        jsonld_compaction::{compact_jsonld_with_options, CompactionOptions},
+        rdf_xml::write_rdf_xml,
+    },
+};
 use anyhow::{Context, Result};
 use sophia::{
     api::{
@@ -8,19 +14,23 @@ use sophia::{
     inmem::graph::LightGraph,
     jsonld::{serializer::JsonLdSerializer, JsonLdOptions},
 };
-use sophia_turtle::serializer::turtle::{TurtleConfig, TurtleSerializer};
+use sophia_turtle::serializer::{
+    nq::NqSerializer,
+    nt::NtSerializer,
+    trig::{TrigConfig, TrigSerializer},
+    turtle::{TurtleConfig, TurtleSerializer},
+};
 
 /// Serialize an RDF graph to Turtle format
 ///
 /// # Parameters
 /// - `graph`: A reference to the graph to be serialized.
+/// - `prefix_map`: The prefixes of the document.
 ///
 /// # Returns
 /// A `Result` containing the Turtle serialization as a `String`, or an error if serialization fails.
-pub fn serialize_graph_to_turtle(graph: &LightGraph) -> Result<String> {
-    let prefix_map = generate_prefix_map();
-
-    let config = TurtleConfig::default().with_pretty(true).with_own_prefix_map(prefix_map);
+pub fn serialize_graph_to_turtle(graph: &LightGraph, prefix_map: &PrefixMap) -> Result<String> {
+    let config = TurtleConfig::default().with_pretty(true).with_own_prefix_map(prefix_map.clone());
 
     let mut serializer = TurtleSerializer::new_stringifier_with_config(config);
 
@@ -48,3 +58,93 @@ pub fn serialize_graph_to_jsonld(graph: &LightGraph) -> Result<String> {
     // Extract the JSON-LD string from the serializer
     Ok(serializer.as_str().to_string())
 }
+
+/// Serialize an RDF graph to compacted JSON-LD
+///
+/// The `@context` is generated from the prefix map and the properties of the graph, so
+/// that the document can be read as plain JSON with short keys (e.g. `hasBatch`), see
+/// [compact_jsonld_with_options].
+///
+/// # Parameters
+/// - `graph`: A reference to the graph to be serialized.
+/// - `prefix_map`: The prefixes of the generated `@context`.
+/// - `options`: The frame and the external context of the document, if any.
+///
+/// # Returns
+/// A `Result` containing the compacted JSON-LD document, or an error if serialization fails.
+pub fn serialize_graph_to_compact_jsonld(
+    graph: &LightGraph,
+    prefix_map: &PrefixMap,
+    options: &CompactionOptions,
+) -> Result<serde_json::Value> {
+    let expanded: serde_json::Value = serde_json::from_str(&serialize_graph_to_jsonld(graph)?)
+        .context("Failed to read the expanded JSON-LD")?;
+
+    compact_jsonld_with_options(expanded, prefix_map, options).context("Failed to compact JSON-LD")
+}
+
+/// Serialize an RDF graph to N-Triples, one triple per line
+///
+/// # Parameters
+/// - `graph`: A reference to the graph to be serialized.
+///
+/// # Returns
+/// A `Result` containing the N-Triples serialization as a `String`, or an error if serialization fails.
+pub fn serialize_graph_to_ntriples(graph: &LightGraph) -> Result<String> {
+    let mut serializer = NtSerializer::new_stringifier();
+
+    serializer.serialize_graph(graph).context("Failed to serialize graph to N-Triples")?;
+
+    Ok(serializer.as_str().to_string())
+}
+
+/// Serialize an RDF graph to N-Quads, one quad of the default graph per line
+///
+/// # Parameters
+/// - `graph`: A reference to the graph to be serialized.
+///
+/// # Returns
+/// A `Result` containing the N-Quads serialization as a `String`, or an error if serialization fails.
+pub fn serialize_graph_to_nquads(graph: &LightGraph) -> Result<String> {
+    let mut serializer = NqSerializer::new_stringifier();
+
+    serializer
+        .serialize_quads(graph.triples().to_quads())
+        .context("Failed to serialize graph to N-Quads")?;
+
+    Ok(serializer.as_str().to_string())
+}
+
+/// Serialize an RDF graph to TriG, as its default graph
+///
+/// # Parameters
+/// - `graph`: A reference to the graph to be serialized.
+/// - `prefix_map`: The prefixes of the document.
+///
+/// # Returns
+/// A `Result` containing the TriG serialization as a `String`, or an error if serialization fails.
+pub fn serialize_graph_to_trig(graph: &LightGraph, prefix_map: &PrefixMap) -> Result<String> {
+    let config = TrigConfig::default().with_pretty(true).with_own_prefix_map(prefix_map.clone());
+
+    let mut serializer = TrigSerializer::new_stringifier_with_config(config);
+
+    serializer
+        .serialize_quads(graph.triples().to_quads())
+        .context("Failed to serialize graph to TriG")?;
+
+    Ok(serializer.as_str().to_string())
+}
+
+/// Serialize an RDF graph to RDF/XML
+///
+/// The properties are named with the prefix map, see [super::rdf_xml].
+///
+/// # Parameters
+/// - `graph`: A reference to the graph to be serialized.
+/// - `prefix_map`: The prefixes naming the properties.
+///
+/// # Returns
+/// A `Result` containing the RDF/XML serialization as a `String`, or an error if serialization fails.
+pub fn serialize_graph_to_rdfxml(graph: &LightGraph, prefix_map: &PrefixMap) -> Result<String> {
+    write_rdf_xml(graph, prefix_map).context("Failed to serialize graph to RDF/XML")
+}
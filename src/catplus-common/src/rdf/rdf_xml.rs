@@ -0,0 +1,184 @@
+// A writer for the RDF/XML syntax (https://www.w3.org/TR/rdf-syntax-grammar/), as no
+// serializer of sophia supports it.
+//
+// Every subject is written as an `rdf:Description` element listing its properties, and
+// objects refer to other nodes by IRI or node ID: the document is flat rather than
+// nested, which every RDF/XML reader accepts.
+use crate::graph::prefix_map::PrefixMap;
+use anyhow::{bail, Result};
+use sophia::{api::ns::xsd, inmem::graph::LightGraph};
+use sophia_api::{
+    graph::Graph,
+    term::{Term, TermKind},
+    triple::Triple,
+};
+use std::collections::HashMap;
+
+const RDF: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+
+/// Writes `graph` as an RDF/XML document, naming the properties with `prefixes`.
+pub fn write_rdf_xml(graph: &LightGraph, prefixes: &PrefixMap) -> Result<String> {
+    let mut namespaces = Namespaces::new(prefixes);
+    let mut subjects: Vec<String> = Vec::new();
+    let mut properties: HashMap<String, Vec<String>> = HashMap::new();
+    for t in graph.triples() {
+        let t = t?;
+        let subject = match t.s().kind() {
+            TermKind::Iri => format!("rdf:about=\"{}\"", escape(&t.s().iri().unwrap())),
+            TermKind::BlankNode => node_id(&t.s().bnode_id().unwrap()),
+            _ => bail!("Cannot write a subject of kind {:?} to RDF/XML", t.s().kind()),
+        };
+        let predicate = t.p().iri().expect("predicates are IRIs");
+        let element = namespaces.qualified_name(&predicate)?;
+        let o = t.o();
+        let property = match o.kind() {
+            TermKind::Iri => {
+                format!("<{} rdf:resource=\"{}\"/>", element, escape(&o.iri().unwrap()))
+            }
+            TermKind::BlankNode => format!("<{} {}/>", element, node_id(&o.bnode_id().unwrap())),
+            TermKind::Literal => {
+                let value = escape(&o.lexical_form().unwrap());
+                let attribute = match (o.language_tag(), o.datatype()) {
+                    (Some(tag), _) => format!(" xml:lang=\"{}\"", escape(&tag)),
+                    (None, Some(datatype)) if Term::eq(&datatype, xsd::string) => String::new(),
+                    (None, Some(datatype)) => format!(" rdf:datatype=\"{}\"", escape(&datatype)),
+                    (None, None) => String::new(),
+                };
+                format!("<{}{}>{}</{}>", element, attribute, value, element)
+            }
+            kind => bail!("Cannot write an object of kind {:?} to RDF/XML", kind),
+        };
+        if !properties.contains_key(&subject) {
+            subjects.push(subject.clone());
+        }
+        properties.entry(subject).or_default().push(property);
+    }
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<rdf:RDF");
+    for (prefix, namespace) in namespaces.used() {
+        xml.push_str(&format!("\n    xmlns:{}=\"{}\"", prefix, escape(namespace)));
+    }
+    xml.push_str(">\n");
+    for subject in subjects {
+        xml.push_str(&format!("  <rdf:Description {}>\n", subject));
+        for property in &properties[&subject] {
+            xml.push_str(&format!("    {}\n", property));
+        }
+        xml.push_str("  </rdf:Description>\n");
+    }
+    xml.push_str("</rdf:RDF>\n");
+    Ok(xml)
+}
+
+/// The namespaces of the properties, declared on the root element.
+struct Namespaces {
+    /// (prefix, namespace) of the prefix map, then of the generated prefixes.
+    known: Vec<(String, String)>,
+    used: Vec<usize>,
+}
+
+impl Namespaces {
+    fn new(prefixes: &PrefixMap) -> Self {
+        let mut known = vec![("rdf".to_string(), RDF.to_string())];
+        known.extend(
+            prefixes
+                .iter()
+                .filter(|(prefix, _)| prefix.as_str() != "rdf")
+                .map(|(prefix, iri)| (prefix.as_str().to_string(), iri.as_str().to_string())),
+        );
+        Self { known, used: vec![0] }
+    }
+
+    /// The `prefix:local` name of the element of a property, as XML elements cannot be
+    /// named by full IRIs.
+    fn qualified_name(&mut self, iri: &str) -> Result<String> {
+        let found = self.known.iter().enumerate().find_map(|(i, (prefix, namespace))| {
+            let local = iri.strip_prefix(namespace.as_str())?;
+            is_ncname(local).then(|| (i, format!("{}:{}", prefix, local)))
+        });
+        let (i, name) = match found {
+            Some(found) => found,
+            None => {
+                let Some(split) = iri
+                    .char_indices()
+                    .filter(|(_, c)| matches!(c, '#' | '/' | ':'))
+                    .map(|(i, _)| i + 1)
+                    .rfind(|&i| is_ncname(&iri[i..]))
+                else {
+                    bail!("Cannot write the property <{}> to RDF/XML", iri);
+                };
+                let prefix = format!("ns{}", self.known.len());
+                let name = format!("{}:{}", prefix, &iri[split..]);
+                self.known.push((prefix, iri[..split].to_string()));
+                (self.known.len() - 1, name)
+            }
+        };
+        if !self.used.contains(&i) {
+            self.used.push(i);
+        }
+        Ok(name)
+    }
+
+    fn used(&self) -> impl Iterator<Item = (&str, &str)> {
+        let mut used = self.used.clone();
+        used.sort();
+        used.into_iter().map(|i| (self.known[i].0.as_str(), self.known[i].1.as_str()))
+    }
+}
+
+/// The `rdf:nodeID` attribute of a blank node, whose ID must be an XML name.
+fn node_id(label: &str) -> String {
+    if is_ncname(label) {
+        format!("rdf:nodeID=\"{}\"", label)
+    } else {
+        format!("rdf:nodeID=\"b{}\"", label)
+    }
+}
+
+/// Whether `name` is an XML name without colon, e.g. the local name of a property.
+fn is_ncname(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || matches!(c, '-' | '.' | '_'))
+}
+
+fn escape(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{graph::prefix_map::generate_prefix_map, rdf::rdf_parser::parse_turtle_to_graph};
+
+    #[test]
+    fn test_write_rdf_xml() -> Result<()> {
+        let graph = parse_turtle_to_graph(
+            r#"
+            PREFIX cat: <http://example.org/cat#>
+            PREFIX schema: <https://schema.org/>
+            PREFIX xsd: <http://www.w3.org/2001/XMLSchema#>
+
+            <http://example.org/batch/1> a cat:Batch;
+                schema:name "23 & <24>";
+                cat:hasBatch _:b0.
+            _:b0 <http://example.org/other/temperature> "25.0"^^xsd:double;
+                <http://example.org/other/label> "hot"@en.
+            "#,
+        )?;
+        let xml = write_rdf_xml(&graph, &generate_prefix_map())?;
+
+        let document = roxmltree::Document::parse(&xml)?;
+        let root = document.root_element();
+        assert_eq!(root.tag_name().name(), "RDF");
+        let descriptions: Vec<_> = root.children().filter(|n| n.is_element()).collect();
+        assert_eq!(descriptions.len(), 2);
+        assert!(xml.contains("<rdf:type rdf:resource=\"http://example.org/cat#Batch\"/>"));
+        assert!(xml.contains("<schema:name>23 &amp; &lt;24&gt;</schema:name>"));
+        assert!(xml.contains("<cat:hasBatch rdf:nodeID=\""));
+        assert!(xml.contains("rdf:datatype=\"http://www.w3.org/2001/XMLSchema#double\">25.0</"));
+        assert!(xml.contains("xml:lang=\"en\">hot</"));
+        assert!(xml.contains("=\"http://example.org/other/\""));
+        Ok(())
+    }
+}
@@ -0,0 +1,9 @@
+// SHACL Core validation of the generated graphs.
+//
+// The shapes of a shapes graph (e.g. the shapes of the Cat+ ontology) are read into
+// [shapes::Shapes], which validate a data graph into a [report::ValidationReport].
+//
+// See: https://www.w3.org/TR/shacl/
+pub mod report;
+pub mod shapes;
+pub mod validator;
@@ -0,0 +1,62 @@
+use crate::shacl::shapes::{display_term, OwnedTerm, Path, Severity};
+use std::fmt;
+
+/// The outcome of validating a data graph against [crate::shacl::shapes::Shapes].
+#[derive(Debug, Default)]
+pub struct ValidationReport {
+    pub results: Vec<ValidationResult>,
+}
+
+impl ValidationReport {
+    /// Whether the data graph conforms to the shapes, i.e. there is no result at all.
+    pub fn conforms(&self) -> bool {
+        self.results.is_empty()
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.conforms() {
+            return writeln!(f, "Conforms: true");
+        }
+        writeln!(f, "Conforms: false ({} results)", self.results.len())?;
+        for result in &self.results {
+            writeln!(f, "- {}", result)?;
+        }
+        Ok(())
+    }
+}
+
+/// A constraint of a shape not satisfied by a focus node.
+#[derive(Clone, Debug)]
+pub struct ValidationResult {
+    pub focus_node: OwnedTerm,
+    /// The path of the property shape, if the result was produced by one.
+    pub path: Option<Path>,
+    /// The value node that does not satisfy the constraint, if any.
+    pub value: Option<OwnedTerm>,
+    /// The constraint component, e.g. `sh:MinCountConstraintComponent`.
+    pub constraint: String,
+    pub source_shape: OwnedTerm,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] focus node {}", self.severity, display_term(&self.focus_node))?;
+        if let Some(path) = &self.path {
+            write!(f, ", path {}", path)?;
+        }
+        if let Some(value) = &self.value {
+            write!(f, ", value {}", display_term(value))?;
+        }
+        write!(
+            f,
+            ": {} ({} of shape {})",
+            self.message,
+            self.constraint,
+            display_term(&self.source_shape)
+        )
+    }
+}
@@ -0,0 +1,509 @@
+// The shapes of a SHACL shapes graph, read into constraints that can be checked
+// against a data graph by the validator.
+use crate::{graph::namespaces::sh, rdf::rdf_parser::parse_turtle_to_graph};
+use anyhow::{bail, Context, Result};
+use regex::{Regex, RegexBuilder};
+use sophia::{
+    api::ns::{rdf, rdfs},
+    inmem::graph::LightGraph,
+};
+use sophia_api::{
+    graph::Graph,
+    prelude::Any,
+    term::{matcher::TermMatcher, SimpleTerm, Term, TermKind},
+    triple::Triple,
+};
+use std::{collections::HashMap, fmt};
+
+/// An owned RDF term.
+pub type OwnedTerm = SimpleTerm<'static>;
+
+/// The shapes of a shapes graph.
+#[derive(Debug)]
+pub struct Shapes {
+    pub(super) shapes: Vec<Shape>,
+}
+
+/// A node shape, or a property shape if it has a `path`.
+#[derive(Debug)]
+pub struct Shape {
+    pub id: OwnedTerm,
+    pub path: Option<Path>,
+    pub targets: Vec<Target>,
+    pub constraints: Vec<Constraint>,
+    pub severity: Severity,
+    pub message: Option<String>,
+    pub deactivated: bool,
+}
+
+/// Index of a shape in [Shapes].
+pub type ShapeRef = usize;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Target {
+    Class(OwnedTerm),
+    Node(OwnedTerm),
+    SubjectsOf(OwnedTerm),
+    ObjectsOf(OwnedTerm),
+}
+
+/// A SHACL property path.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Path {
+    Predicate(OwnedTerm),
+    Inverse(Box<Path>),
+    Sequence(Vec<Path>),
+    Alternative(Vec<Path>),
+    ZeroOrMore(Box<Path>),
+    OneOrMore(Box<Path>),
+    ZeroOrOne(Box<Path>),
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |paths: &[Path], separator: &str| {
+            paths.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(separator)
+        };
+        match self {
+            Path::Predicate(iri) => write!(f, "{}", display_term(iri)),
+            Path::Inverse(path) => write!(f, "^{}", path),
+            Path::Sequence(paths) => write!(f, "({})", join(paths, " / ")),
+            Path::Alternative(paths) => write!(f, "({})", join(paths, " | ")),
+            Path::ZeroOrMore(path) => write!(f, "{}*", path),
+            Path::OneOrMore(path) => write!(f, "{}+", path),
+            Path::ZeroOrOne(path) => write!(f, "{}?", path),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Warning,
+    #[default]
+    Violation,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Info => "Info",
+            Severity::Warning => "Warning",
+            Severity::Violation => "Violation",
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeKind {
+    Iri,
+    BlankNode,
+    Literal,
+    BlankNodeOrIri,
+    BlankNodeOrLiteral,
+    IriOrLiteral,
+}
+
+impl NodeKind {
+    pub fn matches(&self, term: &OwnedTerm) -> bool {
+        let kind = term.kind();
+        match self {
+            NodeKind::Iri => kind == TermKind::Iri,
+            NodeKind::BlankNode => kind == TermKind::BlankNode,
+            NodeKind::Literal => kind == TermKind::Literal,
+            NodeKind::BlankNodeOrIri => matches!(kind, TermKind::BlankNode | TermKind::Iri),
+            NodeKind::BlankNodeOrLiteral => {
+                matches!(kind, TermKind::BlankNode | TermKind::Literal)
+            }
+            NodeKind::IriOrLiteral => matches!(kind, TermKind::Iri | TermKind::Literal),
+        }
+    }
+}
+
+/// A SHACL Core constraint component, with its parameters.
+#[derive(Clone, Debug)]
+pub enum Constraint {
+    Class(OwnedTerm),
+    Datatype(OwnedTerm),
+    NodeKind(NodeKind),
+    MinCount(usize),
+    MaxCount(usize),
+    MinExclusive(OwnedTerm),
+    MinInclusive(OwnedTerm),
+    MaxExclusive(OwnedTerm),
+    MaxInclusive(OwnedTerm),
+    MinLength(usize),
+    MaxLength(usize),
+    Pattern(Regex),
+    LanguageIn(Vec<String>),
+    UniqueLang,
+    Equals(OwnedTerm),
+    Disjoint(OwnedTerm),
+    LessThan(OwnedTerm),
+    LessThanOrEquals(OwnedTerm),
+    Not(ShapeRef),
+    And(Vec<ShapeRef>),
+    Or(Vec<ShapeRef>),
+    Xone(Vec<ShapeRef>),
+    Node(ShapeRef),
+    Property(ShapeRef),
+    QualifiedValueShape { shape: ShapeRef, min: Option<usize>, max: Option<usize> },
+    Closed { ignored: Vec<OwnedTerm> },
+    HasValue(OwnedTerm),
+    In(Vec<OwnedTerm>),
+}
+
+impl Constraint {
+    /// The IRI of the constraint component, without the `sh:` prefix.
+    pub fn component(&self) -> &'static str {
+        match self {
+            Constraint::Class(_) => "ClassConstraintComponent",
+            Constraint::Datatype(_) => "DatatypeConstraintComponent",
+            Constraint::NodeKind(_) => "NodeKindConstraintComponent",
+            Constraint::MinCount(_) => "MinCountConstraintComponent",
+            Constraint::MaxCount(_) => "MaxCountConstraintComponent",
+            Constraint::MinExclusive(_) => "MinExclusiveConstraintComponent",
+            Constraint::MinInclusive(_) => "MinInclusiveConstraintComponent",
+            Constraint::MaxExclusive(_) => "MaxExclusiveConstraintComponent",
+            Constraint::MaxInclusive(_) => "MaxInclusiveConstraintComponent",
+            Constraint::MinLength(_) => "MinLengthConstraintComponent",
+            Constraint::MaxLength(_) => "MaxLengthConstraintComponent",
+            Constraint::Pattern(_) => "PatternConstraintComponent",
+            Constraint::LanguageIn(_) => "LanguageInConstraintComponent",
+            Constraint::UniqueLang => "UniqueLangConstraintComponent",
+            Constraint::Equals(_) => "EqualsConstraintComponent",
+            Constraint::Disjoint(_) => "DisjointConstraintComponent",
+            Constraint::LessThan(_) => "LessThanConstraintComponent",
+            Constraint::LessThanOrEquals(_) => "LessThanOrEqualsConstraintComponent",
+            Constraint::Not(_) => "NotConstraintComponent",
+            Constraint::And(_) => "AndConstraintComponent",
+            Constraint::Or(_) => "OrConstraintComponent",
+            Constraint::Xone(_) => "XoneConstraintComponent",
+            Constraint::Node(_) => "NodeConstraintComponent",
+            Constraint::Property(_) => "PropertyConstraintComponent",
+            Constraint::QualifiedValueShape { .. } => "QualifiedValueShapeConstraintComponent",
+            Constraint::Closed { .. } => "ClosedConstraintComponent",
+            Constraint::HasValue(_) => "HasValueConstraintComponent",
+            Constraint::In(_) => "InConstraintComponent",
+        }
+    }
+}
+
+impl Shapes {
+    /// Reads the shapes of a Turtle shapes graph.
+    pub fn from_turtle(turtle: &str) -> Result<Self> {
+        let graph = parse_turtle_to_graph(turtle).context("Invalid shapes graph")?;
+        Self::from_graph(&graph)
+    }
+
+    /// Reads the shapes of a shapes graph: the instances of `sh:NodeShape` and
+    /// `sh:PropertyShape`, the subjects of a target and the shapes they reference.
+    pub fn from_graph(graph: &LightGraph) -> Result<Self> {
+        let mut reader = ShapeReader { graph, shapes: Vec::new(), refs: HashMap::new() };
+        let mut roots: Vec<OwnedTerm> = Vec::new();
+        for class in [sh::NodeShape.as_simple(), sh::PropertyShape.as_simple()] {
+            roots.extend(subjects(graph, rdf::type_, [class]));
+        }
+        for target in [sh::targetClass, sh::targetNode, sh::targetSubjectsOf, sh::targetObjectsOf] {
+            roots.extend(subjects(graph, target, Any));
+        }
+        for root in roots {
+            reader.shape_ref(&root)?;
+        }
+        Ok(Shapes { shapes: reader.shapes })
+    }
+
+    pub fn shapes(&self) -> &[Shape] {
+        &self.shapes
+    }
+}
+
+struct ShapeReader<'a> {
+    graph: &'a LightGraph,
+    shapes: Vec<Shape>,
+    refs: HashMap<OwnedTerm, ShapeRef>,
+}
+
+impl ShapeReader<'_> {
+    /// Reads the shape `id`, once.
+    fn shape_ref(&mut self, id: &OwnedTerm) -> Result<ShapeRef> {
+        if let Some(shape) = self.refs.get(id) {
+            return Ok(*shape);
+        }
+        // The shape is registered before its constraints are read, so that recursive
+        // shapes refer to themselves.
+        let index = self.shapes.len();
+        self.refs.insert(id.clone(), index);
+        self.shapes.push(Shape {
+            id: id.clone(),
+            path: None,
+            targets: Vec::new(),
+            constraints: Vec::new(),
+            severity: Severity::default(),
+            message: None,
+            deactivated: false,
+        });
+        let shape =
+            self.read_shape(id).with_context(|| format!("Invalid shape {}", display_term(id)))?;
+        self.shapes[index] = shape;
+        Ok(index)
+    }
+
+    fn read_shape(&mut self, id: &OwnedTerm) -> Result<Shape> {
+        let graph = self.graph;
+        let path = object(graph, id, sh::path).map(|p| read_path(graph, &p)).transpose()?;
+
+        let mut targets = Vec::new();
+        let is_class = objects(graph, id, rdf::type_)
+            .iter()
+            .any(|t| Term::eq(t, rdfs::Class) || Term::eq(t, sophia::api::ns::owl::Class));
+        if is_class {
+            targets.push(Target::Class(id.clone()));
+        }
+        for (pred, target) in [
+            (sh::targetClass, Target::Class as fn(OwnedTerm) -> Target),
+            (sh::targetNode, Target::Node),
+            (sh::targetSubjectsOf, Target::SubjectsOf),
+            (sh::targetObjectsOf, Target::ObjectsOf),
+        ] {
+            targets.extend(objects(graph, id, pred).into_iter().map(target));
+        }
+
+        let severity = match object(graph, id, sh::severity) {
+            Some(s) if Term::eq(&s, sh::Info) => Severity::Info,
+            Some(s) if Term::eq(&s, sh::Warning) => Severity::Warning,
+            _ => Severity::Violation,
+        };
+        let message =
+            object(graph, id, sh::message).and_then(|m| m.lexical_form().map(|l| l.to_string()));
+        let deactivated = object(graph, id, sh::deactivated)
+            .and_then(|d| d.lexical_form().map(|l| l == "true"))
+            .unwrap_or(false);
+
+        let mut constraints = Vec::new();
+        for (pred, value) in properties(graph, id) {
+            if let Some(constraint) = self.read_constraint(id, &pred, &value)? {
+                constraints.push(constraint);
+            }
+        }
+
+        Ok(Shape { id: id.clone(), path, targets, constraints, severity, message, deactivated })
+    }
+
+    /// Reads the constraint of the parameter `pred`, or `None` if `pred` is not a
+    /// constraint parameter (e.g. `sh:path`) or an auxiliary parameter (e.g. `sh:flags`).
+    fn read_constraint(
+        &mut self,
+        id: &OwnedTerm,
+        pred: &OwnedTerm,
+        value: &OwnedTerm,
+    ) -> Result<Option<Constraint>> {
+        let graph = self.graph;
+        let Some(name) = pred
+            .iri()
+            .and_then(|iri| iri.as_str().strip_prefix(sh::PREFIX.as_str()).map(str::to_string))
+        else {
+            return Ok(None);
+        };
+        let constraint = match name.as_str() {
+            "class" => Constraint::Class(value.clone()),
+            "datatype" => Constraint::Datatype(value.clone()),
+            "nodeKind" => Constraint::NodeKind(read_node_kind(value)?),
+            "minCount" => Constraint::MinCount(integer(value)?),
+            "maxCount" => Constraint::MaxCount(integer(value)?),
+            "minExclusive" => Constraint::MinExclusive(value.clone()),
+            "minInclusive" => Constraint::MinInclusive(value.clone()),
+            "maxExclusive" => Constraint::MaxExclusive(value.clone()),
+            "maxInclusive" => Constraint::MaxInclusive(value.clone()),
+            "minLength" => Constraint::MinLength(integer(value)?),
+            "maxLength" => Constraint::MaxLength(integer(value)?),
+            "pattern" => {
+                let pattern = lexical(value)?;
+                let flags = object(graph, id, sh::flags)
+                    .and_then(|f| f.lexical_form().map(|l| l.to_string()))
+                    .unwrap_or_default();
+                let regex = RegexBuilder::new(&pattern)
+                    .case_insensitive(flags.contains('i'))
+                    .multi_line(flags.contains('m'))
+                    .dot_matches_new_line(flags.contains('s'))
+                    .ignore_whitespace(flags.contains('x'))
+                    .build()
+                    .with_context(|| format!("Invalid sh:pattern '{}'", pattern))?;
+                Constraint::Pattern(regex)
+            }
+            "languageIn" => Constraint::LanguageIn(
+                list(graph, value)?.iter().map(lexical).collect::<Result<_>>()?,
+            ),
+            "uniqueLang" if lexical(value)? == "true" => Constraint::UniqueLang,
+            "equals" => Constraint::Equals(value.clone()),
+            "disjoint" => Constraint::Disjoint(value.clone()),
+            "lessThan" => Constraint::LessThan(value.clone()),
+            "lessThanOrEquals" => Constraint::LessThanOrEquals(value.clone()),
+            "not" => Constraint::Not(self.shape_ref(value)?),
+            "and" => Constraint::And(self.shape_list(value)?),
+            "or" => Constraint::Or(self.shape_list(value)?),
+            "xone" => Constraint::Xone(self.shape_list(value)?),
+            "node" => Constraint::Node(self.shape_ref(value)?),
+            "property" => Constraint::Property(self.shape_ref(value)?),
+            "qualifiedValueShape" => Constraint::QualifiedValueShape {
+                shape: self.shape_ref(value)?,
+                min: object(graph, id, sh::qualifiedMinCount).map(|v| integer(&v)).transpose()?,
+                max: object(graph, id, sh::qualifiedMaxCount).map(|v| integer(&v)).transpose()?,
+            },
+            "closed" if lexical(value)? == "true" => Constraint::Closed {
+                ignored: object(graph, id, sh::ignoredProperties)
+                    .map(|l| list(graph, &l))
+                    .transpose()?
+                    .unwrap_or_default(),
+            },
+            "hasValue" => Constraint::HasValue(value.clone()),
+            "in" => Constraint::In(list(graph, value)?),
+            _ => return Ok(None),
+        };
+        Ok(Some(constraint))
+    }
+
+    fn shape_list(&mut self, list_node: &OwnedTerm) -> Result<Vec<ShapeRef>> {
+        list(self.graph, list_node)?.iter().map(|shape| self.shape_ref(shape)).collect()
+    }
+}
+
+fn read_path(graph: &LightGraph, node: &OwnedTerm) -> Result<Path> {
+    if node.kind() == TermKind::Iri {
+        return Ok(Path::Predicate(node.clone()));
+    }
+    if object(graph, node, rdf::first).is_some() {
+        let paths =
+            list(graph, node)?.iter().map(|p| read_path(graph, p)).collect::<Result<_>>()?;
+        return Ok(Path::Sequence(paths));
+    }
+    let boxed = |pred| -> Result<Option<Box<Path>>> {
+        object(graph, node, pred).map(|p| read_path(graph, &p).map(Box::new)).transpose()
+    };
+    if let Some(path) = boxed(sh::inversePath)? {
+        return Ok(Path::Inverse(path));
+    }
+    if let Some(path) = boxed(sh::zeroOrMorePath)? {
+        return Ok(Path::ZeroOrMore(path));
+    }
+    if let Some(path) = boxed(sh::oneOrMorePath)? {
+        return Ok(Path::OneOrMore(path));
+    }
+    if let Some(path) = boxed(sh::zeroOrOnePath)? {
+        return Ok(Path::ZeroOrOne(path));
+    }
+    if let Some(alternatives) = object(graph, node, sh::alternativePath) {
+        let paths = list(graph, &alternatives)?
+            .iter()
+            .map(|p| read_path(graph, p))
+            .collect::<Result<_>>()?;
+        return Ok(Path::Alternative(paths));
+    }
+    bail!("Unsupported property path {}", display_term(node))
+}
+
+fn read_node_kind(value: &OwnedTerm) -> Result<NodeKind> {
+    for (iri, kind) in [
+        (sh::IRI, NodeKind::Iri),
+        (sh::BlankNode, NodeKind::BlankNode),
+        (sh::Literal, NodeKind::Literal),
+        (sh::BlankNodeOrIRI, NodeKind::BlankNodeOrIri),
+        (sh::BlankNodeOrLiteral, NodeKind::BlankNodeOrLiteral),
+        (sh::IRIOrLiteral, NodeKind::IriOrLiteral),
+    ] {
+        if Term::eq(value, iri) {
+            return Ok(kind);
+        }
+    }
+    bail!("Unknown sh:nodeKind {}", display_term(value))
+}
+
+fn lexical(value: &OwnedTerm) -> Result<String> {
+    value
+        .lexical_form()
+        .map(|l| l.to_string())
+        .with_context(|| format!("Expected a literal, found {}", display_term(value)))
+}
+
+fn integer(value: &OwnedTerm) -> Result<usize> {
+    let lexical = lexical(value)?;
+    lexical
+        .trim()
+        .parse()
+        .with_context(|| format!("Expected a non-negative integer, found '{}'", lexical))
+}
+
+/// The members of the RDF list starting at `node`.
+pub(super) fn list(graph: &LightGraph, node: &OwnedTerm) -> Result<Vec<OwnedTerm>> {
+    let mut members = Vec::new();
+    let mut node = node.clone();
+    while !Term::eq(&node, rdf::nil) {
+        let first = object(graph, &node, rdf::first)
+            .with_context(|| format!("Malformed RDF list at {}", display_term(&node)))?;
+        members.push(first);
+        node = object(graph, &node, rdf::rest)
+            .with_context(|| format!("Malformed RDF list at {}", display_term(&node)))?;
+    }
+    Ok(members)
+}
+
+/// The objects of the triples of `graph` with subject `subject` and predicate `pred`.
+pub(super) fn objects<T: Term>(graph: &LightGraph, subject: &OwnedTerm, pred: T) -> Vec<OwnedTerm> {
+    graph
+        .triples_matching([subject], [pred], Any)
+        .filter_map(Result::ok)
+        .map(|t| t.o().into_term())
+        .collect()
+}
+
+pub(super) fn object<T: Term>(
+    graph: &LightGraph,
+    subject: &OwnedTerm,
+    pred: T,
+) -> Option<OwnedTerm> {
+    objects(graph, subject, pred).into_iter().next()
+}
+
+/// The subjects of the triples of `graph` with predicate `pred` and an object matching `object`.
+pub(super) fn subjects<T: Term, M: TermMatcher>(
+    graph: &LightGraph,
+    pred: T,
+    object: M,
+) -> Vec<OwnedTerm> {
+    graph
+        .triples_matching(Any, [pred], object)
+        .filter_map(Result::ok)
+        .map(|t| t.s().into_term())
+        .collect()
+}
+
+/// The (predicate, object) pairs of the triples of `graph` with subject `subject`.
+pub(super) fn properties(graph: &LightGraph, subject: &OwnedTerm) -> Vec<(OwnedTerm, OwnedTerm)> {
+    graph
+        .triples_matching([subject], Any, Any)
+        .filter_map(Result::ok)
+        .map(|t| (t.p().into_term(), t.o().into_term()))
+        .collect()
+}
+
+/// A Turtle-like representation of a term, for reports and error messages.
+pub fn display_term(term: &OwnedTerm) -> String {
+    match term.kind() {
+        TermKind::Iri => format!("<{}>", term.iri().unwrap().as_str()),
+        TermKind::BlankNode => format!("_:{}", term.bnode_id().unwrap().as_str()),
+        TermKind::Literal => {
+            let lexical = term.lexical_form().unwrap();
+            match (term.language_tag(), term.datatype()) {
+                (Some(tag), _) => format!("{:?}@{}", lexical.as_ref(), tag.as_str()),
+                (None, Some(datatype)) if datatype.as_str().ends_with("#string") => {
+                    format!("{:?}", lexical.as_ref())
+                }
+                (None, Some(datatype)) => {
+                    format!("{:?}^^<{}>", lexical.as_ref(), datatype.as_str())
+                }
+                (None, None) => format!("{:?}", lexical.as_ref()),
+            }
+        }
+        _ => format!("{:?}", term),
+    }
+}
@@ -0,0 +1,668 @@
+// Validation of a data graph against the shapes of SHACL Core.
+//
+// Not supported: SPARQL-based constraints and targets, and `sh:qualifiedValueShapesDisjoint`.
+use crate::shacl::{
+    report::{ValidationReport, ValidationResult},
+    shapes::{
+        display_term, objects, properties, subjects, Constraint, OwnedTerm, Path, Shape, ShapeRef,
+        Shapes, Target,
+    },
+};
+use sophia::{
+    api::ns::{rdf, rdfs, xsd},
+    inmem::graph::LightGraph,
+};
+use sophia_api::term::{Term, TermKind};
+use std::cmp::Ordering;
+
+impl Shapes {
+    /// Validates `data` against the shapes, reporting every unsatisfied constraint of
+    /// every focus node.
+    pub fn validate(&self, data: &LightGraph) -> ValidationReport {
+        let mut validator = Validator { shapes: self, data, in_progress: Vec::new() };
+        let mut results = Vec::new();
+        for (shape, definition) in self.shapes.iter().enumerate() {
+            for focus in validator.focus_nodes(definition) {
+                validator.validate_shape(shape, &focus, &mut results);
+            }
+        }
+        ValidationReport { results }
+    }
+}
+
+struct Validator<'a> {
+    shapes: &'a Shapes,
+    data: &'a LightGraph,
+    /// The (shape, node) pairs being checked by `sh:node`, `sh:not`..., so that recursive
+    /// shapes terminate.
+    in_progress: Vec<(ShapeRef, OwnedTerm)>,
+}
+
+impl Validator<'_> {
+    fn focus_nodes(&self, shape: &Shape) -> Vec<OwnedTerm> {
+        let mut nodes = Vec::new();
+        for target in &shape.targets {
+            let targeted = match target {
+                Target::Class(class) => self.instances(class),
+                Target::Node(node) => vec![node.clone()],
+                Target::SubjectsOf(pred) => subjects(self.data, pred, sophia_api::prelude::Any),
+                Target::ObjectsOf(pred) => self.objects_of(pred),
+            };
+            for node in targeted {
+                push_unique(&mut nodes, node);
+            }
+        }
+        nodes
+    }
+
+    fn validate_shape(
+        &mut self,
+        shape: ShapeRef,
+        focus: &OwnedTerm,
+        results: &mut Vec<ValidationResult>,
+    ) {
+        let definition = &self.shapes.shapes[shape];
+        if definition.deactivated {
+            return;
+        }
+        let values = match &definition.path {
+            Some(path) => self.evaluate(focus, path, false),
+            None => vec![focus.clone()],
+        };
+        for constraint in &definition.constraints {
+            self.check(definition, constraint, focus, &values, results);
+        }
+    }
+
+    /// Whether `node` conforms to `shape`. Nodes already being checked against the shape
+    /// are assumed to conform.
+    fn conforms(&mut self, shape: ShapeRef, node: &OwnedTerm) -> bool {
+        let key = (shape, node.clone());
+        if self.in_progress.iter().any(|(s, n)| *s == shape && Term::eq(n, node)) {
+            return true;
+        }
+        self.in_progress.push(key);
+        let mut results = Vec::new();
+        self.validate_shape(shape, node, &mut results);
+        self.in_progress.pop();
+        results.is_empty()
+    }
+
+    fn check(
+        &mut self,
+        shape: &Shape,
+        constraint: &Constraint,
+        focus: &OwnedTerm,
+        values: &[OwnedTerm],
+        results: &mut Vec<ValidationResult>,
+    ) {
+        let mut report = |value: Option<&OwnedTerm>, message: String| {
+            results.push(ValidationResult {
+                focus_node: focus.clone(),
+                path: shape.path.clone(),
+                value: value.cloned(),
+                constraint: format!("sh:{}", constraint.component()),
+                source_shape: shape.id.clone(),
+                severity: shape.severity,
+                message: shape.message.clone().unwrap_or(message),
+            })
+        };
+        // Reports every value for which `failed` holds.
+        macro_rules! each_value {
+            ($failed:expr, $message:expr) => {
+                for value in values {
+                    if $failed(value) {
+                        report(Some(value), $message(value));
+                    }
+                }
+            };
+        }
+
+        match constraint {
+            Constraint::Class(class) => each_value!(|v| !self.is_instance(v, class), |_| format!(
+                "Value is not an instance of {}",
+                display_term(class)
+            )),
+            Constraint::Datatype(datatype) => each_value!(
+                |v| !has_datatype(v, datatype),
+                |_| format!("Value is not a valid literal of type {}", display_term(datatype))
+            ),
+            Constraint::NodeKind(kind) => each_value!(|v| !kind.matches(v), |_| format!(
+                "Value is not of node kind {:?}",
+                kind
+            )),
+            Constraint::MinCount(min) if values.len() < *min => {
+                report(None, format!("Expected at least {} values, found {}", min, values.len()))
+            }
+            Constraint::MaxCount(max) if values.len() > *max => {
+                report(None, format!("Expected at most {} values, found {}", max, values.len()))
+            }
+            Constraint::MinExclusive(bound) => each_value!(
+                |v| compare(v, bound) != Some(Ordering::Greater),
+                |_| format!("Value is not greater than {}", display_term(bound))
+            ),
+            Constraint::MinInclusive(bound) => each_value!(
+                |v| !matches!(compare(v, bound), Some(Ordering::Greater | Ordering::Equal)),
+                |_| format!("Value is less than {}", display_term(bound))
+            ),
+            Constraint::MaxExclusive(bound) => each_value!(
+                |v| compare(v, bound) != Some(Ordering::Less),
+                |_| format!("Value is not less than {}", display_term(bound))
+            ),
+            Constraint::MaxInclusive(bound) => each_value!(
+                |v| !matches!(compare(v, bound), Some(Ordering::Less | Ordering::Equal)),
+                |_| format!("Value is greater than {}", display_term(bound))
+            ),
+            Constraint::MinLength(min) => each_value!(
+                |v| string_value(v).is_none_or(|s| s.chars().count() < *min),
+                |_| format!("Value is shorter than {} characters", min)
+            ),
+            Constraint::MaxLength(max) => each_value!(
+                |v| string_value(v).is_none_or(|s| s.chars().count() > *max),
+                |_| format!("Value is longer than {} characters", max)
+            ),
+            Constraint::Pattern(regex) => each_value!(
+                |v| string_value(v).is_none_or(|s| !regex.is_match(&s)),
+                |_| format!("Value does not match the pattern '{}'", regex.as_str())
+            ),
+            Constraint::LanguageIn(languages) => each_value!(
+                |v: &OwnedTerm| {
+                    let tag = v.language_tag().map(|t| t.as_str().to_lowercase());
+                    tag.is_none_or(|tag| !languages.iter().any(|l| language_matches(&tag, l)))
+                },
+                |_| format!("Value does not have one of the languages {:?}", languages)
+            ),
+            Constraint::UniqueLang => {
+                let mut tags: Vec<String> = Vec::new();
+                let mut duplicates: Vec<String> = Vec::new();
+                for tag in values.iter().filter_map(|v| v.language_tag()) {
+                    let tag = tag.as_str().to_lowercase();
+                    if tags.contains(&tag) && !duplicates.contains(&tag) {
+                        duplicates.push(tag);
+                    } else {
+                        tags.push(tag);
+                    }
+                }
+                for tag in duplicates {
+                    report(None, format!("Several values have the language '{}'", tag));
+                }
+            }
+            Constraint::Equals(pred) => {
+                let others = objects(self.data, focus, pred);
+                let message = || format!("Values differ from the values of {}", display_term(pred));
+                for value in values.iter().filter(|v| !contains(&others, v)) {
+                    report(Some(value), message());
+                }
+                for value in others.iter().filter(|v| !contains(values, v)) {
+                    report(Some(value), message());
+                }
+            }
+            Constraint::Disjoint(pred) => {
+                let others = objects(self.data, focus, pred);
+                each_value!(|v| contains(&others, v), |_| format!(
+                    "Value is also a value of {}",
+                    display_term(pred)
+                ))
+            }
+            Constraint::LessThan(pred) | Constraint::LessThanOrEquals(pred) => {
+                let or_equal = matches!(constraint, Constraint::LessThanOrEquals(_));
+                let others = objects(self.data, focus, pred);
+                each_value!(
+                    |v| others.iter().any(|o| match compare(v, o) {
+                        Some(Ordering::Less) => false,
+                        Some(Ordering::Equal) => !or_equal,
+                        _ => true,
+                    }),
+                    |_| format!(
+                        "Value is not less than {}the values of {}",
+                        if or_equal { "or equal to " } else { "" },
+                        display_term(pred)
+                    )
+                )
+            }
+            Constraint::Not(not) => each_value!(|v| self.conforms(*not, v), |_| format!(
+                "Value conforms to {}",
+                display_term(&self.shapes.shapes[*not].id)
+            )),
+            Constraint::And(shapes) => {
+                each_value!(|v| !shapes.iter().all(|s| self.conforms(*s, v)), |_| {
+                    "Value does not conform to all the shapes of sh:and".to_string()
+                })
+            }
+            Constraint::Or(shapes) => {
+                each_value!(|v| !shapes.iter().any(|s| self.conforms(*s, v)), |_| {
+                    "Value does not conform to any shape of sh:or".to_string()
+                })
+            }
+            Constraint::Xone(shapes) => each_value!(
+                |v| shapes.iter().filter(|s| self.conforms(**s, v)).count() != 1,
+                |_| "Value does not conform to exactly one shape of sh:xone".to_string()
+            ),
+            Constraint::Node(node) => each_value!(|v| !self.conforms(*node, v), |_| format!(
+                "Value does not conform to {}",
+                display_term(&self.shapes.shapes[*node].id)
+            )),
+            Constraint::Property(property) => {
+                for value in values {
+                    self.validate_shape(*property, value, results);
+                }
+            }
+            Constraint::QualifiedValueShape { shape: qualified, min, max } => {
+                let count = values.iter().filter(|v| self.conforms(*qualified, v)).count();
+                if min.is_some_and(|min| count < min) || max.is_some_and(|max| count > max) {
+                    report(
+                        None,
+                        format!(
+                            "{} values conform to {}, expected between {} and {}",
+                            count,
+                            display_term(&self.shapes.shapes[*qualified].id),
+                            min.unwrap_or(0),
+                            max.map_or("any".to_string(), |m| m.to_string())
+                        ),
+                    );
+                }
+            }
+            Constraint::Closed { ignored } => {
+                let mut allowed: Vec<OwnedTerm> = ignored.clone();
+                for constraint in &shape.constraints {
+                    if let Constraint::Property(property) = constraint {
+                        if let Some(Path::Predicate(pred)) = &self.shapes.shapes[*property].path {
+                            allowed.push(pred.clone());
+                        }
+                    }
+                }
+                for value in values {
+                    for (pred, object) in properties(self.data, value) {
+                        if !contains(&allowed, &pred) {
+                            results.push(ValidationResult {
+                                focus_node: focus.clone(),
+                                path: Some(Path::Predicate(pred.clone())),
+                                value: Some(object),
+                                constraint: format!("sh:{}", constraint.component()),
+                                source_shape: shape.id.clone(),
+                                severity: shape.severity,
+                                message: shape.message.clone().unwrap_or_else(|| {
+                                    format!("Property {} is not allowed", display_term(&pred))
+                                }),
+                            });
+                        }
+                    }
+                }
+            }
+            Constraint::HasValue(expected) if !contains(values, expected) => {
+                report(None, format!("Missing the value {}", display_term(expected)))
+            }
+            Constraint::In(members) => each_value!(|v| !contains(members, v), |_| format!(
+                "Value is not one of {}",
+                members.iter().map(display_term).collect::<Vec<_>>().join(", ")
+            )),
+            _ => {}
+        }
+    }
+
+    /// The nodes reached from `node` by `path` (or by its inverse).
+    fn evaluate(&self, node: &OwnedTerm, path: &Path, inverse: bool) -> Vec<OwnedTerm> {
+        let mut reached = Vec::new();
+        match path {
+            Path::Predicate(pred) if inverse => {
+                for subject in subjects(self.data, pred, [node]) {
+                    push_unique(&mut reached, subject);
+                }
+            }
+            Path::Predicate(pred) => {
+                for object in objects(self.data, node, pred) {
+                    push_unique(&mut reached, object);
+                }
+            }
+            Path::Inverse(path) => return self.evaluate(node, path, !inverse),
+            Path::Sequence(paths) => {
+                let mut current = vec![node.clone()];
+                let steps: Box<dyn Iterator<Item = &Path>> =
+                    if inverse { Box::new(paths.iter().rev()) } else { Box::new(paths.iter()) };
+                for step in steps {
+                    let mut next = Vec::new();
+                    for n in &current {
+                        for reached in self.evaluate(n, step, inverse) {
+                            push_unique(&mut next, reached);
+                        }
+                    }
+                    current = next;
+                }
+                return current;
+            }
+            Path::Alternative(paths) => {
+                for path in paths {
+                    for n in self.evaluate(node, path, inverse) {
+                        push_unique(&mut reached, n);
+                    }
+                }
+            }
+            Path::ZeroOrMore(inner) | Path::OneOrMore(inner) => {
+                if matches!(path, Path::ZeroOrMore(_)) {
+                    reached.push(node.clone());
+                }
+                let mut frontier = vec![node.clone()];
+                while let Some(n) = frontier.pop() {
+                    for next in self.evaluate(&n, inner, inverse) {
+                        if !contains(&reached, &next) {
+                            reached.push(next.clone());
+                            frontier.push(next);
+                        }
+                    }
+                }
+            }
+            Path::ZeroOrOne(path) => {
+                reached.push(node.clone());
+                for n in self.evaluate(node, path, inverse) {
+                    push_unique(&mut reached, n);
+                }
+            }
+        }
+        reached
+    }
+
+    /// The instances of `class` and of its subclasses.
+    fn instances(&self, class: &OwnedTerm) -> Vec<OwnedTerm> {
+        let mut classes = vec![class.clone()];
+        let mut index = 0;
+        while index < classes.len() {
+            for subclass in subjects(self.data, rdfs::subClassOf, [&classes[index]]) {
+                push_unique(&mut classes, subclass);
+            }
+            index += 1;
+        }
+        let mut instances = Vec::new();
+        for class in &classes {
+            for instance in subjects(self.data, rdf::type_, [class]) {
+                push_unique(&mut instances, instance);
+            }
+        }
+        instances
+    }
+
+    fn is_instance(&self, node: &OwnedTerm, class: &OwnedTerm) -> bool {
+        let mut classes = objects(self.data, node, rdf::type_);
+        let mut index = 0;
+        while index < classes.len() {
+            if Term::eq(&classes[index], class) {
+                return true;
+            }
+            for superclass in objects(self.data, &classes[index], rdfs::subClassOf) {
+                push_unique(&mut classes, superclass);
+            }
+            index += 1;
+        }
+        false
+    }
+
+    fn objects_of(&self, pred: &OwnedTerm) -> Vec<OwnedTerm> {
+        use sophia_api::{graph::Graph, prelude::Any, triple::Triple};
+        let mut nodes = Vec::new();
+        for triple in self.data.triples_matching(Any, [pred], Any).filter_map(Result::ok) {
+            push_unique(&mut nodes, triple.o().into_term());
+        }
+        nodes
+    }
+}
+
+fn push_unique(terms: &mut Vec<OwnedTerm>, term: OwnedTerm) {
+    if !contains(terms, &term) {
+        terms.push(term);
+    }
+}
+
+fn contains(terms: &[OwnedTerm], term: &OwnedTerm) -> bool {
+    terms.iter().any(|t| Term::eq(t, term))
+}
+
+/// The string value of an IRI or a literal, as checked by `sh:pattern` and the length
+/// constraints.
+fn string_value(term: &OwnedTerm) -> Option<String> {
+    match term.kind() {
+        TermKind::Iri => term.iri().map(|iri| iri.as_str().to_string()),
+        TermKind::Literal => term.lexical_form().map(|l| l.to_string()),
+        _ => None,
+    }
+}
+
+/// Basic language range matching, as used by `sh:languageIn`.
+fn language_matches(tag: &str, range: &str) -> bool {
+    let range = range.to_lowercase();
+    range == "*" || tag == range || tag.starts_with(&format!("{}-", range))
+}
+
+const NUMERIC_TYPES: [&str; 16] = [
+    "decimal",
+    "integer",
+    "double",
+    "float",
+    "long",
+    "int",
+    "short",
+    "byte",
+    "nonNegativeInteger",
+    "positiveInteger",
+    "nonPositiveInteger",
+    "negativeInteger",
+    "unsignedLong",
+    "unsignedInt",
+    "unsignedShort",
+    "unsignedByte",
+];
+
+/// The local name of the XSD datatype of a literal, if it has one.
+fn xsd_type(term: &OwnedTerm) -> Option<String> {
+    let datatype = term.datatype()?;
+    datatype.as_str().strip_prefix(xsd::PREFIX.as_str()).map(str::to_string)
+}
+
+fn numeric_value(term: &OwnedTerm) -> Option<f64> {
+    let datatype = xsd_type(term)?;
+    if !NUMERIC_TYPES.contains(&datatype.as_str()) {
+        return None;
+    }
+    let lexical = term.lexical_form()?;
+    match lexical.trim() {
+        "INF" | "+INF" => Some(f64::INFINITY),
+        "-INF" => Some(f64::NEG_INFINITY),
+        value => value.parse().ok(),
+    }
+}
+
+/// Compares two literals: numbers by value, literals of the same datatype (e.g. dates)
+/// by lexical form. Other terms are not comparable.
+fn compare(a: &OwnedTerm, b: &OwnedTerm) -> Option<Ordering> {
+    if let (Some(a), Some(b)) = (numeric_value(a), numeric_value(b)) {
+        return a.partial_cmp(&b);
+    }
+    if a.kind() != TermKind::Literal || b.kind() != TermKind::Literal {
+        return None;
+    }
+    if a.datatype()?.as_str() != b.datatype()?.as_str() {
+        return None;
+    }
+    Some(Ord::cmp(a.lexical_form()?.as_ref(), b.lexical_form()?.as_ref()))
+}
+
+/// Whether `term` is a literal of `datatype` with a valid lexical form.
+fn has_datatype(term: &OwnedTerm, datatype: &OwnedTerm) -> bool {
+    let (Some(actual), Some(expected)) = (term.datatype(), datatype.iri()) else {
+        return false;
+    };
+    if actual.as_str() != expected.as_str() {
+        return false;
+    }
+    let Some(lexical) = term.lexical_form() else {
+        return false;
+    };
+    let lexical = lexical.as_ref();
+    match xsd_type(term).as_deref() {
+        Some("double" | "float") => {
+            matches!(lexical, "INF" | "+INF" | "-INF" | "NaN") || lexical.parse::<f64>().is_ok()
+        }
+        Some("decimal") => !lexical.contains(['e', 'E']) && lexical.parse::<f64>().is_ok(),
+        Some("boolean") => matches!(lexical, "true" | "false" | "1" | "0"),
+        Some("dateTime") => is_date_time(lexical),
+        Some(datatype) if NUMERIC_TYPES.contains(&datatype) => {
+            let Ok(value) = lexical.parse::<i128>() else {
+                return false;
+            };
+            match datatype {
+                "nonNegativeInteger" | "unsignedLong" | "unsignedInt" | "unsignedShort"
+                | "unsignedByte" => value >= 0,
+                "positiveInteger" => value > 0,
+                "nonPositiveInteger" => value <= 0,
+                "negativeInteger" => value < 0,
+                "long" => i64::try_from(value).is_ok(),
+                "int" => i32::try_from(value).is_ok(),
+                "short" => i16::try_from(value).is_ok(),
+                "byte" => i8::try_from(value).is_ok(),
+                _ => true,
+            }
+        }
+        _ => true,
+    }
+}
+
+/// Checks the `YYYY-MM-DDThh:mm:ss` part of an `xsd:dateTime`; fractional seconds and
+/// the time zone are not checked.
+fn is_date_time(lexical: &str) -> bool {
+    let bytes = lexical.as_bytes();
+    bytes.len() >= 19
+        && bytes[..19].iter().enumerate().all(|(i, b)| match i {
+            4 | 7 => *b == b'-',
+            10 => *b == b'T',
+            13 | 16 => *b == b':',
+            _ => b.is_ascii_digit(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rdf::rdf_parser::parse_turtle_to_graph;
+
+    const SHAPES: &str = r#"
+        PREFIX sh: <http://www.w3.org/ns/shacl#>
+        PREFIX xsd: <http://www.w3.org/2001/XMLSchema#>
+        PREFIX cat: <http://example.org/cat#>
+        PREFIX schema: <https://schema.org/>
+        PREFIX qudt: <http://qudt.org/schema/qudt/>
+
+        cat:BatchShape a sh:NodeShape;
+            sh:targetClass cat:Batch;
+            sh:property [
+                sh:path schema:name;
+                sh:minCount 1;
+                sh:maxCount 1;
+                sh:datatype xsd:string;
+                sh:pattern "^[0-9]+$"
+            ].
+
+        cat:ActionShape a sh:NodeShape;
+            sh:targetSubjectsOf cat:hasBatch;
+            sh:property [
+                sh:path cat:hasBatch;
+                sh:class cat:Batch;
+                sh:maxCount 1
+            ], [
+                sh:path ( cat:speedInRPM qudt:value );
+                sh:minInclusive 0;
+                sh:maxExclusive 2000
+            ].
+    "#;
+
+    #[test]
+    fn test_validate_graph() -> anyhow::Result<()> {
+        let shapes = Shapes::from_turtle(SHAPES)?;
+        let data = parse_turtle_to_graph(
+            r#"
+            PREFIX cat: <http://example.org/cat#>
+            PREFIX schema: <https://schema.org/>
+            PREFIX qudt: <http://qudt.org/schema/qudt/>
+
+            cat:batch a cat:Batch; schema:name "23".
+            cat:shake cat:hasBatch cat:batch;
+                cat:speedInRPM [ qudt:value 600.0 ].
+            "#,
+        )?;
+        let report = shapes.validate(&data);
+        assert!(report.conforms(), "{}", report);
+
+        let data = parse_turtle_to_graph(
+            r#"
+            PREFIX cat: <http://example.org/cat#>
+            PREFIX schema: <https://schema.org/>
+            PREFIX qudt: <http://qudt.org/schema/qudt/>
+
+            cat:batch a cat:Batch; schema:name "B-23".
+            cat:other a cat:Batch.
+            cat:shake cat:hasBatch cat:batch, cat:notABatch;
+                cat:speedInRPM [ qudt:value 2400.0 ].
+            "#,
+        )?;
+        let report = shapes.validate(&data);
+        let mut components: Vec<&str> =
+            report.results.iter().map(|r| r.constraint.as_str()).collect();
+        components.sort();
+        assert_eq!(
+            components,
+            [
+                "sh:ClassConstraintComponent",
+                "sh:MaxCountConstraintComponent",
+                "sh:MaxExclusiveConstraintComponent",
+                "sh:MinCountConstraintComponent",
+                "sh:PatternConstraintComponent",
+            ]
+        );
+        let class_result =
+            report.results.iter().find(|r| r.constraint == "sh:ClassConstraintComponent").unwrap();
+        assert_eq!(display_term(&class_result.focus_node), "<http://example.org/cat#shake>");
+        assert_eq!(
+            class_result.path.as_ref().map(|p| p.to_string()).as_deref(),
+            Some("<http://example.org/cat#hasBatch>")
+        );
+        assert_eq!(
+            class_result.value.as_ref().map(display_term).as_deref(),
+            Some("<http://example.org/cat#notABatch>")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_logical_and_closed_constraints() -> anyhow::Result<()> {
+        let shapes = Shapes::from_turtle(
+            r#"
+            PREFIX sh: <http://www.w3.org/ns/shacl#>
+            PREFIX ex: <http://example.org/>
+
+            ex:Shape a sh:NodeShape;
+                sh:targetNode ex:a, ex:b;
+                sh:closed true;
+                sh:ignoredProperties ( ex:ignored );
+                sh:property [ sh:path ex:p; sh:in ( ex:x ex:y ) ];
+                sh:or ( [ sh:path ex:p; sh:minCount 1 ] [ sh:path ex:q; sh:minCount 1 ] ).
+            "#,
+        )?;
+        let data = parse_turtle_to_graph(
+            r#"
+            PREFIX ex: <http://example.org/>
+            ex:a ex:p ex:x; ex:ignored 1.
+            ex:b ex:r ex:z.
+            "#,
+        )?;
+        let report = shapes.validate(&data);
+        let components: Vec<(String, &str)> = report
+            .results
+            .iter()
+            .map(|r| (display_term(&r.focus_node), r.constraint.as_str()))
+            .collect();
+        assert_eq!(components.len(), 2, "{}", report);
+        assert!(components.iter().all(|(focus, _)| focus == "<http://example.org/b>"));
+        assert!(components.iter().any(|(_, c)| *c == "sh:ClosedConstraintComponent"));
+        assert!(components.iter().any(|(_, c)| *c == "sh:OrConstraintComponent"));
+
+        Ok(())
+    }
+}
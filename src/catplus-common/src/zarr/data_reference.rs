@@ -0,0 +1,19 @@
+use crate::zarr::zarr_array::DataType;
+use serde::{Deserialize, Serialize};
+
+/// Where and how an array was written to a Zarr store.
+///
+/// Returned by [crate::zarr::zarr_store::ZarrStore::write_array] and inserted into the
+/// graph so that the array can be found from its RDF metadata.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DataReference {
+    /// File name of the Zarr store, which is written next to the output file, or `None`
+    /// if the array has not been written to a store. The graph is then the same wherever
+    /// the converter runs.
+    pub store_path: Option<String>,
+    /// Path of the array inside the store.
+    pub array_path: String,
+    pub shape: Vec<u64>,
+    pub data_type: DataType,
+    pub chunk_shape: Vec<u64>,
+}
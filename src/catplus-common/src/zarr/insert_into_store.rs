@@ -0,0 +1,37 @@
+use crate::zarr::zarr_store::ZarrStore;
+
+/// InsertIntoStore provides a trait to write the numeric data carried by a type
+/// (spectra, chromatograms, time series) into a Zarr store.
+///
+/// Types that only carry metadata keep the default implementation, which writes nothing.
+pub trait InsertIntoStore {
+    /// Writes the arrays of `self` into `store`, recording where each array was written.
+    fn insert_into_store(&mut self, _store: &ZarrStore) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// Default implementation for [Option<T>].
+impl<T> InsertIntoStore for Option<T>
+where
+    T: InsertIntoStore,
+{
+    fn insert_into_store(&mut self, store: &ZarrStore) -> anyhow::Result<()> {
+        if let Some(v) = self.as_mut() {
+            v.insert_into_store(store)?
+        }
+        Ok(())
+    }
+}
+
+impl<T> InsertIntoStore for Vec<T>
+where
+    T: InsertIntoStore,
+{
+    fn insert_into_store(&mut self, store: &ZarrStore) -> anyhow::Result<()> {
+        for item in self.iter_mut() {
+            item.insert_into_store(store)?;
+        }
+        Ok(())
+    }
+}
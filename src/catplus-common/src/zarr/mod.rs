@@ -0,0 +1,4 @@
+pub mod data_reference;
+pub mod insert_into_store;
+pub mod zarr_array;
+pub mod zarr_store;
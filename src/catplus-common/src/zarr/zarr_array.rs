@@ -0,0 +1,168 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+/// Zarr v3 data types supported by the store writer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DataType {
+    Float64,
+    Float32,
+    Int64,
+    Int32,
+    Uint8,
+}
+
+impl DataType {
+    /// The name of the data type in the Zarr v3 metadata.
+    pub fn name(&self) -> &'static str {
+        match self {
+            DataType::Float64 => "float64",
+            DataType::Float32 => "float32",
+            DataType::Int64 => "int64",
+            DataType::Int32 => "int32",
+            DataType::Uint8 => "uint8",
+        }
+    }
+
+    /// The fill value used for missing elements and for padding edge chunks.
+    pub fn fill_value(&self) -> Value {
+        match self {
+            DataType::Float64 | DataType::Float32 => json!(0.0),
+            DataType::Int64 | DataType::Int32 | DataType::Uint8 => json!(0),
+        }
+    }
+}
+
+/// The values of an array, stored flat in row-major (C) order.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ArrayValues {
+    Float64(Vec<f64>),
+    Float32(Vec<f32>),
+    Int64(Vec<i64>),
+    Int32(Vec<i32>),
+    Uint8(Vec<u8>),
+}
+
+impl ArrayValues {
+    pub fn data_type(&self) -> DataType {
+        match self {
+            ArrayValues::Float64(_) => DataType::Float64,
+            ArrayValues::Float32(_) => DataType::Float32,
+            ArrayValues::Int64(_) => DataType::Int64,
+            ArrayValues::Int32(_) => DataType::Int32,
+            ArrayValues::Uint8(_) => DataType::Uint8,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            ArrayValues::Float64(v) => v.len(),
+            ArrayValues::Float32(v) => v.len(),
+            ArrayValues::Int64(v) => v.len(),
+            ArrayValues::Int32(v) => v.len(),
+            ArrayValues::Uint8(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// An N-dimensional numeric array (e.g. a spectrum, a chromatogram or a time series).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NdArray {
+    pub shape: Vec<u64>,
+    pub values: ArrayValues,
+}
+
+impl NdArray {
+    /// Creates an array of the given `shape`, failing if the number of values does not match.
+    pub fn new(shape: Vec<u64>, values: ArrayValues) -> Result<Self> {
+        let expected: u64 = shape.iter().product();
+        if expected != values.len() as u64 {
+            bail!("Array of shape {:?} expects {} values, got {}", shape, expected, values.len());
+        }
+        Ok(Self { shape, values })
+    }
+
+    /// Creates a one-dimensional array from `values`.
+    pub fn vector(values: ArrayValues) -> Self {
+        Self { shape: vec![values.len() as u64], values }
+    }
+
+    pub fn data_type(&self) -> DataType {
+        self.values.data_type()
+    }
+}
+
+/// Compression codec applied to every chunk after it is serialized to bytes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip {
+        level: u32,
+    },
+}
+
+/// Chunking and codec configuration of a Zarr array.
+#[derive(Clone, Debug, Default)]
+pub struct ArrayConfig {
+    /// Shape of a chunk; the whole array is stored as a single chunk when `None`.
+    pub chunk_shape: Option<Vec<u64>>,
+    pub compression: Compression,
+    pub dimension_names: Option<Vec<String>>,
+    pub attributes: Map<String, Value>,
+}
+
+impl ArrayConfig {
+    pub fn with_chunk_shape(mut self, chunk_shape: Vec<u64>) -> Self {
+        self.chunk_shape = Some(chunk_shape);
+        self
+    }
+
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    pub fn with_dimension_names(mut self, names: Vec<String>) -> Self {
+        self.dimension_names = Some(names);
+        self
+    }
+
+    pub fn with_attribute(mut self, key: &str, value: Value) -> Self {
+        self.attributes.insert(key.to_string(), value);
+        self
+    }
+
+    /// Resolves the chunk shape used for `shape`, checking it against the array rank.
+    pub(crate) fn resolve_chunk_shape(&self, shape: &[u64]) -> Result<Vec<u64>> {
+        let chunk_shape = match &self.chunk_shape {
+            Some(chunk_shape) => chunk_shape.clone(),
+            None => shape.iter().map(|&dim| dim.max(1)).collect(),
+        };
+        if chunk_shape.len() != shape.len() {
+            bail!(
+                "Chunk shape {:?} does not match the dimensionality of shape {:?}",
+                chunk_shape,
+                shape
+            );
+        }
+        if chunk_shape.contains(&0) {
+            bail!("Chunk shape {:?} must not contain zero-sized dimensions", chunk_shape);
+        }
+        Ok(chunk_shape)
+    }
+
+    /// The Zarr v3 `codecs` pipeline for this configuration.
+    pub(crate) fn codecs(&self) -> Value {
+        let mut codecs = vec![json!({"name": "bytes", "configuration": {"endian": "little"}})];
+        if let Compression::Gzip { level } = self.compression {
+            codecs.push(json!({"name": "gzip", "configuration": {"level": level}}));
+        }
+        Value::Array(codecs)
+    }
+}
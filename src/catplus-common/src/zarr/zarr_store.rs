@@ -0,0 +1,395 @@
+use crate::{
+    graph::graph_builder::GraphBuilder,
+    zarr::{
+        data_reference::DataReference,
+        zarr_array::{ArrayConfig, ArrayValues, Compression, NdArray},
+    },
+};
+use anyhow::{bail, Context, Result};
+use flate2::{write::GzEncoder, Compression as GzLevel};
+use serde_json::{json, Map, Value};
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Name of the metadata document of every Zarr v3 node.
+const METADATA_FILE: &str = "zarr.json";
+
+/// Root group attribute holding the compacted JSON-LD metadata of the store.
+pub const JSONLD_ATTRIBUTE: &str = "jsonld";
+
+/// A Zarr v3 directory store.
+///
+/// Groups and arrays are addressed by `/`-separated paths relative to the root
+/// group, e.g. `actions/0/chromatogram`. Missing parent groups are created on the fly.
+///
+/// # public methods:
+/// * create: creates (or recreates) a store with an empty root group
+/// * stage: creates a store aside, moved into place by publish once complete
+/// * create_group: adds a group with the given attributes
+/// * set_attributes: replaces the attributes of an existing group
+/// * insert_graph: embeds the RDF metadata as JSON-LD into the root group
+/// * write_array: writes an N-dimensional array with its chunks and returns a reference to it
+pub struct ZarrStore {
+    root: PathBuf,
+    /// The directory the staged store is moved to by [ZarrStore::publish].
+    target: Option<PathBuf>,
+}
+
+impl ZarrStore {
+    /// Creates a new store at `root` with an empty root group.
+    ///
+    /// An existing Zarr store at `root` is replaced; any other existing path is an error.
+    pub fn create(root: impl AsRef<Path>) -> Result<Self> {
+        let root = root.as_ref().to_path_buf();
+        remove_store(&root)?;
+        fs::create_dir_all(&root)
+            .with_context(|| format!("Failed to create Zarr store '{}'", root.display()))?;
+
+        let store = Self { root, target: None };
+        store.write_group_metadata(&store.root, Map::new())?;
+        Ok(store)
+    }
+
+    /// Creates a new store to be moved to `root` by [ZarrStore::publish], so that an
+    /// existing store at `root` is only replaced by a complete one. Until then, the store
+    /// is written next to `root` (e.g. `.batch.zarr.partial` for `batch.zarr`) and removed
+    /// if dropped.
+    ///
+    /// Any existing path at `root` other than a Zarr store is an error.
+    pub fn stage(root: impl AsRef<Path>) -> Result<Self> {
+        let target = root.as_ref().to_path_buf();
+        if target.exists() && !target.join(METADATA_FILE).is_file() {
+            bail!("'{}' exists and is not a Zarr store", target.display());
+        }
+        let name = target
+            .file_name()
+            .with_context(|| format!("'{}' is not a file name", target.display()))?;
+        let staging = target.with_file_name(format!(".{}.partial", name.to_string_lossy()));
+        let mut store = Self::create(staging)?;
+        store.target = Some(target);
+        Ok(store)
+    }
+
+    /// Moves a store created by [ZarrStore::stage] into place, replacing the existing
+    /// store.
+    pub fn publish(mut self) -> Result<()> {
+        let Some(target) = self.target.take() else {
+            return Ok(());
+        };
+        remove_store(&target)?;
+        fs::rename(&self.root, &target)
+            .with_context(|| format!("Failed to move Zarr store '{}' into place", target.display()))
+    }
+
+    /// The directory of the store on disk.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Creates a group at `path` with `attributes`, creating missing parent groups.
+    pub fn create_group(&self, path: &str, attributes: Map<String, Value>) -> Result<()> {
+        let dir = self.node_dir(path)?;
+        self.write_group_metadata(&dir, attributes)
+    }
+
+    /// Replaces the attributes of the group at `path` (`""` for the root group).
+    pub fn set_attributes(&self, path: &str, attributes: Map<String, Value>) -> Result<()> {
+        let dir = self.node_dir(path)?;
+        let metadata_path = dir.join(METADATA_FILE);
+        let content = fs::read_to_string(&metadata_path)
+            .with_context(|| format!("No Zarr node found at '{}'", dir.display()))?;
+        let mut metadata: Value = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid Zarr metadata '{}'", metadata_path.display()))?;
+        if metadata["node_type"] != "group" {
+            bail!("'{}' is not a Zarr group", path);
+        }
+        metadata["attributes"] = Value::Object(attributes);
+        write_json(&metadata_path, &metadata)
+    }
+
+    /// Embeds the graph of `graph_builder` as compacted JSON-LD into the root group
+    /// attributes, under [JSONLD_ATTRIBUTE], so that the store is self-describing.
+    pub fn insert_graph(&self, graph_builder: &GraphBuilder) -> Result<()> {
+        let mut attributes = Map::new();
+        attributes.insert(JSONLD_ATTRIBUTE.to_string(), graph_builder.to_compact_jsonld()?);
+        self.set_attributes("", attributes)
+    }
+
+    /// Writes `array` at `path` with the chunking and codecs given in `config`.
+    ///
+    /// Edge chunks are padded with the fill value of the data type, as required by Zarr v3.
+    ///
+    /// # Returns
+    /// A [DataReference] locating the written array, or an error if writing fails.
+    pub fn write_array(
+        &self,
+        path: &str,
+        array: &NdArray,
+        config: &ArrayConfig,
+    ) -> Result<DataReference> {
+        let chunk_shape = config.resolve_chunk_shape(&array.shape)?;
+        if let Some(names) = &config.dimension_names {
+            if names.len() != array.shape.len() {
+                bail!("Expected {} dimension names, got {}", array.shape.len(), names.len());
+            }
+        }
+
+        let dir = self.node_dir(path)?;
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create array directory '{}'", dir.display()))?;
+
+        let mut metadata = json!({
+            "zarr_format": 3,
+            "node_type": "array",
+            "shape": array.shape,
+            "data_type": array.data_type().name(),
+            "chunk_grid": {
+                "name": "regular",
+                "configuration": {"chunk_shape": chunk_shape},
+            },
+            "chunk_key_encoding": {
+                "name": "default",
+                "configuration": {"separator": "/"},
+            },
+            "fill_value": array.data_type().fill_value(),
+            "codecs": config.codecs(),
+            "attributes": config.attributes,
+        });
+        if let Some(names) = &config.dimension_names {
+            metadata["dimension_names"] = json!(names);
+        }
+        write_json(&dir.join(METADATA_FILE), &metadata)?;
+
+        let grid: Vec<u64> =
+            array.shape.iter().zip(&chunk_shape).map(|(dim, chunk)| dim.div_ceil(*chunk)).collect();
+        for chunk_index in indices(&grid) {
+            let bytes = encode_chunk(array, &chunk_shape, &chunk_index);
+            let bytes = compress(bytes, &config.compression)?;
+
+            let mut chunk_path = dir.join("c");
+            for index in &chunk_index {
+                chunk_path.push(index.to_string());
+            }
+            if let Some(parent) = chunk_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&chunk_path, bytes)
+                .with_context(|| format!("Failed to write chunk '{}'", chunk_path.display()))?;
+        }
+
+        Ok(DataReference {
+            store_path: self
+                .target
+                .as_ref()
+                .unwrap_or(&self.root)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned()),
+            array_path: path.trim_matches('/').to_string(),
+            shape: array.shape.clone(),
+            data_type: array.data_type(),
+            chunk_shape,
+        })
+    }
+
+    /// Resolves the directory of the node at `path` and makes sure all its parents are groups.
+    fn node_dir(&self, path: &str) -> Result<PathBuf> {
+        let mut dir = self.root.clone();
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        for (i, segment) in segments.iter().enumerate() {
+            if *segment == "." || *segment == ".." || segment.starts_with("__") {
+                bail!("Invalid Zarr node name '{}' in path '{}'", segment, path);
+            }
+            dir.push(segment);
+            let is_last = i + 1 == segments.len();
+            if !is_last && !dir.join(METADATA_FILE).is_file() {
+                self.write_group_metadata(&dir, Map::new())?;
+            }
+        }
+        Ok(dir)
+    }
+
+    fn write_group_metadata(&self, dir: &Path, attributes: Map<String, Value>) -> Result<()> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create group directory '{}'", dir.display()))?;
+        let metadata = json!({
+            "zarr_format": 3,
+            "node_type": "group",
+            "attributes": attributes,
+        });
+        write_json(&dir.join(METADATA_FILE), &metadata)
+    }
+}
+
+impl Drop for ZarrStore {
+    /// Removes a staged store which was not published, e.g. as the conversion failed.
+    fn drop(&mut self) {
+        if self.target.is_some() {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+}
+
+/// Removes the Zarr store at `root`, if any; any other existing path is an error.
+fn remove_store(root: &Path) -> Result<()> {
+    if !root.exists() {
+        return Ok(());
+    }
+    if !root.join(METADATA_FILE).is_file() {
+        bail!("'{}' exists and is not a Zarr store", root.display());
+    }
+    fs::remove_dir_all(root)
+        .with_context(|| format!("Failed to remove Zarr store '{}'", root.display()))
+}
+
+fn write_json(path: &Path, value: &Value) -> Result<()> {
+    let content = serde_json::to_string_pretty(value)?;
+    fs::write(path, content).with_context(|| format!("Failed to write '{}'", path.display()))
+}
+
+/// Iterates over all indices of an N-dimensional grid of the given `shape` in row-major order.
+fn indices(shape: &[u64]) -> impl Iterator<Item = Vec<u64>> + '_ {
+    let total: u64 = shape.iter().product();
+    (0..total).map(move |mut flat| {
+        let mut index = vec![0; shape.len()];
+        for (i, dim) in shape.iter().enumerate().rev() {
+            index[i] = flat % dim;
+            flat /= dim;
+        }
+        index
+    })
+}
+
+/// Serializes one chunk of `array` to little-endian bytes, padding out-of-bounds elements.
+fn encode_chunk(array: &NdArray, chunk_shape: &[u64], chunk_index: &[u64]) -> Vec<u8> {
+    match &array.values {
+        ArrayValues::Float64(v) => encode_values(v, &array.shape, chunk_shape, chunk_index),
+        ArrayValues::Float32(v) => encode_values(v, &array.shape, chunk_shape, chunk_index),
+        ArrayValues::Int64(v) => encode_values(v, &array.shape, chunk_shape, chunk_index),
+        ArrayValues::Int32(v) => encode_values(v, &array.shape, chunk_shape, chunk_index),
+        ArrayValues::Uint8(v) => encode_values(v, &array.shape, chunk_shape, chunk_index),
+    }
+}
+
+fn encode_values<T: Element>(
+    values: &[T],
+    shape: &[u64],
+    chunk_shape: &[u64],
+    chunk_index: &[u64],
+) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(chunk_shape.iter().product::<u64>() as usize * T::SIZE);
+    for local in indices(chunk_shape) {
+        let mut flat = 0;
+        let mut in_bounds = true;
+        for (d, dim) in shape.iter().enumerate() {
+            let global = chunk_index[d] * chunk_shape[d] + local[d];
+            in_bounds &= global < *dim;
+            flat = flat * dim + global;
+        }
+        let value = if in_bounds { values[flat as usize] } else { T::default() };
+        value.extend_le(&mut bytes);
+    }
+    bytes
+}
+
+fn compress(bytes: Vec<u8>, compression: &Compression) -> Result<Vec<u8>> {
+    match compression {
+        Compression::None => Ok(bytes),
+        Compression::Gzip { level } => {
+            let mut encoder = GzEncoder::new(Vec::new(), GzLevel::new(*level));
+            encoder.write_all(&bytes)?;
+            Ok(encoder.finish()?)
+        }
+    }
+}
+
+/// A numeric element that can be written with the Zarr `bytes` codec.
+trait Element: Copy + Default {
+    const SIZE: usize;
+    fn extend_le(self, bytes: &mut Vec<u8>);
+}
+
+macro_rules! impl_element {
+    ($($t:ty),*) => {
+        $(
+            impl Element for $t {
+                const SIZE: usize = std::mem::size_of::<$t>();
+                fn extend_le(self, bytes: &mut Vec<u8>) {
+                    bytes.extend_from_slice(&self.to_le_bytes());
+                }
+            }
+        )*
+    };
+}
+
+impl_element!(f64, f32, i64, i32, u8);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn test_write_chunked_array() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let store = ZarrStore::create(dir.path().join("data.zarr"))?;
+        let array = NdArray::new(vec![3, 2], ArrayValues::Int32(vec![1, 2, 3, 4, 5, 6]))?;
+        let config = ArrayConfig::default()
+            .with_chunk_shape(vec![2, 2])
+            .with_compression(Compression::Gzip { level: 5 });
+        let reference = store.write_array("runs/0/signal", &array, &config)?;
+        assert_eq!(reference.array_path, "runs/0/signal");
+        assert_eq!(reference.chunk_shape, vec![2, 2]);
+
+        let metadata: Value = serde_json::from_str(&fs::read_to_string(
+            store.root().join("runs/0/signal/zarr.json"),
+        )?)?;
+        assert_eq!(metadata["shape"], json!([3, 2]));
+        assert_eq!(metadata["chunk_grid"]["configuration"]["chunk_shape"], json!([2, 2]));
+        assert_eq!(metadata["codecs"][1]["name"], "gzip");
+        assert!(store.root().join("runs/0/zarr.json").is_file());
+
+        // The second chunk holds the last row, padded with the fill value.
+        let mut decoded = Vec::new();
+        GzDecoder::new(fs::File::open(store.root().join("runs/0/signal/c/1/0"))?)
+            .read_to_end(&mut decoded)?;
+        let expected: Vec<u8> = [5i32, 6, 0, 0].iter().flat_map(|v| v.to_le_bytes()).collect();
+        assert_eq!(decoded, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_refuses_foreign_directory() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        assert!(ZarrStore::create(dir.path()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_staged_store_replaces_store_once_published() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let root = dir.path().join("data.zarr");
+        let array = NdArray::new(vec![2], ArrayValues::Int32(vec![1, 2]))?;
+        ZarrStore::create(&root)?.write_array("old", &array, &ArrayConfig::default())?;
+
+        // A dropped store leaves the existing one untouched.
+        let store = ZarrStore::stage(&root)?;
+        store.write_array("new", &array, &ArrayConfig::default())?;
+        drop(store);
+        assert!(root.join("old/zarr.json").is_file());
+        assert_eq!(fs::read_dir(dir.path())?.count(), 1);
+
+        let store = ZarrStore::stage(&root)?;
+        let reference = store.write_array("new", &array, &ArrayConfig::default())?;
+        assert_eq!(reference.store_path.as_deref(), Some("data.zarr"));
+        store.publish()?;
+        assert!(root.join("new/zarr.json").is_file());
+        assert!(!root.join("old").exists());
+        assert_eq!(fs::read_dir(dir.path())?.count(), 1);
+        Ok(())
+    }
+}
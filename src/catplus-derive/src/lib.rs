@@ -0,0 +1,234 @@
+//! `#[derive(InsertIntoGraph)]` for the models of `catplus-common`.
+//!
+//! Every field of the struct is mapped with an `rdf` attribute:
+//!
+//! * `#[rdf(pred = "cat::role")]` attaches the value of the field with this predicate.
+//!   Strings and numbers are attached as literals, units and action names as IRIs,
+//!   other values as nodes; `Option` and `Vec` fields attach zero, one or more values.
+//! * `#[rdf(pred = "...", datatype = "xsd::dateTime")]` attaches a string (or an
+//!   optional string) as a literal of this datatype.
+//! * `#[rdf(pred = "...", reverse)]` attaches the node of the value to the node of the
+//!   struct, i.e. the value is the subject of the triple.
+//! * `#[rdf(pred = "...", ordered)]` also writes the position of each item of a `Vec` (or
+//!   optional `Vec`) field of several items as its `schema:position`, so that the list
+//!   can be read back in order.
+//! * `#[rdf(flatten)]` inserts the triples of the value on the node of the struct.
+//! * `#[rdf(skip)]` leaves the field out of the graph.
+//!
+//! On the struct, `#[rdf(type = "cat::Sample")]` adds an `rdf:type` triple, while
+//! `#[rdf(get_uri = "Self::uri")]` and `#[rdf(attach_into = "Self::attach")]` name the
+//! functions implementing the corresponding methods of the trait, if the defaults do not
+//! fit.
+//!
+//! The mapping can be overridden at runtime by model (the name of the struct) and field,
+//! see `catplus_common::graph::mapping`; flattened fields are mapped with their own model.
+//!
+//! The generated code refers to the `catplus_common` crate, which re-exports the macro
+//! along with the trait.
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, quote_spanned};
+use syn::{
+    parse_macro_input, spanned::Spanned, Data, DeriveInput, Error, Fields, LitStr, Path, Type,
+};
+
+#[proc_macro_derive(InsertIntoGraph, attributes(rdf))]
+pub fn derive_insert_into_graph(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input).unwrap_or_else(Error::into_compile_error).into()
+}
+
+/// The `rdf` attribute of the struct.
+#[derive(Default)]
+struct StructAttr {
+    rdf_type: Option<Path>,
+    get_uri: Option<Path>,
+    attach_into: Option<Path>,
+}
+
+/// The `rdf` attribute of a field.
+#[derive(Default)]
+struct FieldAttr {
+    pred: Option<Path>,
+    datatype: Option<Path>,
+    reverse: bool,
+    ordered: bool,
+    flatten: bool,
+    skip: bool,
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new(input.span(), "InsertIntoGraph can only be derived for structs"));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(Error::new(input.span(), "InsertIntoGraph needs named fields"));
+    };
+
+    let attr = parse_struct_attr(&input)?;
+    let derive = quote!(::catplus_common::graph::insert_into::derive);
+
+    let name = &input.ident;
+    let model = name.to_string();
+    let class = match attr.rdf_type {
+        Some(class) => quote!(Some(#derive::as_simple(&#class))),
+        None => quote!(None),
+    };
+    let mut statements = Vec::new();
+    for field in &fields.named {
+        statements.extend(field_statement(field, &model, &derive)?);
+    }
+    let get_uri = attr.get_uri.map(|path| {
+        quote! {
+            fn get_uri(
+                &self,
+                builder: &mut #derive::GraphBuilder,
+            ) -> #derive::Result<#derive::SimpleTerm<'static>> {
+                #path(self, builder)
+            }
+        }
+    });
+    let attach_into = attr.attach_into.map(|path| {
+        quote! {
+            fn attach_into(
+                &self,
+                builder: &mut #derive::GraphBuilder,
+                attach: ::catplus_common::graph::insert_into::Link,
+            ) -> #derive::Result<()> {
+                #path(self, builder, attach)
+            }
+        }
+    });
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::catplus_common::graph::insert_into::InsertIntoGraph
+            for #name #ty_generics #where_clause
+        {
+            fn insert_into(
+                &self,
+                builder: &mut #derive::GraphBuilder,
+                iri: #derive::SimpleTerm,
+            ) -> #derive::Result<()> {
+                #derive::attach_class(builder, &iri, #model, #class)?;
+                #(#statements)*
+                Ok(())
+            }
+
+            #get_uri
+            #attach_into
+        }
+    })
+}
+
+fn parse_struct_attr(input: &DeriveInput) -> syn::Result<StructAttr> {
+    let mut attr = StructAttr::default();
+    for a in input.attrs.iter().filter(|a| a.path().is_ident("rdf")) {
+        a.parse_nested_meta(|meta| {
+            if meta.path.is_ident("type") {
+                attr.rdf_type = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+            } else if meta.path.is_ident("get_uri") {
+                attr.get_uri = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+            } else if meta.path.is_ident("attach_into") {
+                attr.attach_into = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+            } else {
+                return Err(meta.error("expected `type`, `get_uri` or `attach_into`"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(attr)
+}
+
+fn parse_field_attr(field: &syn::Field) -> syn::Result<FieldAttr> {
+    let mut attr = FieldAttr::default();
+    for a in field.attrs.iter().filter(|a| a.path().is_ident("rdf")) {
+        a.parse_nested_meta(|meta| {
+            if meta.path.is_ident("pred") {
+                attr.pred = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+            } else if meta.path.is_ident("datatype") {
+                attr.datatype = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+            } else if meta.path.is_ident("reverse") {
+                attr.reverse = true;
+            } else if meta.path.is_ident("ordered") {
+                attr.ordered = true;
+            } else if meta.path.is_ident("flatten") {
+                attr.flatten = true;
+            } else if meta.path.is_ident("skip") {
+                attr.skip = true;
+            } else {
+                return Err(meta.error(
+                    "expected `pred`, `datatype`, `reverse`, `ordered`, `flatten` or `skip`",
+                ));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(attr)
+}
+
+/// The statement inserting `field`, if any.
+fn field_statement(
+    field: &syn::Field,
+    model: &str,
+    derive: &TokenStream2,
+) -> syn::Result<Option<TokenStream2>> {
+    let attr = parse_field_attr(field)?;
+    let ident = field.ident.as_ref().expect("named field");
+    let span = field.span();
+
+    if attr.skip {
+        return Ok(None);
+    }
+    if attr.flatten {
+        if attr.pred.is_some() || attr.datatype.is_some() || attr.reverse || attr.ordered {
+            return Err(Error::new(span, "`flatten` cannot be combined with other options"));
+        }
+        return Ok(Some(quote_spanned! {span=>
+            ::catplus_common::graph::insert_into::InsertIntoGraph::insert_into(
+                &self.#ident,
+                builder,
+                iri.clone(),
+            )?;
+        }));
+    }
+    let Some(pred) = attr.pred else {
+        return Err(Error::new(
+            span,
+            "expected #[rdf(pred = \"...\")], #[rdf(flatten)] or #[rdf(skip)] on the field",
+        ));
+    };
+
+    let value = match attr.datatype {
+        Some(_) if attr.reverse || attr.ordered => {
+            return Err(Error::new(
+                span,
+                "`datatype` cannot be combined with `reverse` or `ordered`",
+            ));
+        }
+        Some(datatype) if is_option(&field.ty) => quote_spanned! {span=>
+            &self.#ident.as_ref().map(|v| #derive::typed_literal(v, #datatype))
+        },
+        Some(datatype) => quote_spanned! {span=>
+            &#derive::typed_literal(&self.#ident, #datatype)
+        },
+        None => quote!(&self.#ident),
+    };
+    let attach = match (attr.reverse, attr.ordered) {
+        (false, false) => quote!(attach),
+        (true, false) => quote!(attach_reverse),
+        (false, true) => quote!(attach_ordered),
+        (true, true) => quote!(attach_reverse_ordered),
+    };
+    let field = ident.to_string();
+    Ok(Some(quote_spanned! {span=>
+        #derive::#attach(builder, &iri, #model, #field, #derive::as_simple(&#pred), #value)?;
+    }))
+}
+
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path.path.segments.last().is_some_and(|s| s.ident == "Option"),
+        _ => false,
+    }
+}
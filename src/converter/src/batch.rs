@@ -0,0 +1,334 @@
+// Conversion of every file of a directory, e.g. the Synth files produced by the lab
+// during a day.
+//
+// The files are converted in parallel by a pool of worker threads, and a failure is
+// recorded in the manifest of the run instead of aborting it.
+use crate::{
+    convert::{convert_input, ConvertOptions, InputType, RdfFormat},
+    detect::detect_input_type,
+    error::{ConvertError, ErrorKind},
+};
+use anyhow::{Context, Result};
+use catplus_common::graph::graph_builder::GraphConfig;
+use clap::ValueEnum;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+/// How the files of a directory are selected and converted.
+pub struct BatchOptions {
+    pub input_type: InputType,
+    pub format: RdfFormat,
+    /// Glob patterns of the files to convert (all files if empty), see [glob_match].
+    pub include: Vec<String>,
+    /// Glob patterns of the files to leave out.
+    pub exclude: Vec<String>,
+    /// The number of files converted at the same time.
+    pub jobs: usize,
+    /// Whether to write the numeric data of every file to a Zarr store next to its output.
+    pub zarr: bool,
+    /// Whether to write the graphs in canonical form, see [ConvertOptions::canonical].
+    pub canonical: bool,
+    /// How the nodes of the graphs are built.
+    pub graph: GraphConfig,
+}
+
+/// The record of a run: the outcome of the conversion of every selected file.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub input_dir: String,
+    pub output_dir: String,
+    pub input_type: String,
+    pub format: String,
+    pub converted: usize,
+    pub failed: usize,
+    /// The files, sorted by path.
+    pub files: Vec<FileRecord>,
+}
+
+/// The outcome of the conversion of a file.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileRecord {
+    /// The path of the input file, relative to the input directory.
+    pub input: String,
+    /// The path of the output file, relative to the output directory.
+    pub output: String,
+    pub status: FileStatus,
+    /// The type detected from the content of the input file, with the auto input type.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_type: Option<String>,
+    /// The number of triples of the graph.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub triples: Option<usize>,
+    /// The SHA-256 hash of the input file, in hexadecimal.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_sha256: Option<String>,
+    /// The SHA-256 hash of the output file, in hexadecimal.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_sha256: Option<String>,
+    /// The warnings of the conversion, such as the conflicting properties of merged
+    /// chemicals.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// The error of a failed conversion, with its causes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileStatus {
+    Converted,
+    Failed,
+}
+
+/// Converts the selected files of `input_dir` into `output_dir`, mirroring their paths
+/// with the extension of the format (e.g. `a/b.json` to `a/b.ttl`).
+///
+/// The output directory is not searched for input files when it lies in the input
+/// directory.
+///
+/// # Returns
+/// A `Result` containing the manifest of the run, whatever the outcome of the
+/// conversions, or an error if the input directory cannot be read.
+pub fn convert_directory(
+    input_dir: &Path,
+    output_dir: &Path,
+    options: &BatchOptions,
+) -> Result<Manifest> {
+    let skipped = output_dir.canonicalize().ok();
+    let mut inputs = Vec::new();
+    list_files(input_dir, input_dir, skipped.as_deref(), &mut inputs)?;
+    inputs.retain(|path| {
+        (options.include.is_empty() || options.include.iter().any(|p| glob_match(p, path)))
+            && !options.exclude.iter().any(|p| glob_match(p, path))
+    });
+    inputs.sort();
+    let (inputs, mut collisions) = take_collisions(inputs, options);
+
+    let next = AtomicUsize::new(0);
+    let records = Mutex::new(Vec::with_capacity(inputs.len()));
+    std::thread::scope(|scope| {
+        for _ in 0..options.jobs.clamp(1, inputs.len().max(1)) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(input) = inputs.get(i) else {
+                    break;
+                };
+                let record = convert_file(input_dir, output_dir, input, options);
+                records.lock().unwrap().push(record);
+            });
+        }
+    });
+
+    let mut files = records.into_inner().unwrap();
+    files.append(&mut collisions);
+    files.sort_by(|a, b| a.input.cmp(&b.input));
+    let converted = files.iter().filter(|f| f.status == FileStatus::Converted).count();
+    let name = |value: Option<clap::builder::PossibleValue>| {
+        value.map(|v| v.get_name().to_string()).unwrap_or_default()
+    };
+    Ok(Manifest {
+        input_dir: input_dir.display().to_string(),
+        output_dir: output_dir.display().to_string(),
+        input_type: name(options.input_type.to_possible_value()),
+        format: name(options.format.to_possible_value()),
+        converted,
+        failed: files.len() - converted,
+        files,
+    })
+}
+
+/// Splits the sorted `inputs` into the files to convert and the failed records of the
+/// files sharing an output path (e.g. `a.json` and `a.xml` both written to `a.ttl`),
+/// none of which is converted.
+fn take_collisions(inputs: Vec<String>, options: &BatchOptions) -> (Vec<String>, Vec<FileRecord>) {
+    let mut by_output: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for input in inputs {
+        by_output.entry(output_path_string(&input, options)).or_default().push(input);
+    }
+    let mut kept = Vec::new();
+    let mut collisions = Vec::new();
+    for (output, mut sharing) in by_output {
+        if sharing.len() == 1 {
+            kept.append(&mut sharing);
+            continue;
+        }
+        for input in &sharing {
+            let others: Vec<String> = sharing
+                .iter()
+                .filter(|other| *other != input)
+                .map(|other| format!("'{}'", other))
+                .collect();
+            let error = anyhow::anyhow!(
+                "The output file '{}' would also be written by {}",
+                output,
+                others.join(", ")
+            );
+            collisions.push(failed(input, options, None, &error));
+        }
+    }
+    kept.sort();
+    (kept, collisions)
+}
+
+/// Converts the file at the relative path `input` of `input_dir`.
+fn convert_file(
+    input_dir: &Path,
+    output_dir: &Path,
+    input: &str,
+    options: &BatchOptions,
+) -> FileRecord {
+    let content = match std::fs::read(input_dir.join(input)) {
+        Ok(content) => content,
+        Err(e) => return failed(input, options, None, &anyhow::Error::new(e)),
+    };
+    let input_sha256 = Some(sha256(&content));
+    let output_path = output_dir.join(output_path(input, options));
+    let zarr_path = options.zarr.then(|| output_path.with_extension("zarr"));
+
+    let conversion = String::from_utf8(content)
+        .context("The input file is not UTF-8 text")
+        .and_then(|content| {
+            let input_type = match options.input_type {
+                InputType::Auto => detect_input_type(&content)?,
+                input_type => input_type,
+            };
+            let convert_options = ConvertOptions {
+                zarr_path: zarr_path.as_deref(),
+                canonical: options.canonical,
+                graph: Some(&options.graph),
+                ..Default::default()
+            };
+            if let Some(parent) = output_path.parent() {
+                std::fs::create_dir_all(parent).with_context(|| {
+                    format!("Failed to create output directory '{}'", parent.display())
+                })?;
+            }
+            let conversion = convert_input(input_type, &content, &options.format, convert_options)?;
+            Ok((input_type, conversion))
+        })
+        .and_then(|(input_type, conversion)| {
+            std::fs::write(&output_path, &conversion.output).with_context(|| {
+                format!("Failed to write to output file '{}'", output_path.display())
+            })?;
+            Ok((input_type, conversion))
+        });
+    match conversion {
+        Ok((input_type, conversion)) => FileRecord {
+            input: input.to_string(),
+            output: output_path_string(input, options),
+            status: FileStatus::Converted,
+            input_type: (options.input_type == InputType::Auto).then(|| input_type.to_string()),
+            triples: Some(conversion.triple_count),
+            input_sha256,
+            output_sha256: Some(sha256(conversion.output.as_bytes())),
+            warnings: conversion.warnings,
+            error: None,
+        },
+        Err(e) => failed(input, options, input_sha256, &e),
+    }
+}
+
+fn failed(
+    input: &str,
+    options: &BatchOptions,
+    input_sha256: Option<String>,
+    error: &anyhow::Error,
+) -> FileRecord {
+    FileRecord {
+        input: input.to_string(),
+        output: output_path_string(input, options),
+        status: FileStatus::Failed,
+        input_type: None,
+        triples: None,
+        input_sha256,
+        output_sha256: None,
+        warnings: Vec::new(),
+        error: Some(format!("{:#}", error)),
+    }
+}
+
+fn output_path(input: &str, options: &BatchOptions) -> PathBuf {
+    Path::new(input).with_extension(options.format.extension())
+}
+
+fn output_path_string(input: &str, options: &BatchOptions) -> String {
+    output_path(input, options).to_string_lossy().replace('\\', "/")
+}
+
+fn sha256(content: &[u8]) -> String {
+    Sha256::digest(content).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Adds the files under `dir` to `files`, as paths relative to `root` separated by `/`,
+/// leaving out the directory `skipped`. Symbolic links to directories are not followed,
+/// so that a link to a parent directory does not list its files forever.
+fn list_files(
+    root: &Path,
+    dir: &Path,
+    skipped: Option<&Path>,
+    files: &mut Vec<String>,
+) -> Result<()> {
+    let entries = std::fs::read_dir(dir).with_context(|| {
+        ConvertError::new(ErrorKind::Io, format!("Failed to read directory '{}'", dir.display()))
+    })?;
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            if skipped.is_none() || path.canonicalize().ok().as_deref() != skipped {
+                list_files(root, &path, skipped, files)?;
+            }
+        } else if path.is_file() {
+            let relative = path.strip_prefix(root).expect("the files are under the root");
+            files.push(relative.to_string_lossy().replace('\\', "/"));
+        }
+    }
+    Ok(())
+}
+
+/// Whether the relative `path` matches the glob `pattern`: `*` matches any characters
+/// but `/`, `?` any character but `/` and `**` any number of directories. A pattern
+/// without `/` matches the file name, e.g. `*.json` matches `2024/05/16/23.json`.
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    if !pattern.contains('/') {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        return match_segment(&chars(pattern), &chars(name));
+    }
+    let pattern: Vec<&str> = pattern.split('/').collect();
+    let path: Vec<&str> = path.split('/').collect();
+    match_segments(&pattern, &path)
+}
+
+fn match_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| match_segments(rest, &path[skip..])),
+        Some((segment, rest)) => path.split_first().is_some_and(|(name, path)| {
+            match_segment(&chars(segment), &chars(name)) && match_segments(rest, path)
+        }),
+    }
+}
+
+fn chars(text: &str) -> Vec<char> {
+    text.chars().collect()
+}
+
+fn match_segment(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|skip| match_segment(rest, &name[skip..])),
+        Some(('?', rest)) => !name.is_empty() && match_segment(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && match_segment(rest, &name[1..]),
+    }
+}
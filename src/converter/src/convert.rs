@@ -1,17 +1,209 @@
-use anyhow::{Context, Result};
-use catplus_common::graph::{graph_builder::GraphBuilder, insert_into::InsertIntoGraph};
-use serde::{de::DeserializeOwned, Deserialize};
+use crate::{
+    detect::detect_input_type,
+    error::{ConvertError, ErrorKind},
+    json_input::parse_json,
+};
+use anyhow::{bail, Context, Result};
+use catplus_common::{
+    graph::{
+        cat_namespace::{from_cat_namespace, to_cat_namespace, CatNamespace},
+        from_graph::FromGraph,
+        graph_builder::{GraphBuilder, GraphConfig},
+        insert_into::InsertIntoGraph,
+    },
+    models::{
+        chromatography::ChromatographyRun,
+        mass_spectrometry::MassSpectrometryRun,
+        spectroscopy::Spectrum,
+        types::{Batch, CampaignWrapper},
+    },
+    parsers::{animl::AnimlDocument, asm::AsmDocument},
+    rdf::{
+        graph_diff::{diff_graphs, GraphDiff},
+        jsonld_compaction::CompactionOptions,
+        rdf_parser::{
+            parse_jsonld_to_graph, parse_nquads_to_graph, parse_ntriples_to_graph,
+            parse_trig_to_graph, parse_turtle_to_graph,
+        },
+    },
+    shacl::shapes::Shapes,
+    zarr::{insert_into_store::InsertIntoStore, zarr_store::ZarrStore},
+};
+use clap::ValueEnum;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sophia::inmem::graph::LightGraph;
+use sophia_api::graph::Graph;
+use std::{fmt, path::Path, str::FromStr};
 
 // Derive Deserialize and ValueEnum
 #[derive(Deserialize, Debug, clap::ValueEnum, Clone)]
 pub enum RdfFormat {
     Turtle,
     Jsonld,
+    /// Compacted JSON-LD, optionally framed, see [ConvertOptions::compaction].
+    CompactJsonld,
+    /// N-Triples, one triple per line.
+    Ntriples,
+    /// N-Quads, one quad per line.
+    Nquads,
+    Trig,
+    /// RDF/XML, which cannot be read back.
+    Rdfxml,
+}
+
+impl RdfFormat {
+    /// The format of a file with the extension of `path`, e.g. `.ttl` for Turtle.
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "ttl" => Some(Self::Turtle),
+            "jsonld" | "json" => Some(Self::Jsonld),
+            "nt" => Some(Self::Ntriples),
+            "nq" => Some(Self::Nquads),
+            "trig" => Some(Self::Trig),
+            "rdf" | "xml" | "owl" => Some(Self::Rdfxml),
+            _ => None,
+        }
+    }
+
+    /// The usual extension of the files of the format, e.g. `ttl` for Turtle.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Turtle => "ttl",
+            Self::Jsonld | Self::CompactJsonld => "jsonld",
+            Self::Ntriples => "nt",
+            Self::Nquads => "nq",
+            Self::Trig => "trig",
+            Self::Rdfxml => "rdf",
+        }
+    }
+}
+
+/// Optional steps of a conversion.
+#[derive(Clone, Copy, Default)]
+pub struct ConvertOptions<'a> {
+    /// The directory of the Zarr store to write the numeric data to, if any.
+    pub zarr_path: Option<&'a Path>,
+    /// The SHACL shapes the graph must conform to, if any.
+    pub shapes: Option<&'a Shapes>,
+    /// The frame and the external context of [RdfFormat::CompactJsonld] documents.
+    pub compaction: Option<&'a CompactionOptions>,
+    /// Whether to write the graph in canonical form, with the blank node labels of
+    /// RDFC-1.0 and sorted triples, so that unchanged inputs yield identical files.
+    pub canonical: bool,
+    /// How the nodes of the graph are built, the default [GraphConfig] if `None`.
+    pub graph: Option<&'a GraphConfig>,
+}
+
+/// The type of an input file, i.e. the model it is read into.
+#[derive(Deserialize, Debug, clap::ValueEnum, Clone, Copy, PartialEq, Eq)]
+pub enum InputType {
+    Synth,
+    HCI,
+    /// Agilent ChemStation / OpenLab CDS CSV export.
+    Agilent,
+    /// JCAMP-DX spectrum (IR, UV-Vis).
+    Jcamp,
+    /// AnIML XML document.
+    Animl,
+    /// Allotrope Simple Model (ASM) JSON document.
+    Asm,
+    /// mzML mass-spectrometry run (LC-MS, GC-MS).
+    Mzml,
+    /// Detected from the content of the input, see [detect_input_type].
+    Auto,
+}
+
+impl fmt::Display for InputType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.to_possible_value().expect("no input type is skipped");
+        f.write_str(value.get_name())
+    }
+}
+
+/// The outcome of a conversion.
+#[derive(Debug)]
+pub struct Conversion {
+    /// The serialized graph.
+    pub output: String,
+    /// The number of triples of the graph.
+    pub triple_count: usize,
+    /// The warnings of the conversion, such as the conflicting properties of merged
+    /// chemicals.
+    pub warnings: Vec<String>,
+}
+
+impl Conversion {
+    fn new(
+        graph_builder: &GraphBuilder,
+        format: &RdfFormat,
+        compaction: Option<&CompactionOptions>,
+    ) -> Result<Self> {
+        Ok(Self {
+            output: serialize_graph(graph_builder, format, compaction)?,
+            triple_count: graph_builder.graph.triples().count(),
+            warnings: graph_warnings(graph_builder),
+        })
+    }
+}
+
+/// Parses an input of type `input_type` and serializes its RDF graph, writing the
+/// numeric data to a Zarr store and validating the graph as requested by `options`.
+///
+/// # Returns
+/// A `Result` containing the serialized graph and its size, or an error if the input is
+/// invalid or the graph does not conform to the shapes.
+pub fn convert_input(
+    input_type: InputType,
+    input_content: &str,
+    format: &RdfFormat,
+    options: ConvertOptions,
+) -> Result<Conversion> {
+    fn json<T>(
+        input_content: &str,
+        format: &RdfFormat,
+        options: ConvertOptions,
+    ) -> Result<Conversion>
+    where
+        T: DeserializeOwned + InsertIntoGraph + InsertIntoStore,
+    {
+        let data: T = parse_json(input_content)
+            .context(ConvertError::new(ErrorKind::Parse, "Failed to parse JSON input"))?;
+        convert_data(data, format, options)
+    }
+    fn instrument<T>(
+        input_content: &str,
+        format: &RdfFormat,
+        options: ConvertOptions,
+    ) -> Result<Conversion>
+    where
+        T: FromStr<Err = anyhow::Error> + InsertIntoGraph + InsertIntoStore,
+    {
+        let data: T = input_content
+            .parse()
+            .context(ConvertError::new(ErrorKind::Parse, "Failed to parse instrument input"))?;
+        convert_data(data, format, options)
+    }
+
+    match input_type {
+        InputType::Synth => json::<Batch>(input_content, format, options),
+        InputType::HCI => json::<CampaignWrapper>(input_content, format, options),
+        InputType::Agilent => instrument::<ChromatographyRun>(input_content, format, options),
+        InputType::Jcamp => instrument::<Spectrum>(input_content, format, options),
+        InputType::Animl => instrument::<AnimlDocument>(input_content, format, options),
+        InputType::Asm => instrument::<AsmDocument>(input_content, format, options),
+        InputType::Mzml => instrument::<MassSpectrometryRun>(input_content, format, options),
+        InputType::Auto => {
+            convert_input(detect_input_type(input_content)?, input_content, format, options)
+        }
+    }
 }
 
 /// Parses JSON and serializes the RDF graph to the specified format.
 ///
 /// This function can handle any struct that implements `serde::DeserializeOwned` and your `InsertIntoGraph` trait.
+/// It is the conversion of [convert_input] with the default [ConvertOptions], for callers
+/// that know the type of their input.
 ///
 /// # Arguments
 /// - `input_content`: The JSON input as a string.
@@ -23,27 +215,270 @@ pub fn json_to_rdf<T>(input_content: &str, format: &RdfFormat) -> Result<String>
 where
     T: DeserializeOwned + InsertIntoGraph, // Trait bounds
 {
-    let data: T = parse_json(input_content).context("Failed to parse JSON input")?;
+    let data: T = parse_json(input_content)
+        .context(ConvertError::new(ErrorKind::Parse, "Failed to parse JSON input"))?;
+    Ok(convert_graph(&data, format, ConvertOptions::default())?.output)
+}
+
+/// Merges an HCI campaign and the Synth files of its batch into one document and
+/// serializes its RDF graph, so that the campaign and the actions share the batch node.
+///
+/// The Synth batches are joined on their `batchID`, which must be the one of the batch of
+/// the campaign: their actions are added to it and their properties fill the missing
+/// ones (conflicting properties keep the value of the campaign, with a warning).
+///
+/// # Arguments
+/// - `hci_content`: The HCI JSON input as a string.
+/// - `synth_inputs`: The name (e.g. the path) and the JSON content of every Synth input.
+/// - `format`: The desired serialization format.
+/// - `options`: The optional steps of the conversion.
+///
+/// # Returns
+/// A `Result` containing the serialized graph and its warnings, or an error if an input
+/// is invalid or describes another batch.
+pub fn merge_to_rdf_with_options(
+    hci_content: &str,
+    synth_inputs: &[(&str, &str)],
+    format: &RdfFormat,
+    options: ConvertOptions,
+) -> Result<Conversion> {
+    let mut campaign: CampaignWrapper = parse_json(hci_content)
+        .context(ConvertError::new(ErrorKind::Parse, "Failed to parse HCI input"))?;
+    let batch = &mut campaign.has_campaign.has_batch;
+    let mut warnings = Vec::new();
+    for (name, content) in synth_inputs {
+        let synth: Batch = parse_json(content).with_context(|| {
+            ConvertError::new(ErrorKind::Parse, format!("Failed to parse Synth input '{}'", name))
+        })?;
+        let conflicts = batch
+            .merge(synth)
+            .with_context(|| format!("Failed to join Synth input '{}' on the campaign", name))?;
+        warnings.extend(conflicts.into_iter().map(|conflict| {
+            format!("conflicting batch '{}' in '{}': {}", batch.batch_id, name, conflict)
+        }));
+    }
+
+    let mut conversion = convert_data(campaign, format, options)?;
+    warnings.append(&mut conversion.warnings);
+    conversion.warnings = warnings;
+    Ok(conversion)
+}
 
-    let mut graph_builder = GraphBuilder::new();
-    graph_builder.insert(&data).context("Failed to build RDF graph")?;
+/// Builds, validates and serializes the graph of `data`. With the Zarr store of
+/// `options`, the arrays are written to a staged store first, as the graph references
+/// them, and the store is only put in place once the graph is valid, see
+/// [ZarrStore::stage].
+fn convert_data<T>(mut data: T, format: &RdfFormat, options: ConvertOptions) -> Result<Conversion>
+where
+    T: InsertIntoGraph + InsertIntoStore,
+{
+    let Some(zarr_path) = options.zarr_path else {
+        return convert_graph(&data, format, options);
+    };
+
+    let store = ZarrStore::stage(zarr_path).with_context(|| {
+        ConvertError::new(
+            ErrorKind::Io,
+            format!("Failed to create Zarr store '{}'", zarr_path.display()),
+        )
+    })?;
+    data.insert_into_store(&store)
+        .context(ConvertError::new(ErrorKind::Io, "Failed to write arrays to the Zarr store"))?;
+
+    let graph_builder = build_graph(&data, options)?;
+    validate_graph(&graph_builder, options.shapes)?;
+    let conversion = Conversion::new(&graph_builder, format, options.compaction)?;
+    store
+        .insert_graph(&graph_builder)
+        .context(ConvertError::new(ErrorKind::Io, "Failed to write metadata to the Zarr store"))?;
+    store.publish().context(ConvertError::new(ErrorKind::Io, "Failed to write the Zarr store"))?;
 
+    Ok(conversion)
+}
+
+/// Builds, validates and serializes the graph of `data`, without a Zarr store.
+fn convert_graph<T: InsertIntoGraph>(
+    data: &T,
+    format: &RdfFormat,
+    options: ConvertOptions,
+) -> Result<Conversion> {
+    let graph_builder = build_graph(data, options)?;
+    validate_graph(&graph_builder, options.shapes)?;
+    Conversion::new(&graph_builder, format, options.compaction)
+}
+
+/// Parses an RDF graph and serializes it back to JSON, the inverse of [convert_input].
+///
+/// The document is read from the root node of the graph, e.g. its only batch for a
+/// [catplus_common::models::types::Batch]. Lists are read back in the order of the
+/// `schema:position` of their items, and numbers as integers unless written with a
+/// decimal point, so that the JSON input of the graph is written back as it was.
+///
+/// # Arguments
+/// - `input_content`: The RDF graph as a string.
+/// - `format`: The serialization format of the graph.
+/// - `namespace`: The Cat+ namespace of the graph.
+///
+/// # Returns
+/// A `Result` containing the pretty-printed JSON document or an error.
+pub fn rdf_to_json<T>(
+    input_content: &str,
+    format: &RdfFormat,
+    namespace: &CatNamespace,
+) -> Result<String>
+where
+    T: FromGraph + Serialize,
+{
+    let graph = from_cat_namespace(
+        &parse_graph(input_content, format)
+            .context(ConvertError::new(ErrorKind::Parse, "Failed to parse the RDF graph"))?,
+        namespace,
+    )?;
+    let root = T::find_root(&graph).context(ConvertError::new(
+        ErrorKind::Mapping,
+        "Failed to find the root of the document",
+    ))?;
+    let data = T::from_graph(&graph, &root).context(ConvertError::new(
+        ErrorKind::Mapping,
+        "Failed to read the document from the graph",
+    ))?;
+
+    serde_json::to_string_pretty(&data).context("Failed to serialize to JSON")
+}
+
+/// Rewrites an RDF graph of the placeholder Cat+ namespace `http://example.org/cat#`,
+/// e.g. written by an earlier version of the converter, to `namespace`.
+///
+/// # Arguments
+/// - `input_content`: The RDF graph as a string.
+/// - `format`: The serialization format of the graph, also used for the output.
+/// - `namespace`: The Cat+ namespace of the output.
+///
+/// # Returns
+/// A `Result` containing the rewritten graph as a string or an error.
+pub fn migrate_rdf(
+    input_content: &str,
+    format: &RdfFormat,
+    namespace: &CatNamespace,
+) -> Result<String> {
+    let mut graph_builder = GraphBuilder::with_config(GraphConfig {
+        cat_namespace: namespace.clone(),
+        ..Default::default()
+    });
+    graph_builder.graph = to_cat_namespace(
+        &parse_graph(input_content, format)
+            .context(ConvertError::new(ErrorKind::Parse, "Failed to parse the RDF graph"))?,
+        namespace,
+    )?;
+
+    serialize_graph(&graph_builder, format, None)
+}
+
+/// Compares two RDF graphs, e.g. two conversions of the same batch, matching their
+/// blank nodes by structure.
+///
+/// # Arguments
+/// - `old_content`, `old_format`: The old graph and its serialization format.
+/// - `new_content`, `new_format`: The new graph and its serialization format.
+///
+/// # Returns
+/// A `Result` containing the triples removed from the old graph and added to the new
+/// one, or an error if a graph cannot be parsed.
+pub fn diff_rdf(
+    old_content: &str,
+    old_format: &RdfFormat,
+    new_content: &str,
+    new_format: &RdfFormat,
+) -> Result<GraphDiff> {
+    let old = parse_graph(old_content, old_format)
+        .context(ConvertError::new(ErrorKind::Parse, "Failed to parse the old graph"))?;
+    let new = parse_graph(new_content, new_format)
+        .context(ConvertError::new(ErrorKind::Parse, "Failed to parse the new graph"))?;
+
+    diff_graphs(&old, &new)
+}
+
+fn parse_graph(input_content: &str, format: &RdfFormat) -> Result<LightGraph> {
+    match format {
+        RdfFormat::Turtle => parse_turtle_to_graph(input_content),
+        RdfFormat::Jsonld | RdfFormat::CompactJsonld => parse_jsonld_to_graph(input_content),
+        RdfFormat::Ntriples => parse_ntriples_to_graph(input_content),
+        RdfFormat::Nquads => parse_nquads_to_graph(input_content),
+        RdfFormat::Trig => parse_trig_to_graph(input_content),
+        RdfFormat::Rdfxml => bail!("Reading RDF/XML is not supported"),
+    }
+}
+
+/// Fails with the validation report if the graph does not conform to `shapes`.
+fn validate_graph(graph_builder: &GraphBuilder, shapes: Option<&Shapes>) -> Result<()> {
+    let Some(shapes) = shapes else {
+        return Ok(());
+    };
+    let report = graph_builder.validate(shapes);
+    if !report.conforms() {
+        bail!(ConvertError::new(
+            ErrorKind::Validation,
+            format!("The graph does not conform to the SHACL shapes\n{}", report)
+        ));
+    }
+    Ok(())
+}
+
+/// Builds the RDF graph of `data` as set by [ConvertOptions::graph], and puts it in
+/// canonical form if requested.
+fn build_graph(data: &dyn InsertIntoGraph, options: ConvertOptions) -> Result<GraphBuilder> {
+    let mut graph_builder = GraphBuilder::with_config(options.graph.cloned().unwrap_or_default());
+    graph_builder
+        .insert(data)
+        .context(ConvertError::new(ErrorKind::Mapping, "Failed to build RDF graph"))?;
+    if options.canonical {
+        graph_builder.canonicalize()?;
+    }
+    Ok(graph_builder)
+}
+
+/// The chemicals that were merged despite differing properties and the mapping overrides
+/// matching no field.
+fn graph_warnings(graph_builder: &GraphBuilder) -> Vec<String> {
+    let conflicts = graph_builder
+        .chemical_conflicts()
+        .iter()
+        .map(|conflict| format!("conflicting {}", conflict));
+    let fields = graph_builder
+        .unknown_mapped_fields()
+        .into_iter()
+        .map(|field| format!("the mapping of {} matches no field", field));
+    conflicts.chain(fields).collect()
+}
+
+fn serialize_graph(
+    graph_builder: &GraphBuilder,
+    format: &RdfFormat,
+    compaction: Option<&CompactionOptions>,
+) -> Result<String> {
     let serialized_graph = match format {
         RdfFormat::Jsonld => {
             graph_builder.serialize_to_jsonld().context("Failed to serialize to JSON-LD")?
         }
+        RdfFormat::CompactJsonld => graph_builder
+            .serialize_to_compact_jsonld(compaction.unwrap_or(&CompactionOptions::default()))
+            .context("Failed to serialize to compacted JSON-LD")?,
         RdfFormat::Turtle => {
             graph_builder.serialize_to_turtle().context("Failed to serialize to Turtle")?
         }
+        RdfFormat::Ntriples => {
+            graph_builder.serialize_to_ntriples().context("Failed to serialize to N-Triples")?
+        }
+        RdfFormat::Nquads => {
+            graph_builder.serialize_to_nquads().context("Failed to serialize to N-Quads")?
+        }
+        RdfFormat::Trig => {
+            graph_builder.serialize_to_trig().context("Failed to serialize to TriG")?
+        }
+        RdfFormat::Rdfxml => {
+            graph_builder.serialize_to_rdfxml().context("Failed to serialize to RDF/XML")?
+        }
     };
 
     Ok(serialized_graph)
 }
-
-/// Parses a JSON string into a struct of type T.
-fn parse_json<T>(json_data: &str) -> Result<T>
-where
-    T: DeserializeOwned, // Trait bound
-{
-    serde_json::from_str(json_data).map_err(|e| anyhow::Error::new(e))
-}
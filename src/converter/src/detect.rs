@@ -0,0 +1,111 @@
+// Detection of the type of an input from its content, for the `auto` input type.
+//
+// JSON documents are told apart by their top-level keys: `hasCampaign` for HCI,
+// `batchID` and `Actions` for Synth, `$asm.manifest` and `<technique> aggregate
+// document` for ASM. The other formats are recognized by their signature: the root
+// element of XML documents, the `##JCAMP-DX=` record of JCAMP-DX spectra and the
+// `[Signal]` and `[Peaks]` sections of Agilent CSV exports.
+use crate::{
+    convert::InputType,
+    error::{ConvertError, ErrorKind},
+};
+use anyhow::{bail, Context, Result};
+use serde_json::{Map, Value};
+
+/// Detects the type of the input `content`.
+///
+/// # Returns
+/// A `Result` containing the detected type, or an error listing the candidate types
+/// with the evidence for each if the content looks like several types, or none.
+pub fn detect_input_type(content: &str) -> Result<InputType> {
+    let candidates = candidates(content)?;
+    match candidates.as_slice() {
+        [(input_type, _)] => Ok(*input_type),
+        [] => bail!(ConvertError::new(
+            ErrorKind::Parse,
+            "Cannot detect the input type: the input is neither a Synth, HCI or ASM JSON \
+             document, an AnIML or mzML XML document, a JCAMP-DX spectrum nor an Agilent \
+             CSV export. Pass the input type explicitly"
+        )),
+        _ => bail!(ConvertError::new(
+            ErrorKind::Parse,
+            format!(
+                "The input type is ambiguous, the input looks like {}. Pass the input type \
+                 explicitly",
+                candidates
+                    .iter()
+                    .map(|(input_type, evidence)| format!("{} ({})", input_type, evidence))
+                    .collect::<Vec<_>>()
+                    .join(" or ")
+            )
+        )),
+    }
+}
+
+/// The types `content` looks like, with the evidence for each.
+fn candidates(content: &str) -> Result<Vec<(InputType, String)>> {
+    let content = content.trim_start_matches('\u{feff}').trim_start();
+    if content.starts_with('{') {
+        let document: Map<String, Value> = serde_json::from_str(content).context(
+            ConvertError::new(ErrorKind::Parse, "Cannot detect the input type of invalid JSON"),
+        )?;
+        return Ok(json_candidates(&document));
+    }
+    if content.starts_with('<') {
+        return Ok(match root_element(content) {
+            Some(name @ "AnIML") => vec![(InputType::Animl, format!("root element <{}>", name))],
+            Some(name @ ("mzML" | "indexedmzML")) => {
+                vec![(InputType::Mzml, format!("root element <{}>", name))]
+            }
+            _ => Vec::new(),
+        });
+    }
+
+    let mut candidates = Vec::new();
+    let lines = || content.lines().map(str::trim_start);
+    if lines().any(|line| line.starts_with("##JCAMP-DX=")) {
+        candidates.push((InputType::Jcamp, "record ##JCAMP-DX=".to_string()));
+    }
+    if let Some(section) =
+        lines().find_map(|line| ["[Signal]", "[Peaks]"].into_iter().find(|s| line.starts_with(s)))
+    {
+        candidates.push((InputType::Agilent, format!("section {}", section)));
+    }
+    Ok(candidates)
+}
+
+fn json_candidates(document: &Map<String, Value>) -> Vec<(InputType, String)> {
+    let key = |keys: &[&'static str]| -> Option<&str> {
+        keys.iter().find(|key| document.contains_key(**key)).copied()
+    };
+    let mut candidates = Vec::new();
+    if let Some(key) = key(&["hasCampaign"]) {
+        candidates.push((InputType::HCI, format!("key '{}'", key)));
+    }
+    if let Some(key) = key(&["batchID", "Actions"]) {
+        candidates.push((InputType::Synth, format!("key '{}'", key)));
+    }
+    let aggregate = document.keys().find(|key| key.ends_with(" aggregate document"));
+    if let Some(key) = key(&["$asm.manifest"]).or(aggregate.map(String::as_str)) {
+        candidates.push((InputType::Asm, format!("key '{}'", key)));
+    }
+    candidates
+}
+
+/// The local name of the root element of an XML document, skipping the XML
+/// declaration, processing instructions, comments and the document type.
+fn root_element(content: &str) -> Option<&str> {
+    let mut rest = content;
+    loop {
+        rest = &rest[rest.find('<')?..];
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = &comment[comment.find("-->")? + 3..];
+        } else if rest.starts_with("<?") || rest.starts_with("<!") {
+            rest = &rest[rest.find('>')? + 1..];
+        } else {
+            let name =
+                rest[1..].split(|c: char| c.is_whitespace() || c == '>' || c == '/').next()?;
+            return Some(name.rsplit(':').next().unwrap_or(name));
+        }
+    }
+}
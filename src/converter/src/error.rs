@@ -0,0 +1,56 @@
+// Kinds of failure of the conversions, so that callers such as the command line
+// interface can tell them apart (e.g. by exit code) whatever the context of the errors.
+//
+// The errors stay `anyhow` errors: the stages of a conversion add a [ConvertError] as
+// the context of their failures, which [ErrorKind::of] finds back in the chain.
+use std::fmt;
+
+/// The stage of a conversion that failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The options of a conversion are invalid or do not go together.
+    Argument,
+    /// An input cannot be read or an output written.
+    Io,
+    /// An input is not a valid document of its type (or graph of its format).
+    Parse,
+    /// A document cannot be mapped to its graph (or back), or the mapping file is invalid.
+    Mapping,
+    /// The graph does not conform to the SHACL shapes.
+    Validation,
+}
+
+impl ErrorKind {
+    /// The kind of `error`: the kind of its outermost [ConvertError], or
+    /// [ErrorKind::Io] if it is caused by an I/O error.
+    ///
+    /// # Returns
+    /// The kind of the error, or `None` if it is of none of the kinds.
+    pub fn of(error: &anyhow::Error) -> Option<Self> {
+        if let Some(convert_error) = error.downcast_ref::<ConvertError>() {
+            return Some(convert_error.kind);
+        }
+        error.chain().any(|cause| cause.is::<std::io::Error>()).then_some(Self::Io)
+    }
+}
+
+/// The context of the errors of a stage of a conversion.
+#[derive(Debug)]
+pub struct ConvertError {
+    pub kind: ErrorKind,
+    message: String,
+}
+
+impl ConvertError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self { kind, message: message.into() }
+    }
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ConvertError {}
@@ -0,0 +1,306 @@
+// Reading of the JSON input files (Synth, HCI), reporting every error of a file at once
+// with its JSON pointer, the type expected by the models and a snippet of the input.
+//
+// The document is first parsed as a `serde_json::Value`, then read into the model with
+// `serde_path_to_error`, which gives the path of the first error only. To find the next
+// errors, the element of the innermost array holding the error (e.g. the action of the
+// Synth file) is left out and the document read again, until it is read or the error
+// is outside any array. The pointers of the errors are those of the input file, i.e.
+// before elements were left out.
+//
+// The type expected at a pointer is probed by replacing the value there with `true`,
+// which no model accepts: the message of the error names what the model expected
+// instead, e.g. `struct Sample` or `a string`.
+use serde::de::DeserializeOwned;
+use serde_json::{value::RawValue, Value};
+use serde_path_to_error::Segment;
+use std::{collections::HashMap, fmt};
+
+/// The number of errors after which the document is no longer read.
+const MAX_ERRORS: usize = 100;
+/// The number of characters of the input shown around an error.
+const SNIPPET_WIDTH: usize = 100;
+
+/// An error of a JSON document.
+#[derive(Debug)]
+pub struct JsonError {
+    /// The JSON pointer of the invalid (or missing) value, e.g. `/Actions/3/startTime`,
+    /// or `None` for a syntax error.
+    pub pointer: Option<String>,
+    /// The error, with the expected type.
+    pub message: String,
+    /// The model of the object holding the value, e.g. `Action`.
+    pub model: Option<String>,
+    /// The line of the error, starting from 1.
+    pub line: usize,
+    /// The column of the error, in characters, starting from 1.
+    pub column: usize,
+    /// The line of the input at the error, shortened around the error if it is long.
+    snippet: String,
+    /// The column of the error in the snippet.
+    caret: usize,
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.pointer {
+            Some(pointer) if !pointer.is_empty() => write!(f, "{}: {}", pointer, self.message)?,
+            _ => write!(f, "{}", self.message)?,
+        }
+        if let Some(model) = &self.model {
+            write!(f, " (in {})", model)?;
+        }
+        let number = self.line.to_string();
+        let margin = " ".repeat(number.len());
+        writeln!(f, "\n{}--> line {}, column {}", margin, self.line, self.column)?;
+        writeln!(f, "{} | {}", number, self.snippet)?;
+        write!(f, "{} | {}^", margin, " ".repeat(self.caret - 1))
+    }
+}
+
+/// The errors of a JSON document, in the order of the document.
+#[derive(Debug)]
+pub struct JsonErrors(pub Vec<JsonError>);
+
+impl fmt::Display for JsonErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let [error] = &self.0[..] {
+            return write!(f, "{}", error);
+        }
+        let more = if self.0.len() >= MAX_ERRORS { " (stopped reading)" } else { "" };
+        write!(f, "{} errors in the JSON input{}:", self.0.len(), more)?;
+        for error in &self.0 {
+            write!(f, "\n\n{}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for JsonErrors {}
+
+/// Parses the JSON document `content` into the model `T`.
+///
+/// # Returns
+/// A `Result` containing the model, or [JsonErrors] listing every error of the document
+/// (the syntax error only if it is not valid JSON).
+pub fn parse_json<T>(content: &str) -> Result<T, JsonErrors>
+where
+    T: DeserializeOwned,
+{
+    let mut value: Value = serde_json::from_str(content).map_err(|e| {
+        let message = e.to_string();
+        let message = message.rsplit_once(" at line ").map_or(&message[..], |(m, _)| m);
+        let offset = offset_of_line_column(content, e.line(), e.column());
+        JsonErrors(vec![json_error(content, offset, None, message.to_string(), None)])
+    })?;
+
+    let mut errors = Vec::new();
+    // The indices of the elements left out, by pointer of their array in the input.
+    let mut left_out: HashMap<String, Vec<usize>> = HashMap::new();
+    loop {
+        let error = match serde_path_to_error::deserialize::<_, T>(&value) {
+            Ok(data) if errors.is_empty() => return Ok(data),
+            Ok(_) => break,
+            Err(error) => error,
+        };
+        // The path of the error of a missing field is the one of its object, which is
+        // pointed at in the input.
+        let steps = steps(error.path());
+        let mut message = error.inner().to_string();
+        let mut error_steps = steps.clone();
+        if let Some(field) = missing_field(&message) {
+            if let Some(expected) = expected_at::<T>(&value, &steps, Some(&field)) {
+                message = format!("{}, expected {}", message, expected);
+            }
+            error_steps.push(Step::Key(field));
+        }
+        let model = match error_steps.split_last() {
+            Some((Step::Key(_), object)) => expected_at::<T>(&value, object, None)
+                .and_then(|expected| expected.strip_prefix("struct ").map(str::to_string)),
+            _ => None,
+        };
+
+        let offset = locate(content, &pointer(&original_steps(&steps, &left_out)));
+        let error_pointer = Some(pointer(&original_steps(&error_steps, &left_out)));
+        errors.push(json_error(content, offset, error_pointer, message, model));
+
+        let Some(index) = steps.iter().rposition(|step| matches!(step, Step::Index(_))) else {
+            break;
+        };
+        if errors.len() >= MAX_ERRORS {
+            break;
+        }
+        let Step::Index(element) = steps[index] else { unreachable!() };
+        let array = pointer(&steps[..index]);
+        match value.pointer_mut(&array).and_then(Value::as_array_mut) {
+            Some(elements) if element < elements.len() => elements.remove(element),
+            _ => break,
+        };
+        let original_array = &original_steps(&steps[..=index], &left_out);
+        let Some((Step::Index(original), prefix)) = original_array.split_last() else {
+            unreachable!()
+        };
+        let removed = left_out.entry(pointer(prefix)).or_default();
+        removed.push(*original);
+        removed.sort_unstable();
+    }
+    errors.sort_by_key(|error| (error.line, error.column));
+    Err(JsonErrors(errors))
+}
+
+/// A step of the path of a value: the key of an object or the index of an array.
+#[derive(Clone, Debug, PartialEq)]
+enum Step {
+    Key(String),
+    Index(usize),
+}
+
+fn steps(path: &serde_path_to_error::Path) -> Vec<Step> {
+    let mut steps = Vec::new();
+    for segment in path.iter() {
+        steps.push(match segment {
+            Segment::Seq { index } => Step::Index(*index),
+            Segment::Map { key } => Step::Key(key.clone()),
+            Segment::Enum { variant } => Step::Key(variant.clone()),
+            Segment::Unknown => break,
+        });
+    }
+    steps
+}
+
+/// The JSON pointer of `steps`.
+fn pointer(steps: &[Step]) -> String {
+    steps
+        .iter()
+        .map(|step| match step {
+            Step::Key(key) => format!("/{}", key.replace('~', "~0").replace('/', "~1")),
+            Step::Index(index) => format!("/{}", index),
+        })
+        .collect()
+}
+
+/// The field of a `missing field` error.
+fn missing_field(message: &str) -> Option<String> {
+    let field = message.strip_prefix("missing field `")?.strip_suffix('`')?;
+    Some(field.to_string())
+}
+
+/// The steps of the input for the `steps` of the document whose elements `left_out`
+/// were removed.
+fn original_steps(steps: &[Step], left_out: &HashMap<String, Vec<usize>>) -> Vec<Step> {
+    let mut original: Vec<Step> = Vec::with_capacity(steps.len());
+    for step in steps {
+        let step = match step {
+            Step::Index(index) => {
+                let mut index = *index;
+                for removed in left_out.get(&pointer(&original)).into_iter().flatten() {
+                    if *removed <= index {
+                        index += 1;
+                    }
+                }
+                Step::Index(index)
+            }
+            key => key.clone(),
+        };
+        original.push(step);
+    }
+    original
+}
+
+/// What `T` expects at `steps` of `value` (or at its member `field`), e.g. `a string`.
+fn expected_at<T>(value: &Value, steps: &[Step], field: Option<&str>) -> Option<String>
+where
+    T: DeserializeOwned,
+{
+    let mut probe = value.clone();
+    let target = probe.pointer_mut(&pointer(steps))?;
+    let mut probed = steps.to_vec();
+    match field {
+        Some(field) => {
+            target.as_object_mut()?.insert(field.to_string(), Value::Bool(true));
+            probed.push(Step::Key(field.to_string()));
+        }
+        None => *target = Value::Bool(true),
+    }
+    let error = serde_path_to_error::deserialize::<_, T>(&probe).err()?;
+    if self::steps(error.path()) != probed {
+        return None;
+    }
+    let message = error.inner().to_string();
+    message.split_once(", expected ").map(|(_, expected)| expected.to_string())
+}
+
+/// The byte offset of the value at `pointer` in `content`, or of its closest ancestor in
+/// the input.
+fn locate(content: &str, pointer: &str) -> usize {
+    let offset = |raw: &RawValue| raw.get().as_ptr() as usize - content.as_ptr() as usize;
+    let Ok(mut raw) = serde_json::from_str::<&RawValue>(content) else {
+        return 0;
+    };
+    for segment in pointer.split('/').skip(1) {
+        let segment = segment.replace("~1", "/").replace("~0", "~");
+        let child = if raw.get().starts_with('[') {
+            let elements = serde_json::from_str::<Vec<&RawValue>>(raw.get()).ok();
+            segment.parse().ok().and_then(|index: usize| elements?.get(index).copied())
+        } else {
+            serde_json::from_str::<HashMap<String, &RawValue>>(raw.get())
+                .ok()
+                .and_then(|members| members.get(&segment).copied())
+        };
+        match child {
+            Some(child) => raw = child,
+            None => break,
+        }
+    }
+    offset(raw)
+}
+
+fn offset_of_line_column(content: &str, line: usize, column: usize) -> usize {
+    let start: usize =
+        content.split_inclusive('\n').take(line.saturating_sub(1)).map(str::len).sum();
+    let line = content[start..].lines().next().unwrap_or("");
+    start + line.char_indices().nth(column.saturating_sub(1)).map_or(line.len(), |(i, _)| i)
+}
+
+/// The error `message` of the value at `pointer` in `content`, shown at the value at
+/// `location` (e.g. the object of a missing value).
+pub(crate) fn error_at(
+    content: &str,
+    pointer: String,
+    location: &str,
+    message: String,
+    model: Option<String>,
+) -> JsonError {
+    json_error(content, locate(content, location), Some(pointer), message, model)
+}
+
+fn json_error(
+    content: &str,
+    offset: usize,
+    pointer: Option<String>,
+    message: String,
+    model: Option<String>,
+) -> JsonError {
+    let start = content[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let end = content[offset..].find('\n').map_or(content.len(), |i| offset + i);
+    let line: Vec<char> = content[start..end].trim_end_matches('\r').chars().collect();
+    let column = content[start..offset].chars().count() + 1;
+    // Long lines, e.g. of minified documents, are cut around the error.
+    let first = column.saturating_sub(SNIPPET_WIDTH / 2 + 1).min(line.len());
+    let last = (first + SNIPPET_WIDTH).min(line.len());
+    let snippet = format!(
+        "{}{}{}",
+        if first > 0 { "..." } else { "" },
+        line[first..last].iter().collect::<String>(),
+        if last < line.len() { "..." } else { "" }
+    );
+    JsonError {
+        pointer,
+        message,
+        model,
+        line: content[..start].matches('\n').count() + 1,
+        column,
+        snippet,
+        caret: column - first + if first > 0 { 3 } else { 0 },
+    }
+}
@@ -1 +1,6 @@
+pub mod batch;
 pub mod convert;
+pub mod detect;
+pub mod error;
+pub mod json_input;
+pub mod schema;
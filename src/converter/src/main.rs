@@ -1,79 +1,652 @@
 use anyhow::{Context, Result};
-use catplus_common::models::types::{Batch, CampaignWrapper};
-use clap::Parser;
-use converter::convert::{json_to_rdf, RdfFormat};
-use serde::Deserialize;
+use catplus_common::{
+    graph::{
+        cat_namespace::CatNamespace, chemical_registry::ChemicalKey, graph_builder::GraphConfig,
+        mapping::Mapping, utils::IriStrategy,
+    },
+    models::types::{Batch, CampaignWrapper},
+    rdf::jsonld_compaction::CompactionOptions,
+    shacl::shapes::Shapes,
+};
+use clap::{Parser, Subcommand};
+use converter::{
+    batch::{convert_directory, BatchOptions, FileStatus},
+    convert::{
+        convert_input, diff_rdf, merge_to_rdf_with_options, migrate_rdf, rdf_to_json,
+        ConvertOptions, InputType, RdfFormat,
+    },
+    detect::detect_input_type,
+    error::{ConvertError, ErrorKind},
+    schema::{input_schema, validate_input},
+};
 use std::{
     fs::File,
     io::{Read, Write},
-    path::Path,
+    path::{Path, PathBuf},
+    process::ExitCode,
 };
 
-// Derive Deserialize and ValueEnum
-#[derive(Deserialize, Debug, clap::ValueEnum, Clone)]
-enum InputType {
-    Synth,
-    HCI,
-}
-
 /// Converts CAT+ JSON input into RDF formats.
 ///
-/// This tool expects data similar to examples/1-Synth.json or examples/0-HCI.json
+/// This tool expects data similar to examples/1-Synth.json or examples/0-HCI.json,
+/// or an instrument export such as examples/2-Agilent.csv, examples/3-IR.jdx,
+/// examples/4-AnIML.animl, examples/5-ASM-plate-reader.json or examples/6-LC-MS.mzML.
 /// This data is then transformed to RDF and
 /// serialized as Turtle (ttl) or JSON-LD (jsonld).
+///
+/// With the `diff` command, compares two RDF files instead.
 #[derive(Parser, Debug)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
-    /// Type of input data: "Synth" or "HCI".
-    #[arg(value_enum)]
-    input_type: InputType,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Type of input data: "Synth", "HCI", "Agilent", "Jcamp", "Animl", "Asm", "Mzml", or
+    /// "Auto" to detect it from the content of the input file.
+    #[arg(value_enum, required = true)]
+    input_type: Option<InputType>,
 
-    /// Path to the input JSON file.
-    input_file: String,
+    /// Path to the input file.
+    #[arg(required = true)]
+    input_file: Option<String>,
 
     /// Path to the output RDF file.
-    output_file: String,
+    #[arg(required = true)]
+    output_file: Option<String>,
+
+    /// Format of the output RDF: "Turtle", "Jsonld", "CompactJsonld", "Ntriples", "Nquads",
+    /// "Trig" or "Rdfxml" (of the input RDF with `--to-json` and `--migrate`). Defaults to the format
+    /// of the extension of the output file (of the input file), e.g. `.nt` for N-Triples.
+    #[arg(value_enum)]
+    format: Option<RdfFormat>,
+
+    /// Also write the numeric data to a Zarr v3 store next to the output file
+    /// (the output path with a `.zarr` extension).
+    #[arg(long)]
+    zarr: bool,
+
+    /// Name batches, samples, chemicals... with IRIs derived from their identifiers under
+    /// this base IRI (e.g. `https://example.org/catplus/`) instead of random blank nodes,
+    /// so that the output is reproducible and can be linked across files.
+    #[arg(long)]
+    base_iri: Option<String>,
+
+    /// Property identifying a chemical: chemicals with the same value are represented by
+    /// a single node of the graph.
+    #[arg(long, value_enum, default_value_t = ChemicalKeyArg::ChemicalId)]
+    chemical_key: ChemicalKeyArg,
+
+    /// Convert back: read the input file as an RDF graph and write the Synth or HCI JSON
+    /// document it describes to the output file.
+    #[arg(long, conflicts_with_all = ["zarr", "base_iri", "validate", "mapping"])]
+    to_json: bool,
+
+    /// Namespace of the Cat+ ontology in the graph (and the input graph with `--to-json`),
+    /// instead of the placeholder `http://example.org/cat#`.
+    #[arg(long, env = "CATPLUS_NAMESPACE", value_name = "IRI")]
+    namespace: Option<String>,
+
+    /// Migrate: read the input file as an RDF graph of the placeholder Cat+ namespace,
+    /// whatever the input type, and write it in the namespace of `--namespace`.
+    #[arg(long, requires = "namespace", conflicts_with_all = ["to_json", "zarr", "base_iri", "validate", "mapping"])]
+    migrate: bool,
+
+    /// Validate the graph against the SHACL shapes of this Turtle file, failing the
+    /// conversion with a validation report if it does not conform.
+    #[arg(long, value_name = "SHAPES")]
+    validate: Option<String>,
+
+    /// Override the predicates, classes and datatypes of the built-in mapping with the
+    /// YAML mapping file MAPPING (see examples/catplus-mapping.yaml).
+    #[arg(long, value_name = "MAPPING")]
+    mapping: Option<String>,
+
+    /// Frame the compacted JSON-LD on the nodes of this class (e.g. `cat:Batch`), embedding
+    /// the other nodes into them.
+    #[arg(long, value_name = "CLASS")]
+    frame: Option<String>,
+
+    /// Reference the published prefix context at this URL (see
+    /// examples/catplus-context.jsonld) in the compacted JSON-LD instead of inlining it.
+    #[arg(long, value_name = "URL")]
+    jsonld_context: Option<String>,
+
+    /// Write the graph in canonical form: blank nodes labelled with RDF Dataset
+    /// Canonicalization (RDFC-1.0) and sorted triples, so that converting an unchanged
+    /// input yields a byte-identical file.
+    #[arg(long, conflicts_with_all = ["to_json", "migrate"])]
+    canonical: bool,
+
+    /// Merge the Synth file SYNTH_FILE into the HCI input, joining its batch on the
+    /// `batchID` of the campaign, so that a single graph links the campaign and the
+    /// actions to the same batch. Can be repeated.
+    #[arg(long, value_name = "SYNTH_FILE", conflicts_with_all = ["to_json", "migrate"])]
+    merge: Vec<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Compare two RDF files, e.g. two conversions of a batch, and report the triples
+    /// removed from OLD and added to NEW. Exits with status 1 if the graphs differ.
+    Diff(DiffArgs),
+    /// Convert every file of INPUT_DIR into OUTPUT_DIR, mirroring their paths, and write a
+    /// JSON manifest of the run. Exits with status 1 if a conversion failed.
+    Batch(BatchArgs),
+    /// Print the JSON Schema (draft 2020-12) of the Synth or HCI input files.
+    Schema(SchemaArgs),
+    /// Check Synth or HCI input files against their JSON Schema, without converting them.
+    /// Exits with status 6 if a file does not conform.
+    Validate(ValidateArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct SchemaArgs {
+    /// Type of the input files: "Synth" or "HCI".
+    #[arg(value_enum)]
+    input_type: InputType,
+}
 
-    /// Type of input data: "Turtle" or "Jsonld".
+#[derive(clap::Args, Debug)]
+struct ValidateArgs {
+    /// Type of the input files: "Synth", "HCI", or "Auto" to detect the type of every file.
     #[arg(value_enum)]
+    input_type: InputType,
+
+    /// Paths to the input files (`-` for the standard input).
+    #[arg(required = true)]
+    input_files: Vec<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct BatchArgs {
+    /// Path to the directory of the input files, searched recursively.
+    input_dir: String,
+
+    /// Path to the directory of the output files.
+    output_dir: String,
+
+    /// Type of the input files, or `auto` to detect the type of every file.
+    #[arg(long, value_enum, default_value_t = InputType::Synth)]
+    input_type: InputType,
+
+    /// Format of the output RDF.
+    #[arg(long, value_enum, default_value_t = RdfFormat::Turtle)]
     format: RdfFormat,
+
+    /// Convert the files matching this glob pattern only, e.g. `*.json` or
+    /// `2024/**/*.json` (relative to INPUT_DIR). Can be repeated.
+    #[arg(long, value_name = "GLOB")]
+    include: Vec<String>,
+
+    /// Leave out the files matching this glob pattern. Can be repeated.
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Number of files converted in parallel. Defaults to the number of CPUs.
+    #[arg(long)]
+    jobs: Option<usize>,
+
+    /// Path to the manifest of the run. Defaults to `manifest.json` in OUTPUT_DIR.
+    #[arg(long)]
+    manifest: Option<String>,
+
+    /// Also write the numeric data of every file to a Zarr v3 store next to its output.
+    #[arg(long)]
+    zarr: bool,
+
+    /// Name the nodes with IRIs derived from their identifiers under this base IRI, as
+    /// for a single conversion.
+    #[arg(long)]
+    base_iri: Option<String>,
+
+    /// Namespace of the Cat+ ontology in the graphs.
+    #[arg(long, env = "CATPLUS_NAMESPACE", value_name = "IRI")]
+    namespace: Option<String>,
+
+    /// Property identifying a chemical.
+    #[arg(long, value_enum, default_value_t = ChemicalKeyArg::ChemicalId)]
+    chemical_key: ChemicalKeyArg,
+
+    /// Write the graphs in canonical form.
+    #[arg(long)]
+    canonical: bool,
+}
+
+/// The property identifying a chemical, see [ChemicalKey].
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum ChemicalKeyArg {
+    /// The `chemicalID` of the input.
+    ChemicalId,
+    /// The InChI of the compound.
+    Inchi,
+    /// The CAS registry number; chemicals without one are not deduplicated.
+    Cas,
+}
+
+impl From<ChemicalKeyArg> for ChemicalKey {
+    fn from(key: ChemicalKeyArg) -> Self {
+        match key {
+            ChemicalKeyArg::ChemicalId => ChemicalKey::ChemicalId,
+            ChemicalKeyArg::Inchi => ChemicalKey::Inchi,
+            ChemicalKeyArg::Cas => ChemicalKey::Cas,
+        }
+    }
 }
 
-fn main() -> Result<()> {
+#[derive(clap::Args, Debug)]
+struct DiffArgs {
+    /// Path to the old RDF file.
+    old_file: String,
+
+    /// Path to the new RDF file.
+    new_file: String,
+
+    /// Format of both RDF files. Defaults to the format of the extension of each file.
+    #[arg(long, value_enum)]
+    format: Option<RdfFormat>,
+
+    /// Report the differences as JSON, with the removed and added triples in N-Triples
+    /// syntax.
+    #[arg(long)]
+    json: bool,
+}
+
+/// The exit status of the converter: 0 on success, 1 for the differences of the diff
+/// command, the failed files of the batch command and the other failures, 2 for invalid
+/// arguments, and by kind of failure (see [exit_code]) otherwise.
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Error: {:?}", error);
+            ExitCode::from(exit_code(&error))
+        }
+    }
+}
+
+/// The exit status of a failed run: 2 for invalid arguments, 3 for I/O errors, 4 for
+/// invalid inputs, 5 for mapping errors and 6 for graphs that do not conform to the SHACL
+/// shapes.
+fn exit_code(error: &anyhow::Error) -> u8 {
+    match ErrorKind::of(error) {
+        Some(ErrorKind::Argument) => 2,
+        Some(ErrorKind::Io) => 3,
+        Some(ErrorKind::Parse) => 4,
+        Some(ErrorKind::Mapping) => 5,
+        Some(ErrorKind::Validation) => 6,
+        None => 1,
+    }
+}
+
+fn run() -> Result<()> {
     let args = Args::parse();
+    match &args.command {
+        Some(Command::Diff(diff_args)) => return diff(diff_args),
+        Some(Command::Batch(batch_args)) => return batch(batch_args),
+        Some(Command::Schema(schema_args)) => return schema(schema_args),
+        Some(Command::Validate(validate_args)) => return validate(validate_args),
+        None => {}
+    }
+    let (Some(input_type), Some(input_file), Some(output_file)) =
+        (args.input_type, args.input_file.clone(), args.output_file.clone())
+    else {
+        unreachable!("the arguments of a conversion are required without a command");
+    };
+
+    // Read input file
+    let input_content = read_input_file(&input_file)?;
+    let input_path = Path::new(&input_file);
 
-    // Validate input file
-    let input_path = Path::new(&args.input_file);
+    let output_path = Path::new(&output_file);
+    let format_path = if args.to_json || args.migrate { input_path } else { output_path };
+    let format = match args.format.clone() {
+        Some(format) => format,
+        None => infer_format(format_path)?,
+    };
+    if args.zarr && output_file == STDIO {
+        anyhow::bail!(argument_error(
+            "--zarr needs an output file, next to which the Zarr store is written"
+        ));
+    }
+    let zarr_path = args.zarr.then(|| output_path.with_extension("zarr"));
+    let shapes = args
+        .validate
+        .as_ref()
+        .map(|path| -> Result<Shapes> {
+            let turtle = std::fs::read_to_string(path).with_context(|| {
+                ConvertError::new(ErrorKind::Io, format!("Failed to read shapes file '{}'", path))
+            })?;
+            Shapes::from_turtle(&turtle).with_context(|| {
+                ConvertError::new(
+                    ErrorKind::Parse,
+                    format!("Failed to load shapes from '{}'", path),
+                )
+            })
+        })
+        .transpose()?;
+    let compaction =
+        CompactionOptions { frame: args.frame.clone(), context_url: args.jsonld_context.clone() };
+    if (compaction.frame.is_some() || compaction.context_url.is_some())
+        && !matches!(format, RdfFormat::CompactJsonld)
+    {
+        anyhow::bail!(argument_error(
+            "--frame and --jsonld-context apply to the compact-jsonld format only"
+        ));
+    }
+    let graph = graph_config(
+        args.base_iri.as_deref(),
+        args.namespace.as_deref(),
+        args.chemical_key,
+        args.mapping.as_deref(),
+    )?;
+    let options = ConvertOptions {
+        zarr_path: zarr_path.as_deref(),
+        shapes: shapes.as_ref(),
+        compaction: Some(&compaction),
+        canonical: args.canonical,
+        graph: Some(&graph),
+    };
+
+    if args.migrate {
+        let rdf = migrate_rdf(&input_content, &format, &graph.cat_namespace)
+            .context("Failed to migrate the RDF graph to the Cat+ namespace")?;
+        write_output_file(&output_file, &rdf)?;
+        eprintln!("Migration successful!");
+        return Ok(());
+    }
+
+    if args.to_json {
+        let json = match input_type {
+            InputType::Synth => rdf_to_json::<Batch>(&input_content, &format, &graph.cat_namespace),
+            InputType::HCI => {
+                rdf_to_json::<CampaignWrapper>(&input_content, &format, &graph.cat_namespace)
+            }
+            InputType::Auto => {
+                anyhow::bail!(argument_error("--to-json needs the input type, synth or hci"))
+            }
+            other => anyhow::bail!(argument_error(format!(
+                "Cannot convert RDF back to {:?} input",
+                other
+            ))),
+        }
+        .context("Failed to convert RDF to JSON")?;
+        write_output_file(&output_file, &json)?;
+        eprintln!("Conversion successful!");
+        return Ok(());
+    }
+
+    let input_type = match input_type {
+        InputType::Auto => {
+            let detected = detect_input_type(&input_content).with_context(|| {
+                format!("Failed to detect the type of input file '{}'", input_file)
+            })?;
+            eprintln!("Detected input type: {}", detected);
+            detected
+        }
+        input_type => input_type,
+    };
+    if !args.merge.is_empty() && !matches!(input_type, InputType::HCI) {
+        anyhow::bail!(argument_error("--merge merges Synth files into an HCI input only"));
+    }
+    let synth_contents =
+        args.merge.iter().map(|path| read_input_file(path)).collect::<Result<Vec<_>>>()?;
+    let synth_inputs: Vec<(&str, &str)> = args
+        .merge
+        .iter()
+        .map(String::as_str)
+        .zip(synth_contents.iter().map(String::as_str))
+        .collect();
+
+    // Unified conversion function with type selection
+    let conversion = match input_type {
+        InputType::HCI if !synth_inputs.is_empty() => {
+            merge_to_rdf_with_options(&input_content, &synth_inputs, &format, options)
+        }
+        input_type => convert_input(input_type, &input_content, &format, options),
+    }
+    .with_context(|| format!("Failed to convert input to RDF format '{:?}'", format))?;
+    for warning in &conversion.warnings {
+        eprintln!("Warning: {}", warning);
+    }
+
+    write_output_file(&output_file, &conversion.output)?;
+    eprintln!("Conversion successful!");
+    if let Some(zarr_path) = zarr_path {
+        eprintln!("Zarr store written to '{}'", zarr_path.display());
+    }
+    Ok(())
+}
+
+/// The path of the standard input as input file, and of the standard output as output
+/// file.
+const STDIO: &str = "-";
+
+/// The RDF format of the extension of `path`.
+fn infer_format(path: &Path) -> Result<RdfFormat> {
+    if path == Path::new(STDIO) {
+        anyhow::bail!(argument_error(
+            "Cannot infer the RDF format of the standard input or output, give the format"
+        ));
+    }
+    RdfFormat::from_extension(path).with_context(|| {
+        argument_error(format!(
+            "Cannot infer the RDF format of '{}' from its extension",
+            path.display()
+        ))
+    })
+}
+
+/// The context of the errors of invalid or conflicting arguments, exiting with status 2
+/// like the errors reported by clap.
+fn argument_error(message: impl Into<String>) -> ConvertError {
+    ConvertError::new(ErrorKind::Argument, message)
+}
+
+/// Reads the content of the input file `path` (the standard input for `-`), checking
+/// that it is a file.
+fn read_input_file(path: &str) -> Result<String> {
+    let io_error = |message: String| ConvertError::new(ErrorKind::Io, message);
+    let mut input_content = String::new();
+    if path == STDIO {
+        std::io::stdin()
+            .read_to_string(&mut input_content)
+            .with_context(|| io_error("Failed to read the standard input".to_string()))?;
+        return Ok(input_content);
+    }
+    let input_path = Path::new(path);
     if !input_path.exists() {
-        anyhow::bail!("Input file '{}' does not exist.", args.input_file);
+        anyhow::bail!(io_error(format!("Input file '{}' does not exist.", path)));
     }
     if !input_path.is_file() {
-        anyhow::bail!("'{}' is not a valid file.", args.input_file);
+        anyhow::bail!(io_error(format!("'{}' is not a valid file.", path)));
     }
 
-    // Read input file
-    let mut input_content = String::new();
     File::open(input_path)
-        .with_context(|| format!("Failed to open input file '{}'", args.input_file))?
+        .with_context(|| io_error(format!("Failed to open input file '{}'", path)))?
         .read_to_string(&mut input_content)
-        .with_context(|| format!("Failed to read input file '{}'", args.input_file))?;
+        .with_context(|| io_error(format!("Failed to read input file '{}'", path)))?;
+    Ok(input_content)
+}
 
-    // Unified conversion function with type selection
-    let serialized_graph = match args.input_type {
-        InputType::Synth => json_to_rdf::<Batch>(&input_content, &args.format),
-        InputType::HCI => json_to_rdf::<CampaignWrapper>(&input_content, &args.format),
+/// Writes `content` to the output file `path` (the standard output for `-`).
+fn write_output_file(path: &str, content: &str) -> Result<()> {
+    let io_error = |message: String| ConvertError::new(ErrorKind::Io, message);
+    if path == STDIO {
+        let mut stdout = std::io::stdout().lock();
+        return stdout
+            .write_all(content.as_bytes())
+            .and_then(|()| stdout.flush())
+            .with_context(|| io_error("Failed to write to the standard output".to_string()));
     }
-    .with_context(|| format!("Failed to convert JSON to RDF format '{:?}'", &args.format))?;
+    File::create(path)
+        .with_context(|| io_error(format!("Failed to create output file '{}'", path)))?
+        .write_all(content.as_bytes())
+        .with_context(|| io_error(format!("Failed to write to output file '{}'", path)))?;
+    eprintln!("Processed content written to '{}'", path);
+    Ok(())
+}
+
+/// Prints the differences between the graphs of the diff command, exiting with status 1
+/// if there are any.
+fn diff(args: &DiffArgs) -> Result<()> {
+    let format = |path: &str| -> Result<RdfFormat> {
+        match args.format.clone() {
+            Some(format) => Ok(format),
+            None => infer_format(Path::new(path)),
+        }
+    };
+    let graph_diff = diff_rdf(
+        &read_input_file(&args.old_file)?,
+        &format(&args.old_file)?,
+        &read_input_file(&args.new_file)?,
+        &format(&args.new_file)?,
+    )
+    .context("Failed to compare the RDF graphs")?;
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&graph_diff.to_json())?);
+    } else if graph_diff.is_empty() {
+        println!("The graphs are the same.");
+    } else {
+        print!("{}", graph_diff);
+        println!(
+            "{} triples removed, {} triples added",
+            graph_diff.removed.len(),
+            graph_diff.added.len()
+        );
+    }
+    if !graph_diff.is_empty() {
+        anyhow::bail!("The graphs differ");
+    }
+    Ok(())
+}
+
+/// The settings of the graphs: their nodes are named under `base_iri` if set (see
+/// [IriStrategy::ContentDerived]), in the Cat+ `namespace` if set, with the mapping
+/// overrides of the YAML file at `mapping` if set.
+fn graph_config(
+    base_iri: Option<&str>,
+    namespace: Option<&str>,
+    chemical_key: ChemicalKeyArg,
+    mapping: Option<&str>,
+) -> Result<GraphConfig> {
+    let iri_strategy = match base_iri {
+        Some(base) => IriStrategy::content_derived(base)
+            .context(argument_error(format!("Invalid --base-iri '{}'", base)))?,
+        None => IriStrategy::Random,
+    };
+    let cat_namespace = namespace
+        .map(|namespace| {
+            CatNamespace::new(namespace)
+                .context(argument_error(format!("Invalid --namespace '{}'", namespace)))
+        })
+        .transpose()?
+        .unwrap_or_default();
+    let mapping = match mapping {
+        Some(path) => {
+            let yaml = std::fs::read_to_string(path).with_context(|| {
+                ConvertError::new(ErrorKind::Io, format!("Failed to read mapping file '{}'", path))
+            })?;
+            Mapping::from_yaml(&yaml).with_context(|| {
+                ConvertError::new(
+                    ErrorKind::Mapping,
+                    format!("Failed to load mapping from '{}'", path),
+                )
+            })?
+        }
+        None => Mapping::default(),
+    };
+    Ok(GraphConfig { iri_strategy, chemical_key: chemical_key.into(), mapping, cat_namespace })
+}
+
+/// Converts the files of a directory, writing the manifest of the run and exiting with
+/// status 1 if a conversion failed.
+fn batch(args: &BatchArgs) -> Result<()> {
+    let options = BatchOptions {
+        input_type: args.input_type,
+        format: args.format.clone(),
+        include: args.include.clone(),
+        exclude: args.exclude.clone(),
+        jobs: args
+            .jobs
+            .unwrap_or_else(|| std::thread::available_parallelism().map(usize::from).unwrap_or(1)),
+        zarr: args.zarr,
+        canonical: args.canonical,
+        graph: graph_config(
+            args.base_iri.as_deref(),
+            args.namespace.as_deref(),
+            args.chemical_key,
+            None,
+        )?,
+    };
+    let output_dir = Path::new(&args.output_dir);
+    std::fs::create_dir_all(output_dir).with_context(|| {
+        ConvertError::new(
+            ErrorKind::Io,
+            format!("Failed to create output directory '{}'", args.output_dir),
+        )
+    })?;
 
-    println!("Conversion successful!");
+    let manifest = convert_directory(Path::new(&args.input_dir), output_dir, &options)?;
+
+    let manifest_path = args
+        .manifest
+        .as_ref()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| output_dir.join("manifest.json"));
+    std::fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?).with_context(
+        || {
+            ConvertError::new(
+                ErrorKind::Io,
+                format!("Failed to write manifest '{}'", manifest_path.display()),
+            )
+        },
+    )?;
+    for file in &manifest.files {
+        for warning in &file.warnings {
+            eprintln!("Warning: '{}': {}", file.input, warning);
+        }
+    }
+    for file in manifest.files.iter().filter(|file| file.status == FileStatus::Failed) {
+        eprintln!("Failed to convert '{}': {}", file.input, file.error.as_deref().unwrap_or(""));
+    }
+    eprintln!("{} files converted, {} failed", manifest.converted, manifest.failed);
+    eprintln!("Manifest written to '{}'", manifest_path.display());
+    if manifest.failed > 0 {
+        anyhow::bail!("{} of {} files failed to convert", manifest.failed, manifest.files.len());
+    }
+    Ok(())
+}
 
-    // Write to output file
-    let output_path = Path::new(&args.output_file);
-    let mut output = File::create(output_path)
-        .with_context(|| format!("Failed to create output file '{}'", args.output_file))?;
-    output
-        .write_all(serialized_graph.as_bytes())
-        .with_context(|| format!("Failed to write to output file '{}'", args.output_file))?;
+/// Prints the JSON Schema of an input type.
+fn schema(args: &SchemaArgs) -> Result<()> {
+    let schema = input_schema(args.input_type)?;
+    println!("{}", serde_json::to_string_pretty(&schema)?);
+    Ok(())
+}
 
-    println!("Processed content written to '{}'", output_path.display());
+/// Checks the input files against their JSON Schema, reporting the errors of every file
+/// that does not conform.
+fn validate(args: &ValidateArgs) -> Result<()> {
+    let mut invalid = 0;
+    for input_file in &args.input_files {
+        match validate_input(args.input_type, &read_input_file(input_file)?) {
+            Ok(()) => eprintln!("'{}' conforms to the JSON Schema", input_file),
+            Err(error) => {
+                eprintln!("'{}': {:#}\n", input_file, error);
+                invalid += 1;
+            }
+        }
+    }
+    if invalid > 0 {
+        anyhow::bail!(ConvertError::new(
+            ErrorKind::Validation,
+            format!(
+                "{} of {} files do not conform to the JSON Schema",
+                invalid,
+                args.input_files.len()
+            ),
+        ));
+    }
     Ok(())
 }
@@ -0,0 +1,129 @@
+// JSON Schemas (draft 2020-12) of the JSON input files, generated from the models, so
+// that the integrators of the instruments have a contract of what the converter accepts
+// and can check their files against it without converting them.
+//
+// The schemas follow the serde attributes of the models: the renamed fields, the strings
+// of the units (e.g. `°C`) and the names of the actions.
+use crate::{
+    convert::InputType,
+    detect::detect_input_type,
+    error::{ConvertError, ErrorKind},
+    json_input::{error_at, parse_json, JsonError, JsonErrors},
+};
+use anyhow::{anyhow, bail, Context, Result};
+use catplus_common::models::types::{Batch, CampaignWrapper};
+use jsonschema::error::ValidationErrorKind;
+use schemars::schema_for;
+use serde_json::Value;
+
+/// The JSON Schema of the input files of type `input_type`.
+///
+/// # Returns
+/// A `Result` containing the schema, or an error if the input type is not a JSON
+/// document of the models (`synth` or `hci`).
+pub fn input_schema(input_type: InputType) -> Result<Value> {
+    let schema = match input_type {
+        InputType::Synth => schema_for!(Batch),
+        InputType::HCI => schema_for!(CampaignWrapper),
+        other => bail!("There is no JSON Schema of {} input, only of synth and hci input", other),
+    };
+    Ok(schema.to_value())
+}
+
+/// Checks the JSON document `content` against the JSON Schema of `input_type` (detected
+/// from the content for [InputType::Auto]).
+///
+/// # Returns
+/// A `Result` containing nothing if the document conforms to the schema, or an error
+/// with the [JsonErrors] of the document.
+pub fn validate_input(input_type: InputType, content: &str) -> Result<()> {
+    let input_type = match input_type {
+        InputType::Auto => detect_input_type(content)?,
+        input_type => input_type,
+    };
+    let schema = input_schema(input_type)?;
+    let document: Value = parse_json(content)
+        .context(ConvertError::new(ErrorKind::Parse, "Failed to parse JSON input"))?;
+
+    let mut errors = Vec::new();
+    collect_errors(&schema, &document, "", content, &mut errors)?;
+    if errors.is_empty() {
+        return Ok(());
+    }
+    errors.sort_by_key(|error| (error.line, error.column));
+    Err(anyhow::Error::new(JsonErrors(errors)).context(ConvertError::new(
+        ErrorKind::Validation,
+        format!("The input does not conform to the JSON Schema of {} input", input_type),
+    )))
+}
+
+/// Adds the errors of `instance`, the value at `base` of the document `content`, against
+/// `schema` to `errors`.
+///
+/// The errors of an optional value, i.e. of `anyOf` its schema and `null`, are the errors
+/// of the value against its schema rather than the `anyOf` error itself.
+fn collect_errors(
+    schema: &Value,
+    instance: &Value,
+    base: &str,
+    content: &str,
+    errors: &mut Vec<JsonError>,
+) -> Result<()> {
+    let validator =
+        jsonschema::draft202012::new(schema).map_err(|e| anyhow!("Invalid JSON Schema: {}", e))?;
+    for error in validator.iter_errors(instance) {
+        let location = format!("{}{}", base, error.instance_path.as_str());
+        let schema_path = error.schema_path.as_str();
+        if let ValidationErrorKind::AnyOf = error.kind {
+            if let Some(value_schema) = optional_value_schema(schema, schema_path) {
+                collect_errors(&value_schema, &error.instance, &location, content, errors)?;
+                continue;
+            }
+        }
+        let pointer = match &error.kind {
+            ValidationErrorKind::Required { property: Value::String(property) } => {
+                format!("{}/{}", location, property.replace('~', "~0").replace('/', "~1"))
+            }
+            _ => location.clone(),
+        };
+        let model = walk(schema, schema_path).and_then(|(_, model)| model);
+        errors.push(error_at(content, pointer, &location, error.to_string(), model));
+    }
+    Ok(())
+}
+
+/// The schema of the value of the `anyOf` at `schema_path` of `schema`, if it is an
+/// optional value (`anyOf` a schema and `null`), with the definitions of `schema`.
+fn optional_value_schema(schema: &Value, schema_path: &str) -> Option<Value> {
+    let (any_of, _) = walk(schema, schema_path)?;
+    let null = serde_json::json!({"type": "null"});
+    let [value] = any_of.as_array()?.iter().filter(|s| **s != null).collect::<Vec<_>>()[..] else {
+        return None;
+    };
+    let mut value_schema = value.clone();
+    value_schema.as_object_mut()?.insert("$defs".to_string(), schema.get("$defs")?.clone());
+    Some(value_schema)
+}
+
+/// The node at `schema_path` of `schema`, following its references, with its model: the
+/// definition of the last object referenced on the path, or the title of the schema.
+fn walk<'a>(schema: &'a Value, schema_path: &str) -> Option<(&'a Value, Option<String>)> {
+    let mut model = schema.get("title").and_then(Value::as_str).map(str::to_string);
+    let mut node = schema;
+    for segment in schema_path.split('/').skip(1) {
+        node = if segment == "$ref" {
+            let definition = node.get("$ref")?.as_str()?.strip_prefix('#')?;
+            let target = schema.pointer(definition)?;
+            if target.get("type").and_then(Value::as_str) == Some("object") {
+                model = definition.rsplit('/').next().map(str::to_string);
+            }
+            target
+        } else {
+            match node {
+                Value::Array(elements) => elements.get(segment.parse::<usize>().ok()?)?,
+                _ => node.get(segment)?,
+            }
+        };
+    }
+    Some((node, model))
+}
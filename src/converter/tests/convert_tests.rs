@@ -1,9 +1,61 @@
+// The baseline conversion tests borrow their Turtle strings and compare the outcome of
+// the isomorphism check with a bool.
+#![allow(clippy::needless_borrow, clippy::bool_assert_comparison)]
+
 use catplus_common::{
+    graph::{
+        cat_namespace::CatNamespace, from_graph::FromGraph, graph_builder::GraphConfig,
+        insert_into::InsertIntoGraph, prefix_map::generate_prefix_map, utils::IriStrategy,
+    },
     models::types::{Batch, CampaignWrapper},
-    rdf::rdf_parser::parse_turtle_to_graph,
+    rdf::{
+        jsonld_compaction::{prefix_context, CompactionOptions},
+        rdf_parser::{
+            parse_jsonld_to_graph, parse_nquads_to_graph, parse_ntriples_to_graph,
+            parse_trig_to_graph, parse_turtle_to_graph,
+        },
+    },
+    shacl::shapes::Shapes,
+};
+use converter::{
+    batch::{convert_directory, glob_match, BatchOptions, FileStatus},
+    convert::{
+        convert_input, diff_rdf, json_to_rdf, merge_to_rdf_with_options, migrate_rdf, rdf_to_json,
+        ConvertOptions, InputType, RdfFormat,
+    },
+    detect::detect_input_type,
+    error::ErrorKind,
+    json_input::{parse_json, JsonErrors},
+    schema::{input_schema, validate_input},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use sophia_api::{
+    graph::Graph,
+    ns::rdf,
+    term::{matcher::Any, IriRef, SimpleTerm, Term},
+    triple::Triple,
 };
-use converter::convert::{json_to_rdf, RdfFormat};
 use sophia_isomorphism::isomorphic_graphs;
+use std::collections::BTreeSet;
+
+/// Converts `input_content` with the default options, returning the serialized graph.
+fn convert(
+    input_type: InputType,
+    input_content: &str,
+    format: &RdfFormat,
+) -> anyhow::Result<String> {
+    convert_with(input_type, input_content, format, ConvertOptions::default())
+}
+
+/// Converts `input_content` with `options`, returning the serialized graph.
+fn convert_with(
+    input_type: InputType,
+    input_content: &str,
+    format: &RdfFormat,
+    options: ConvertOptions,
+) -> anyhow::Result<String> {
+    convert_input(input_type, input_content, format, options).map(|conversion| conversion.output)
+}
 
 #[test]
 fn test_convert_filtrate_action() {
@@ -337,6 +389,7 @@ fn test_convert_add_action() {
             schema:name "23"];
         cat:hasContainerPositionAndQuantity [ a cat:ContainerPositionAndQuantity;
             cat:containerID "1";
+            schema:position 2;
             allores:AFR_0002240 "B1";
             qudt:quantity [ a cat:Observation;
                 cat:errorMargin [ a cat:errorMargin;
@@ -346,6 +399,7 @@ fn test_convert_add_action() {
                 qudt:value "0.034"^^xsd:double]],
             [ a cat:ContainerPositionAndQuantity;
             cat:containerID "1";
+            schema:position 1;
             allores:AFR_0002240 "A1";
             qudt:quantity [ a cat:Observation;
                 cat:errorMargin [ a cat:errorMargin;
@@ -538,6 +592,7 @@ fn test_convert_set_vacuum_action() {
         PREFIX schema: <https://schema.org/>
         PREFIX allores: <http://purl.allotrope.org/ontologies/result#>
         PREFIX qudt: <http://qudt.org/schema/qudt/>
+        PREFIX unit: <https://qudt.org/vocab/unit/>
         PREFIX alloqual: <http://purl.allotrope.org/ontologies/quality#>
         PREFIX purl: <http://purl.allotrope.org/ontologies/>
         PREFIX obo: <http://purl.obolibrary.org/obo/>
@@ -549,6 +604,12 @@ fn test_convert_set_vacuum_action() {
         cat:hasBatch [ a cat:Batch;
             schema:name "23"];
         cat:subEquipmentName "vacuum";
+        cat:vacuum [ a cat:Observation;
+            cat:errorMargin [ a cat:errorMargin;
+                qudt:unit unit:Bar;
+                qudt:value "0.5"^^xsd:double];
+            qudt:unit unit:Bar;
+            qudt:value "20"^^xsd:double];
         allores:AFR_0001606 "set_vacuum";
         allores:AFR_0001723 "Chemspeed SWING XL";
         allores:AFR_0002423 "2024-07-25T12:03:50"^^xsd:dateTime;
@@ -685,7 +746,9 @@ fn test_convert_campaign() {
         cat:campaignType "optimization";
         cat:genericObjective "High caffeine yield at the end";
         cat:hasBatch [ a cat:Batch;
+            cat:batchName "20240516";
             cat:optimizationType "Yield optimization";
+            cat:reactionName "Caffeine synthesis";
             cat:reactionType "N-methylation";
             allohdf:HardLink "https://www.sciencedirect.com/science/article/pii/S0187893X15720926";
             schema:name "23"];
@@ -694,6 +757,7 @@ fn test_convert_campaign() {
             cat:chemicalName "Sodium methoxide";
             cat:swissCatNumber "SwissCAT-10942334";
             purl:identifier "19";
+            schema:position 1;
             allores:AFR_0001952 "CH3NaO";
             allores:AFR_0002294 [ a cat:Observation;
                 qudt:unit unit:GM-PER-MOL;
@@ -709,6 +773,7 @@ fn test_convert_campaign() {
             cat:chemicalName "theobromine";
             cat:swissCatNumber "SwissCAT-5429";
             purl:identifier "36";
+            schema:position 2;
             allores:AFR_0001952 "C7H8N4O2";
             allores:AFR_0002294 [ a cat:Observation;
                 qudt:unit unit:GM-PER-MOL;
@@ -724,6 +789,7 @@ fn test_convert_campaign() {
             cat:chemicalName "methyl iodide";
             cat:swissCatNumber "SwissCAT-6328";
             purl:identifier "25";
+            schema:position 3;
             allores:AFR_0001952 "CH3I";
             allores:AFR_0002294 [ a cat:Observation;
                 qudt:unit unit:GM-PER-MOL;
@@ -739,6 +805,7 @@ fn test_convert_campaign() {
             cat:chemicalName "methanol";
             cat:swissCatNumber "SwissCAT-887";
             purl:identifier "79";
+            schema:position 4;
             allores:AFR_0001952 "CH4O";
             allores:AFR_0002294 [ a cat:Observation;
                 qudt:unit unit:GM-PER-MOL;
@@ -764,3 +831,1241 @@ fn test_convert_campaign() {
     let graphs_match = isomorphic_graphs(&result_graph, &expected_graph);
     assert_eq!(graphs_match.unwrap(), true);
 }
+
+#[test]
+fn test_convert_with_zarr_store() {
+    let json_data = r#"
+        {
+            "batchID": "23",
+            "Actions": []
+        }
+    "#;
+    let dir = tempfile::tempdir().unwrap();
+    let zarr_path = dir.path().join("batch.zarr");
+    let result = convert_with(
+        InputType::Synth,
+        json_data,
+        &RdfFormat::Turtle,
+        ConvertOptions { zarr_path: Some(&zarr_path), ..Default::default() },
+    );
+    assert!(result.is_ok());
+
+    let root_metadata: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(zarr_path.join("zarr.json")).unwrap())
+            .unwrap();
+    assert_eq!(root_metadata["zarr_format"], 3);
+    assert_eq!(root_metadata["node_type"], "group");
+
+    let jsonld = &root_metadata["attributes"]["jsonld"];
+    assert_eq!(jsonld["@context"]["cat"], "http://example.org/cat#");
+    assert_eq!(jsonld["@graph"][0]["@type"], "cat:Batch");
+    assert_eq!(jsonld["@graph"][0]["name"], "23");
+}
+
+#[test]
+fn test_convert_with_content_derived_iris() {
+    let json_data = r#"
+        {
+            "batchID": "23",
+            "Actions": [
+                {
+                    "actionName": "setTemperatureAction",
+                    "startTime": "2024-07-25T12:00:00",
+                    "endingTime": "2024-07-25T12:00:20",
+                    "methodName": "set_temperature",
+                    "equipmentName": "Chemspeed SWING XL",
+                    "subEquipmentName": "heater",
+                    "temperatureShaker": {"value": 25, "unit": "°C"}
+                }
+            ]
+        }
+    "#;
+    let graph = GraphConfig {
+        iri_strategy: IriStrategy::content_derived("https://example.org/").unwrap(),
+        ..Default::default()
+    };
+    let options = ConvertOptions { graph: Some(&graph), ..Default::default() };
+    let convert = |input_type, content| {
+        convert_input(input_type, content, &RdfFormat::Turtle, options).unwrap().output
+    };
+    let first = convert(InputType::Synth, json_data);
+    let second = convert(InputType::Synth, json_data);
+    let agilent =
+        convert(InputType::Agilent, "Batch ID,23\n[Peaks] DAD1 A\nPeak,RetTime (min)\n1,0.5\n");
+
+    assert_eq!(first, second);
+    let batch_iri = "<https://example.org/batch/";
+    assert!(first.contains(batch_iri));
+    let batch_node = |ttl: &str| {
+        let start = ttl.find(batch_iri).unwrap();
+        ttl[start..].split_once('>').unwrap().0.to_string()
+    };
+    assert_eq!(batch_node(&first), batch_node(&agilent));
+}
+
+#[test]
+fn test_convert_keeps_conflicting_chemicals_apart() {
+    // Chemical 134 is Toluene in a sample and 4-methoxybenzaldehyde in another.
+    let synth_data = include_str!("../../../examples/1-Synth.json");
+    let expected: BTreeSet<(String, String)> = [
+        ("123", "Toluene"),
+        ("124", "4-methoxybenzaldehyde"),
+        ("147", "Styrene"),
+        ("147", "4-(Difluoromethyl)benzyl bromide"),
+    ]
+    .iter()
+    .map(|(sample, name)| (sample.to_string(), name.to_string()))
+    .collect();
+    let content_derived = GraphConfig {
+        iri_strategy: IriStrategy::content_derived("https://example.org/").unwrap(),
+        ..Default::default()
+    };
+
+    for graph_config in [GraphConfig::default(), content_derived] {
+        let options = ConvertOptions { graph: Some(&graph_config), ..Default::default() };
+        let conversion =
+            convert_input(InputType::Synth, synth_data, &RdfFormat::Turtle, options).unwrap();
+        assert!(conversion.warnings.iter().any(|w| w.contains("chemical '134'")));
+
+        let graph = parse_turtle_to_graph(&conversion.output).unwrap();
+        let identifier = IriRef::new_unchecked("http://purl.allotrope.org/ontologies/identifier");
+        let has_chemical = IriRef::new_unchecked("http://example.org/cat#hasChemical");
+        let chemical_name = IriRef::new_unchecked("http://example.org/cat#chemicalName");
+        let literal = |subject: &SimpleTerm, predicate: &IriRef<&str>| {
+            let mut objects = graph.triples_matching([subject], [predicate], Any);
+            let value = objects.next().unwrap().unwrap().o().lexical_form().unwrap().to_string();
+            assert!(objects.next().is_none(), "{:?} has several {:?}", subject, predicate);
+            value
+        };
+        let samples: BTreeSet<(String, String)> = graph
+            .triples_matching(Any, [&has_chemical], Any)
+            .map(|t| t.unwrap())
+            .filter(|t| graph.triples_matching([t.s()], [&identifier], Any).next().is_some())
+            .map(|t| (literal(t.s(), &identifier), literal(t.o(), &chemical_name)))
+            .collect();
+        assert_eq!(samples, expected);
+    }
+}
+
+#[test]
+fn test_convert_agilent_export() {
+    let output_format = RdfFormat::Turtle;
+    let csv_data = "\
+Batch ID,23
+Method,HPLC_gradient.M
+Instrument,Agilent 1260 Infinity II
+Injection Volume,5,µL
+Column,Zorbax Eclipse Plus C18
+Acquired,2024-07-25T14:00:00
+
+[Signal] DAD1 A
+Time (min),Signal (mAU)
+0.0,0.1
+0.5,2.5
+";
+    let result = convert(InputType::Agilent, csv_data, &output_format);
+    let expected_ttl = r#"
+        PREFIX cat: <http://example.org/cat#>
+        PREFIX schema: <https://schema.org/>
+        PREFIX unit: <https://qudt.org/vocab/unit/>
+        PREFIX allores: <http://purl.allotrope.org/ontologies/result#>
+        PREFIX allohdf: <http://purl.allotrope.org/ontologies/hdf5/1.8#>
+        PREFIX qudt: <http://qudt.org/schema/qudt/>
+        PREFIX xsd: <http://www.w3.org/2001/XMLSchema#>
+
+        [] a cat:ChromatographyRun;
+        cat:chromatographyColumn "Zorbax Eclipse Plus C18";
+        cat:hasBatch [ a cat:Batch;
+            schema:name "23"];
+        cat:hasChromatogram [ a cat:Chromatogram;
+            cat:signalName "DAD1 A";
+            cat:hasDataset [ a allohdf:Dataset;
+                cat:arrayPath "chromatograms/0/time";
+                cat:chunkShape (2);
+                cat:dataType "float64";
+                cat:shape (2)],
+              [ a allohdf:Dataset;
+                cat:arrayPath "chromatograms/0/intensity";
+                cat:chunkShape (2);
+                cat:dataType "float64";
+                cat:shape (2)]];
+        cat:injectionVolume [ a cat:Observation;
+            qudt:unit unit:MicroL;
+            qudt:value "5"^^xsd:double];
+        allores:AFR_0001606 "HPLC_gradient.M";
+        allores:AFR_0001723 "Agilent 1260 Infinity II";
+        allores:AFX_0000622 "2024-07-25T14:00:00"^^xsd:dateTime.
+    "#;
+    let expected_graph = parse_turtle_to_graph(expected_ttl).unwrap();
+    let result_ttl = result.as_ref().unwrap().as_str();
+    let result_graph = parse_turtle_to_graph(result_ttl).unwrap();
+    let graphs_match = isomorphic_graphs(&result_graph, &expected_graph);
+    assert!(graphs_match.unwrap());
+
+    let dir = tempfile::tempdir().unwrap();
+    let zarr_path = dir.path().join("run.zarr");
+    convert_with(
+        InputType::Agilent,
+        csv_data,
+        &output_format,
+        ConvertOptions { zarr_path: Some(&zarr_path), ..Default::default() },
+    )
+    .unwrap();
+    assert!(zarr_path.join("chromatograms/0/intensity/zarr.json").is_file());
+    assert!(zarr_path.join("chromatograms/0/intensity/c/0").is_file());
+}
+
+#[test]
+fn test_convert_jcamp_spectrum() {
+    let output_format = RdfFormat::Turtle;
+    let jcamp_data = "\
+##TITLE= Ethanol
+##JCAMP-DX= 5.01
+##DATA TYPE= UV/VIS SPECTRUM
+##SPECTROMETER/DATA SYSTEM= Agilent Cary 60
+##LONG DATE= 2024/07/25 15:30:00
+##XUNITS= NANOMETERS
+##YUNITS= ABSORBANCE
+##YFACTOR= 0.001
+##FIRSTX= 200
+##LASTX= 203
+##NPOINTS= 4
+##XYDATA= (X++(Y..Y))
+200 A00J00J00%
+##END=
+";
+    let result = convert(InputType::Jcamp, jcamp_data, &output_format);
+    let expected_ttl = r#"
+        PREFIX cat: <http://example.org/cat#>
+        PREFIX schema: <https://schema.org/>
+        PREFIX unit: <https://qudt.org/vocab/unit/>
+        PREFIX allores: <http://purl.allotrope.org/ontologies/result#>
+        PREFIX allohdf: <http://purl.allotrope.org/ontologies/hdf5/1.8#>
+        PREFIX xsd: <http://www.w3.org/2001/XMLSchema#>
+
+        [] a cat:UVVisSpectrum;
+        schema:name "Ethanol";
+        allores:AFR_0001723 "Agilent Cary 60";
+        allores:AFX_0000622 "2024-07-25T15:30:00"^^xsd:dateTime;
+        cat:xAxisLabel "NANOMETERS";
+        cat:yAxisLabel "ABSORBANCE";
+        cat:xUnit unit:NanoM;
+        cat:yUnit unit:UNITLESS;
+        cat:hasDataset [ a allohdf:Dataset;
+                cat:arrayPath "spectrum/x";
+                cat:chunkShape (4);
+                cat:dataType "float64";
+                cat:shape (4)],
+              [ a allohdf:Dataset;
+                cat:arrayPath "spectrum/y";
+                cat:chunkShape (4);
+                cat:dataType "float64";
+                cat:shape (4)].
+    "#;
+    let expected_graph = parse_turtle_to_graph(expected_ttl).unwrap();
+    let result_ttl = result.as_ref().unwrap().as_str();
+    let result_graph = parse_turtle_to_graph(result_ttl).unwrap();
+    let graphs_match = isomorphic_graphs(&result_graph, &expected_graph);
+    assert!(graphs_match.unwrap());
+
+    let dir = tempfile::tempdir().unwrap();
+    let zarr_path = dir.path().join("spectrum.zarr");
+    convert_with(
+        InputType::Jcamp,
+        jcamp_data,
+        &output_format,
+        ConvertOptions { zarr_path: Some(&zarr_path), ..Default::default() },
+    )
+    .unwrap();
+    assert!(zarr_path.join("spectrum/y/zarr.json").is_file());
+    assert!(zarr_path.join("spectrum/y/c/0").is_file());
+}
+
+#[test]
+fn test_convert_animl_document() {
+    let output_format = RdfFormat::Turtle;
+    let animl_data = r#"<?xml version="1.0" encoding="UTF-8"?>
+<AnIML xmlns="urn:org:astm:animl:schema:core:draft:0.90" version="0.90">
+  <SampleSet>
+    <Sample name="Crude product" sampleID="24" containerID="18" barcode="18"/>
+  </SampleSet>
+  <ExperimentStepSet>
+    <ExperimentStep name="UV-Vis absorbance" experimentStepID="E1">
+      <TagSet><Tag name="batchID" value="23"/></TagSet>
+      <Technique name="UV/Vis" uri="https://www.animl.org/technique/uv-vis.atdd"/>
+      <Infrastructure>
+        <SampleReferenceSet><SampleReference sampleID="24" role="analyte"/></SampleReferenceSet>
+        <Timestamp>2024-07-25T15:30:00</Timestamp>
+      </Infrastructure>
+      <Result name="Spectrum">
+        <SeriesSet name="Spectrum" length="2">
+          <Series name="Absorbance" seriesID="absorbance" dependency="dependent" seriesType="Float64">
+            <IndividualValueSet><D>1.254</D><D>0.872</D></IndividualValueSet>
+          </Series>
+        </SeriesSet>
+        <Category name="Conditions">
+          <Parameter name="Temperature" parameterType="Float64"><D>25</D><Unit label="°C"/></Parameter>
+        </Category>
+      </Result>
+    </ExperimentStep>
+  </ExperimentStepSet>
+</AnIML>"#;
+    let result = convert(InputType::Animl, animl_data, &output_format);
+    let expected_ttl = r#"
+        PREFIX cat: <http://example.org/cat#>
+        PREFIX schema: <https://schema.org/>
+        PREFIX unit: <https://qudt.org/vocab/unit/>
+        PREFIX allores: <http://purl.allotrope.org/ontologies/result#>
+        PREFIX allohdf: <http://purl.allotrope.org/ontologies/hdf5/1.8#>
+        PREFIX qudt: <http://qudt.org/schema/qudt/>
+        PREFIX xsd: <http://www.w3.org/2001/XMLSchema#>
+
+        [] a cat:MeasureAction;
+        cat:hasBatch [ a cat:Batch;
+            schema:name "23"];
+        cat:hasDataset [ a allohdf:Dataset;
+            cat:arrayPath "experiment_steps/0/series/0";
+            cat:chunkShape (2);
+            cat:dataType "float64";
+            cat:shape (2)];
+        cat:hasParameter [ a cat:Observation;
+            qudt:unit unit:DEG-C;
+            qudt:value "25"^^xsd:double;
+            schema:name "Temperature"];
+        cat:hasSample [ a cat:Sample;
+            cat:containerBarcode "18";
+            cat:containerID "18";
+            cat:role "analyte";
+            allores:AFR_0002464 "24"];
+        allores:AFR_0001606 "UV/Vis";
+        allores:AFX_0000622 "2024-07-25T15:30:00"^^xsd:dateTime.
+    "#;
+    let expected_graph = parse_turtle_to_graph(expected_ttl).unwrap();
+    let result_ttl = result.as_ref().unwrap().as_str();
+    let result_graph = parse_turtle_to_graph(result_ttl).unwrap();
+    let graphs_match = isomorphic_graphs(&result_graph, &expected_graph);
+    assert!(graphs_match.unwrap());
+}
+
+#[test]
+fn test_convert_asm_document() {
+    let output_format = RdfFormat::Turtle;
+    let asm_data = r#"{
+        "$asm.manifest": "http://purl.allotrope.org/manifests/balance/BENCHLING/2024/06/balance.manifest",
+        "balance aggregate document": {
+            "device system document": {"model number": "XPR205"},
+            "balance document": [{
+                "measurement aggregate document": {
+                    "measurement document": [{
+                        "measurement time": "2024-07-25T12:03:41",
+                        "sample document": {"sample identifier": "24", "batch identifier": "23"},
+                        "weight": {"value": 12.5, "unit": "mg"}
+                    }]
+                }
+            }]
+        }
+    }"#;
+    let result = convert(InputType::Asm, asm_data, &output_format);
+    let expected_ttl = r#"
+        PREFIX cat: <http://example.org/cat#>
+        PREFIX schema: <https://schema.org/>
+        PREFIX unit: <https://qudt.org/vocab/unit/>
+        PREFIX allores: <http://purl.allotrope.org/ontologies/result#>
+        PREFIX qudt: <http://qudt.org/schema/qudt/>
+        PREFIX xsd: <http://www.w3.org/2001/XMLSchema#>
+
+        [] a cat:MeasureAction;
+        cat:hasBatch [ a cat:Batch;
+            schema:name "23"];
+        cat:hasParameter [ a cat:Observation;
+            qudt:unit unit:MilliGM;
+            qudt:value "12.5"^^xsd:double;
+            schema:name "weight"];
+        cat:hasSample [ a cat:Sample;
+            allores:AFR_0002464 "24"];
+        allores:AFR_0001606 "balance";
+        allores:AFR_0001723 "XPR205";
+        allores:AFX_0000622 "2024-07-25T12:03:41"^^xsd:dateTime.
+    "#;
+    let expected_graph = parse_turtle_to_graph(expected_ttl).unwrap();
+    let result_ttl = result.as_ref().unwrap().as_str();
+    let result_graph = parse_turtle_to_graph(result_ttl).unwrap();
+    let graphs_match = isomorphic_graphs(&result_graph, &expected_graph);
+    assert!(graphs_match.unwrap());
+}
+
+#[test]
+fn test_convert_mzml_run() {
+    let output_format = RdfFormat::Turtle;
+    let mzml_data = r#"<?xml version="1.0" encoding="utf-8"?>
+<mzML xmlns="http://psi.hupo.org/ms/mzml" version="1.1.0">
+  <sampleList count="1">
+    <sample id="S1" name="Vial 1"><userParam name="batchID" value="23"/></sample>
+  </sampleList>
+  <instrumentConfigurationList count="1">
+    <instrumentConfiguration id="IC1">
+      <cvParam cvRef="MS" accession="MS:1000554" name="LCQ Deca" value=""/>
+    </instrumentConfiguration>
+  </instrumentConfigurationList>
+  <run id="run01" defaultInstrumentConfigurationRef="IC1" startTimeStamp="2024-07-25T14:00:00">
+    <spectrumList count="1">
+      <spectrum index="0" id="scan=1" defaultArrayLength="2">
+        <cvParam cvRef="MS" accession="MS:1000511" name="ms level" value="1"/>
+        <cvParam cvRef="MS" accession="MS:1000129" name="negative scan" value=""/>
+        <scanList count="1"><scan>
+          <cvParam cvRef="MS" accession="MS:1000016" name="scan start time" value="12.5" unitCvRef="UO" unitAccession="UO:0000010" unitName="second"/>
+        </scan></scanList>
+        <binaryDataArrayList count="2">
+          <binaryDataArray>
+            <cvParam cvRef="MS" accession="MS:1000523" name="64-bit float" value=""/>
+            <cvParam cvRef="MS" accession="MS:1000574" name="zlib compression" value=""/>
+            <cvParam cvRef="MS" accession="MS:1000514" name="m/z array" value=""/>
+            <binary>eJxjYAAChUgHEMXAkekAAAhUAWs=</binary>
+          </binaryDataArray>
+          <binaryDataArray>
+            <cvParam cvRef="MS" accession="MS:1000521" name="32-bit float" value=""/>
+            <cvParam cvRef="MS" accession="MS:1000576" name="no compression" value=""/>
+            <cvParam cvRef="MS" accession="MS:1000515" name="intensity array" value=""/>
+            <binary>AAAgQQAAoEE=</binary>
+          </binaryDataArray>
+        </binaryDataArrayList>
+      </spectrum>
+    </spectrumList>
+  </run>
+</mzML>"#;
+    let result = convert(InputType::Mzml, mzml_data, &output_format);
+    let expected_ttl = r#"
+        PREFIX cat: <http://example.org/cat#>
+        PREFIX schema: <https://schema.org/>
+        PREFIX qudt: <http://qudt.org/schema/qudt/>
+        PREFIX unit: <https://qudt.org/vocab/unit/>
+        PREFIX allores: <http://purl.allotrope.org/ontologies/result#>
+        PREFIX allohdf: <http://purl.allotrope.org/ontologies/hdf5/1.8#>
+        PREFIX purl: <http://purl.allotrope.org/ontologies/>
+        PREFIX xsd: <http://www.w3.org/2001/XMLSchema#>
+
+        [] a cat:MassSpectrometryRun;
+        purl:identifier "run01";
+        cat:sampleName "Vial 1";
+        allores:AFR_0001723 "LCQ Deca";
+        allores:AFX_0000622 "2024-07-25T14:00:00"^^xsd:dateTime;
+        cat:hasBatch [ a cat:Batch;
+            schema:name "23"];
+        cat:hasMassSpectrum [ a cat:MassSpectrum;
+            purl:identifier "scan=1";
+            cat:scanIndex 0;
+            cat:msLevel 1;
+            cat:polarity "negative";
+            cat:scanStartTime [ a cat:Observation;
+                qudt:unit unit:SEC;
+                qudt:value "12.5"^^xsd:double]];
+        cat:hasDataset [ a allohdf:Dataset;
+                cat:arrayPath "mass_spectra/mz";
+                cat:chunkShape (2);
+                cat:dataType "float64";
+                cat:shape (2)],
+              [ a allohdf:Dataset;
+                cat:arrayPath "mass_spectra/intensity";
+                cat:chunkShape (2);
+                cat:dataType "float64";
+                cat:shape (2)],
+              [ a allohdf:Dataset;
+                cat:arrayPath "mass_spectra/offsets";
+                cat:chunkShape (2);
+                cat:dataType "int64";
+                cat:shape (2)].
+    "#;
+    let expected_graph = parse_turtle_to_graph(expected_ttl).unwrap();
+    let result_ttl = result.as_ref().unwrap().as_str();
+    let result_graph = parse_turtle_to_graph(result_ttl).unwrap();
+    let graphs_match = isomorphic_graphs(&result_graph, &expected_graph);
+    assert!(graphs_match.unwrap());
+
+    let dir = tempfile::tempdir().unwrap();
+    let zarr_path = dir.path().join("run.zarr");
+    convert_with(
+        InputType::Mzml,
+        mzml_data,
+        &output_format,
+        ConvertOptions { zarr_path: Some(&zarr_path), ..Default::default() },
+    )
+    .unwrap();
+    assert!(zarr_path.join("mass_spectra/mz/c/0").is_file());
+    assert!(zarr_path.join("mass_spectra/offsets/zarr.json").is_file());
+}
+
+#[test]
+fn test_convert_with_shacl_validation() {
+    let shapes = Shapes::from_turtle(
+        r#"
+        PREFIX sh: <http://www.w3.org/ns/shacl#>
+        PREFIX cat: <http://example.org/cat#>
+        PREFIX schema: <https://schema.org/>
+
+        cat:BatchShape a sh:NodeShape;
+            sh:targetClass cat:Batch;
+            sh:property [
+                sh:path schema:name;
+                sh:pattern "^[0-9]+$";
+                sh:minCount 1
+            ].
+    "#,
+    )
+    .unwrap();
+    let options = ConvertOptions { shapes: Some(&shapes), ..Default::default() };
+
+    let json_data = r#"{"batchID": "23", "Actions": []}"#;
+    let result = convert_with(InputType::Synth, json_data, &RdfFormat::Turtle, options);
+    assert!(result.is_ok());
+
+    let json_data = r#"{"batchID": "B23", "Actions": []}"#;
+    let result = convert_with(InputType::Synth, json_data, &RdfFormat::Turtle, options);
+    let error = result.unwrap_err();
+    assert_eq!(ErrorKind::of(&error), Some(ErrorKind::Validation));
+    let error = format!("{:#}", error);
+    assert!(error.contains("does not conform"));
+    assert!(error.contains("sh:PatternConstraintComponent"));
+    assert!(error.contains("\"B23\""));
+
+    // The Zarr store of an invalid graph is not written.
+    let dir = tempfile::tempdir().unwrap();
+    let zarr_path = dir.path().join("run.zarr");
+    let options = ConvertOptions { zarr_path: Some(&zarr_path), ..options };
+    let agilent = |batch_id: &str| {
+        let content = format!("Batch ID,{}\n[Peaks] DAD1 A\nPeak,RetTime (min)\n1,0.5\n", batch_id);
+        convert_input(InputType::Agilent, &content, &RdfFormat::Turtle, options)
+    };
+    assert!(agilent("B23").is_err());
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    assert!(agilent("23").is_ok());
+    assert!(zarr_path.join("zarr.json").is_file());
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+/// Converts `json_data` to RDF and back in every format, checking that the document is
+/// read back unchanged.
+fn assert_round_trip<T>(input_type: InputType, json_data: &str)
+where
+    T: DeserializeOwned + Serialize + InsertIntoGraph + FromGraph,
+{
+    // NOTE: the input itself is compared, so that fields unknown to the models are lost too
+    let expected: serde_json::Value = serde_json::from_str(json_data).unwrap();
+    for format in [RdfFormat::Turtle, RdfFormat::Jsonld] {
+        let rdf = convert(input_type, json_data, &format).unwrap();
+        let json = rdf_to_json::<T>(&rdf, &format, &CatNamespace::default()).unwrap();
+        let result: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(result, expected, "round trip through {:?}", format);
+    }
+}
+
+#[test]
+fn test_rdf_to_json_round_trip_examples() {
+    assert_round_trip::<Batch>(InputType::Synth, include_str!("../../../examples/1-Synth.json"));
+    assert_round_trip::<CampaignWrapper>(
+        InputType::HCI,
+        include_str!("../../../examples/0-HCI.json"),
+    );
+}
+
+#[test]
+fn test_rdf_to_json_round_trip_batch() {
+    let json_data = r#"
+        {
+            "batchID": "23",
+            "batchName": "20240516",
+            "reactionType": "N-methylation",
+            "Actions": [
+                {
+                    "actionName": "AddAction",
+                    "startTime": "2024-07-25T12:00:13",
+                    "endingTime": "2024-07-25T12:00:17",
+                    "methodName": "addition",
+                    "equipmentName": "Chemspeed SWING XL",
+                    "subEquipmentName": "GDU-V",
+                    "speedShaker": {"value": 152, "unit": "rpm", "errorMargin": {"value": 1, "unit": "rpm"}},
+                    "hasContainerPositionAndQuantity": [
+                        {"containerID": "1", "position": "A1", "quantity": {"value": 1, "unit": "mg"}},
+                        {"containerID": "1", "position": "B1", "quantity": {"value": 4, "unit": "mg"}}
+                    ],
+                    "dispenseState": "Liquid",
+                    "dispenseType": "volume",
+                    "hasSample": {
+                        "containerID": "17",
+                        "containerBarcode": "17",
+                        "vialID": "15",
+                        "vialType": "storage vial",
+                        "role": "solvent",
+                        "expectedDatum": {"value": 2, "unit": "mg"},
+                        "hasSample": [
+                            {
+                                "sampleID": "123",
+                                "role": "solvent",
+                                "internalBarCode": "1",
+                                "measuredQuantity": {"value": 1, "unit": "mg", "errorMargin": {"value": 0.01, "unit": "mg"}},
+                                "concentration": {"value": 0.5, "unit": "mol/L"},
+                                "physicalState": "Liquid",
+                                "hasChemical": {
+                                    "chemicalID": "134",
+                                    "chemicalName": "Toluene",
+                                    "CASNumber": "108-88-3",
+                                    "molecularMass": {"value": 92.14, "unit": "g/mol"},
+                                    "smiles": "CC1=CC=CC=C1",
+                                    "swissCatNumber": "SwissCAT-1140",
+                                    "Inchi": "1S/C7H8/c1-7-5-3-2-4-6-7/h2-6H,1H3",
+                                    "keywords": "solvent",
+                                    "molecularFormula": "C7H8",
+                                    "density": {"value": 0.8623, "unit": "g/mL"}
+                                }
+                            },
+                            {
+                                "sampleID": "124",
+                                "role": "reagent",
+                                "internalBarCode": "2",
+                                "expectedDatum": {"value": 20, "unit": "mg"},
+                                "physicalState": "Solid",
+                                "hasChemical": {
+                                    "chemicalID": "135",
+                                    "chemicalName": "Styrene",
+                                    "molecularMass": {"value": 104.15, "unit": "g/mol"},
+                                    "smiles": "C=CC1=CC=CC=C1",
+                                    "Inchi": "1S/C8H8/c1-2-8-6-4-3-5-7-8/h2-7H,1H2",
+                                    "molecularFormula": "C8H8"
+                                }
+                            }
+                        ]
+                    }
+                },
+                {
+                    "actionName": "setPressureAction",
+                    "startTime": "2024-07-25T12:03:50",
+                    "endingTime": "2024-07-25T12:03:51",
+                    "methodName": "set_pressure",
+                    "equipmentName": "Chemspeed SWING XL",
+                    "subEquipmentName": "pressure controller",
+                    "containerID": "1",
+                    "containerBarcode": "1",
+                    "pressureMeasurement": {"value": 5, "unit": "bar", "errorMargin": {"value": 1, "unit": "bar"}}
+                },
+                {
+                    "actionName": "shakeAction",
+                    "startTime": "2024-07-25T12:03:51",
+                    "endingTime": "2024-07-25T12:15:20",
+                    "methodName": "shake",
+                    "equipmentName": "Chemspeed SWING XL",
+                    "subEquipmentName": "tumble stirrer",
+                    "speedTumbleStirrer": {"value": 600, "unit": "rpm"},
+                    "temperatureTumbleStirrer": {"value": 25, "unit": "°C"},
+                    "temperatureShaker": {"value": 25, "unit": "°C"}
+                }
+            ]
+        }
+    "#;
+    assert_round_trip::<Batch>(InputType::Synth, json_data);
+}
+
+#[test]
+fn test_rdf_to_json_round_trip_campaign() {
+    let json_data = r#"
+        {
+            "hasCampaign": {
+                "campaignName": "Caffeine Synthesis",
+                "description": "1-step N-methylation of theobromine to caffeine",
+                "objective": "High caffeine yield at the end",
+                "campaignClass": "Standard Research",
+                "type": "optimization",
+                "reference": "Substitution reaction - SN2",
+                "hasBatch": {
+                    "batchID": "23",
+                    "batchName": "20240516",
+                    "reactionType": "N-methylation",
+                    "reactionName": "Caffeine synthesis",
+                    "optimizationType": "Yield optimization",
+                    "link": "https://www.sciencedirect.com/science/article/pii/S0187893X15720926"
+                },
+                "hasObjective": {
+                    "criteria": "Yield ≥ 90%",
+                    "condition": "Reflux in acetone with methyl iodide and potassium carbonate",
+                    "description": "Optimize reaction conditions to maximize caffeine yield",
+                    "objectiveName": "Maximize caffeine formation"
+                },
+                "hasChemical": [
+                    {
+                        "chemicalID": "1",
+                        "chemicalName": "Theobromine",
+                        "CASNumber": "83-67-0",
+                        "molecularMass": {"value": 180.16, "unit": "g/mol"},
+                        "smiles": "CN1C=NC2=C1C(=O)NC(=O)N2C",
+                        "Inchi": "1S/C7H8N4O2/c1-10-3-8-5-4(10)6(12)9-7(13)11(5)2/h3H,1-2H3,(H,9,12,13)",
+                        "molecularFormula": "C7H8N4O2"
+                    },
+                    {
+                        "chemicalID": "2",
+                        "chemicalName": "Methyl iodide",
+                        "CASNumber": "74-88-4",
+                        "molecularMass": {"value": 141.94, "unit": "g/mol"},
+                        "smiles": "CI",
+                        "Inchi": "1S/CH3I/c1-2/h1H3",
+                        "molecularFormula": "CH3I",
+                        "density": {"value": 2.28, "unit": "g/mL"}
+                    }
+                ]
+            }
+        }
+    "#;
+    assert_round_trip::<CampaignWrapper>(InputType::HCI, json_data);
+}
+
+#[test]
+fn test_convert_with_cat_namespace() {
+    let json_data = r#"
+        {
+            "batchID": "23",
+            "batchName": "20240516",
+            "Actions": [
+                {
+                    "actionName": "setTemperatureAction",
+                    "startTime": "2024-07-25T12:03:31",
+                    "endingTime": "2024-07-25T12:03:50",
+                    "methodName": "set_temperature",
+                    "equipmentName": "Chemspeed SWING XL",
+                    "subEquipmentName": "heater",
+                    "temperatureShaker": {
+                        "value": 25,
+                        "unit": "°C"
+                    }
+                }
+            ]
+        }
+    "#;
+    let legacy = convert(InputType::Synth, json_data, &RdfFormat::Turtle).unwrap();
+
+    let namespace = CatNamespace::new("https://purl.org/catplus/ontology#").unwrap();
+    let graph = GraphConfig { cat_namespace: namespace.clone(), ..Default::default() };
+    let options = ConvertOptions { graph: Some(&graph), ..Default::default() };
+    let result =
+        convert_input(InputType::Synth, json_data, &RdfFormat::Turtle, options).unwrap().output;
+    let migrated = migrate_rdf(&legacy, &RdfFormat::Turtle, &namespace);
+    let json = rdf_to_json::<Batch>(&result, &RdfFormat::Turtle, &namespace);
+
+    assert!(result.contains("PREFIX cat: <https://purl.org/catplus/ontology#>"));
+    assert!(!result.contains("example.org/cat#"));
+    let migrated = parse_turtle_to_graph(&migrated.unwrap()).unwrap();
+    assert!(isomorphic_graphs(&migrated, &parse_turtle_to_graph(&result).unwrap()).unwrap());
+
+    let expected = serde_json::to_value(serde_json::from_str::<Batch>(json_data).unwrap()).unwrap();
+    let json: Batch = serde_json::from_str(&json.unwrap()).unwrap();
+    assert_eq!(serde_json::to_value(json).unwrap(), expected);
+}
+
+#[test]
+fn test_convert_to_line_based_formats() {
+    let json_data = r#"
+        {
+            "batchID": "23",
+            "Actions": [
+                {
+                    "actionName": "setTemperatureAction",
+                    "startTime": "2024-07-25T12:03:31",
+                    "endingTime": "2024-07-25T12:03:50",
+                    "methodName": "set_temperature",
+                    "equipmentName": "Chemspeed SWING XL",
+                    "subEquipmentName": "heater",
+                    "temperatureShaker": {
+                        "value": 25,
+                        "unit": "°C"
+                    }
+                }
+            ]
+        }
+    "#;
+    let expected =
+        parse_turtle_to_graph(&convert(InputType::Synth, json_data, &RdfFormat::Turtle).unwrap())
+            .unwrap();
+    for format in [RdfFormat::Ntriples, RdfFormat::Nquads, RdfFormat::Trig] {
+        let result = convert(InputType::Synth, json_data, &format).unwrap();
+        let graph = match format {
+            RdfFormat::Ntriples => parse_ntriples_to_graph(&result),
+            RdfFormat::Nquads => parse_nquads_to_graph(&result),
+            _ => parse_trig_to_graph(&result),
+        }
+        .unwrap();
+        assert!(isomorphic_graphs(&graph, &expected).unwrap(), "{:?}", format);
+    }
+
+    let rdfxml = convert(InputType::Synth, json_data, &RdfFormat::Rdfxml).unwrap();
+    assert!(rdfxml.contains("<schema:name>23</schema:name>"));
+    assert_eq!(rdfxml.matches("<rdf:Description ").count(), 3);
+}
+
+#[test]
+fn test_convert_to_canonical_form() {
+    let json_data = include_str!("../../../examples/1-Synth.json");
+    let options = ConvertOptions { canonical: true, ..Default::default() };
+    for format in [RdfFormat::Turtle, RdfFormat::Ntriples] {
+        let first = convert_with(InputType::Synth, json_data, &format, options).unwrap();
+        let second = convert_with(InputType::Synth, json_data, &format, options).unwrap();
+        assert_eq!(first, second, "{:?}", format);
+    }
+
+    let canonical = convert_with(InputType::Synth, json_data, &RdfFormat::Turtle, options).unwrap();
+    let expected = convert(InputType::Synth, json_data, &RdfFormat::Turtle).unwrap();
+    assert_ne!(canonical, expected);
+    assert!(isomorphic_graphs(
+        &parse_turtle_to_graph(&canonical).unwrap(),
+        &parse_turtle_to_graph(&expected).unwrap()
+    )
+    .unwrap());
+}
+
+#[test]
+fn test_diff_conversions() {
+    let json_data = r#"{"batchID": "23", "Actions": [
+        {
+            "actionName": "setTemperatureAction",
+            "startTime": "2024-07-25T12:03:31",
+            "endingTime": "2024-07-25T12:03:50",
+            "methodName": "set_temperature",
+            "equipmentName": "Chemspeed SWING XL",
+            "subEquipmentName": "heater",
+            "temperatureShaker": {"value": 25, "unit": "°C"}
+        }
+    ]}"#;
+    let old = convert(InputType::Synth, json_data, &RdfFormat::Turtle).unwrap();
+    let same = convert(InputType::Synth, json_data, &RdfFormat::Ntriples).unwrap();
+    let new = convert(
+        InputType::Synth,
+        &json_data.replace("\"value\": 25", "\"value\": 30"),
+        &RdfFormat::Turtle,
+    )
+    .unwrap();
+
+    let diff = diff_rdf(&old, &RdfFormat::Turtle, &same, &RdfFormat::Ntriples).unwrap();
+    assert!(diff.is_empty());
+
+    let diff = diff_rdf(&old, &RdfFormat::Turtle, &new, &RdfFormat::Turtle).unwrap();
+    let report = diff.to_string();
+    assert_eq!(report.lines().count(), 2, "{}", report);
+    assert!(report.contains("- _:"));
+    assert!(report.contains("\"25\"^^<http://www.w3.org/2001/XMLSchema#double> ."));
+    assert!(report.contains("\"30\"^^<http://www.w3.org/2001/XMLSchema#double> ."));
+    assert_eq!(diff.to_json()["removed"].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn test_merge_campaign_and_synth() {
+    let hci_data = include_str!("../../../examples/0-HCI.json");
+    let synth_data = include_str!("../../../examples/1-Synth.json");
+    let conversion = merge_to_rdf_with_options(
+        hci_data,
+        &[("1-Synth.json", synth_data)],
+        &RdfFormat::Turtle,
+        ConvertOptions::default(),
+    )
+    .unwrap();
+    let batch_warnings = |warnings: &[String]| {
+        warnings.iter().filter(|w| w.starts_with("conflicting batch")).cloned().collect::<Vec<_>>()
+    };
+    assert!(batch_warnings(&conversion.warnings).is_empty());
+    let turtle = conversion.output;
+
+    let graph = parse_turtle_to_graph(&turtle).unwrap();
+    let cat = |name: &str| format!("http://example.org/cat#{}", name);
+    let batches: Vec<_> = graph
+        .triples_matching(Any, [rdf::type_], [IriRef::new_unchecked(cat("Batch"))])
+        .map(|t| t.unwrap().s().clone())
+        .collect();
+    assert_eq!(batches.len(), 1);
+    let has_batch = IriRef::new_unchecked(cat("hasBatch"));
+    let linked: Vec<_> =
+        graph.triples_matching(Any, [&has_batch], Any).map(|t| t.unwrap().o().clone()).collect();
+    assert!(linked.len() > 1);
+    assert!(linked.iter().all(|batch| *batch == batches[0]));
+
+    let campaign =
+        rdf_to_json::<CampaignWrapper>(&turtle, &RdfFormat::Turtle, &CatNamespace::default())
+            .unwrap();
+    let campaign: CampaignWrapper = serde_json::from_str(&campaign).unwrap();
+    let synth: Batch = serde_json::from_str(synth_data).unwrap();
+    let actions = campaign.has_campaign.has_batch.actions.unwrap();
+    assert_eq!(actions.len(), synth.actions.unwrap().len());
+
+    let other = synth_data.replacen("\"23\"", "\"24\"", 1);
+    let result = merge_to_rdf_with_options(
+        hci_data,
+        &[("other.json", &other)],
+        &RdfFormat::Turtle,
+        ConvertOptions::default(),
+    );
+    let error = format!("{:#}", result.unwrap_err());
+    assert!(error.contains("Cannot merge batch '24' into batch '23'"), "{}", error);
+
+    let conflicting = synth_data.replacen("{", r#"{"reactionType": "Suzuki coupling","#, 1);
+    let conversion = merge_to_rdf_with_options(
+        hci_data,
+        &[("conflicting.json", &conflicting)],
+        &RdfFormat::Turtle,
+        ConvertOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        batch_warnings(&conversion.warnings),
+        ["conflicting batch '23' in 'conflicting.json': reactionType is 'N-methylation', \
+          ignoring 'Suzuki coupling'"]
+    );
+}
+
+#[test]
+fn test_convert_directory() {
+    let input_dir = tempfile::tempdir().unwrap();
+    let output_dir = tempfile::tempdir().unwrap();
+    let synth_data = include_str!("../../../examples/1-Synth.json");
+    std::fs::create_dir_all(input_dir.path().join("2024/05")).unwrap();
+    std::fs::create_dir_all(input_dir.path().join("old")).unwrap();
+    std::fs::write(input_dir.path().join("a.json"), synth_data).unwrap();
+    std::fs::write(input_dir.path().join("2024/05/b.json"), synth_data).unwrap();
+    std::fs::write(input_dir.path().join("2024/05/bad.json"), r#"{"batchID": 1}"#).unwrap();
+    std::fs::write(input_dir.path().join("old/c.json"), synth_data).unwrap();
+    std::fs::write(input_dir.path().join("notes.txt"), "not a batch").unwrap();
+
+    let options = BatchOptions {
+        input_type: InputType::Synth,
+        format: RdfFormat::Turtle,
+        include: vec!["*.json".to_string()],
+        exclude: vec!["old/**".to_string()],
+        jobs: 2,
+        zarr: false,
+        canonical: true,
+        graph: GraphConfig::default(),
+    };
+    let manifest = convert_directory(input_dir.path(), output_dir.path(), &options).unwrap();
+
+    assert_eq!((manifest.converted, manifest.failed), (2, 1));
+    let inputs: Vec<&str> = manifest.files.iter().map(|f| f.input.as_str()).collect();
+    assert_eq!(inputs, ["2024/05/b.json", "2024/05/bad.json", "a.json"]);
+    let [b, bad, a] = &manifest.files[..] else { unreachable!() };
+    assert_eq!(a.status, FileStatus::Converted);
+    assert_eq!(a.output, "a.ttl");
+    assert!(a.triples.unwrap() > 0);
+    assert_eq!(a.output_sha256, b.output_sha256);
+    let turtle = std::fs::read_to_string(output_dir.path().join("2024/05/b.ttl")).unwrap();
+    assert!(isomorphic_graphs(
+        &parse_turtle_to_graph(&turtle).unwrap(),
+        &parse_turtle_to_graph(&convert(InputType::Synth, synth_data, &RdfFormat::Turtle).unwrap())
+            .unwrap()
+    )
+    .unwrap());
+    assert_eq!(bad.status, FileStatus::Failed);
+    assert!(bad.error.as_ref().unwrap().contains("Failed to parse JSON input"));
+    assert!(bad.input_sha256.is_some() && bad.triples.is_none());
+
+    assert!(glob_match("*.json", "2024/05/b.json"));
+    assert!(glob_match("2024/**/*.json", "2024/05/b.json"));
+    assert!(glob_match("**/b.json", "b.json"));
+    assert!(!glob_match("2024/*.json", "2024/05/b.json"));
+    assert!(!glob_match("?.json", "bad.json"));
+}
+
+#[test]
+fn test_convert_directory_collisions_and_links() {
+    let input_dir = tempfile::tempdir().unwrap();
+    let output_dir = tempfile::tempdir().unwrap();
+    let synth_data = include_str!("../../../examples/1-Synth.json");
+    std::fs::write(input_dir.path().join("a.json"), synth_data).unwrap();
+    std::fs::write(input_dir.path().join("a.synth"), synth_data).unwrap();
+    std::fs::write(input_dir.path().join("b.json"), synth_data).unwrap();
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(input_dir.path(), input_dir.path().join("loop")).unwrap();
+
+    let options = BatchOptions {
+        input_type: InputType::Synth,
+        format: RdfFormat::Turtle,
+        include: Vec::new(),
+        exclude: Vec::new(),
+        jobs: 2,
+        zarr: false,
+        canonical: false,
+        graph: GraphConfig::default(),
+    };
+    let manifest = convert_directory(input_dir.path(), output_dir.path(), &options).unwrap();
+
+    let inputs: Vec<&str> = manifest.files.iter().map(|f| f.input.as_str()).collect();
+    assert_eq!(inputs, ["a.json", "a.synth", "b.json"]);
+    assert_eq!((manifest.converted, manifest.failed), (1, 2));
+    let [a_json, a_synth, _] = &manifest.files[..] else { unreachable!() };
+    assert_eq!(a_json.status, FileStatus::Failed);
+    assert_eq!(
+        a_json.error.as_deref(),
+        Some("The output file 'a.ttl' would also be written by 'a.synth'")
+    );
+    assert_eq!(a_synth.status, FileStatus::Failed);
+    assert!(!output_dir.path().join("a.ttl").exists());
+}
+
+#[test]
+fn test_json_input_errors() {
+    let synth_data = include_str!("../../../examples/1-Synth.json");
+    assert!(parse_json::<Batch>(synth_data).is_ok());
+
+    let mut document: serde_json::Value = serde_json::from_str(synth_data).unwrap();
+    let actions = document["Actions"].as_array_mut().unwrap();
+    actions[1]["hasSample"].as_object_mut().unwrap().remove("role");
+    actions[3]["startTime"] = serde_json::json!(5);
+    actions[4]["temperatureShaker"] = serde_json::json!({"value": "fast", "unit": "°C"});
+    let content = serde_json::to_string_pretty(&document).unwrap();
+
+    let JsonErrors(errors) = parse_json::<Batch>(&content).unwrap_err();
+    let pointers: Vec<&str> = errors.iter().filter_map(|e| e.pointer.as_deref()).collect();
+    assert_eq!(
+        pointers,
+        ["/Actions/1/hasSample/role", "/Actions/3/startTime", "/Actions/4/temperatureShaker/value"]
+    );
+    let models: Vec<&str> = errors.iter().filter_map(|e| e.model.as_deref()).collect();
+    assert_eq!(models, ["Sample", "Action", "Observation"]);
+    assert_eq!(errors[0].message, "missing field `role`, expected a string");
+    assert_eq!(errors[1].message, "invalid type: integer `5`, expected a string");
+    let start_time = content.lines().position(|line| line.contains("\"startTime\": 5")).unwrap();
+    assert_eq!(errors[1].line, start_time + 1);
+    assert!(errors[1]
+        .to_string()
+        .contains(&format!("{} |       \"startTime\": 5,", errors[1].line)));
+
+    let JsonErrors(errors) =
+        parse_json::<Batch>("{\"batchID\": \"23\",\n\"Actions\": [}").unwrap_err();
+    assert_eq!((errors.len(), errors[0].pointer.as_ref(), errors[0].line), (1, None, 2));
+    assert_eq!(errors[0].message, "expected value");
+}
+
+#[test]
+fn test_detect_input_type() {
+    let examples = [
+        (include_str!("../../../examples/0-HCI.json"), InputType::HCI),
+        (include_str!("../../../examples/1-Synth.json"), InputType::Synth),
+        (include_str!("../../../examples/2-Agilent.csv"), InputType::Agilent),
+        (include_str!("../../../examples/3-IR.jdx"), InputType::Jcamp),
+        (include_str!("../../../examples/4-AnIML.animl"), InputType::Animl),
+        (include_str!("../../../examples/5-ASM-plate-reader.json"), InputType::Asm),
+        (include_str!("../../../examples/6-LC-MS.mzML"), InputType::Mzml),
+    ];
+    for (content, input_type) in examples {
+        assert_eq!(detect_input_type(content).unwrap(), input_type);
+    }
+    let indexed = "<?xml version=\"1.0\"?>\n<!-- run 23 -->\n<indexedmzML><mzML/></indexedmzML>";
+    assert_eq!(detect_input_type(indexed).unwrap(), InputType::Mzml);
+
+    let error = detect_input_type(r#"{"hasCampaign": {}, "batchID": "23"}"#).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "The input type is ambiguous, the input looks like hci (key 'hasCampaign') or synth \
+         (key 'batchID'). Pass the input type explicitly"
+    );
+    assert!(detect_input_type(r#"{"name": "23"}"#)
+        .unwrap_err()
+        .to_string()
+        .starts_with("Cannot detect the input type"));
+    assert!(detect_input_type("<html></html>").is_err());
+
+    let synth_data = include_str!("../../../examples/1-Synth.json");
+    let conversion =
+        convert_input(InputType::Auto, synth_data, &RdfFormat::Turtle, ConvertOptions::default())
+            .unwrap();
+    assert!(isomorphic_graphs(
+        &parse_turtle_to_graph(&conversion.output).unwrap(),
+        &parse_turtle_to_graph(&convert(InputType::Synth, synth_data, &RdfFormat::Turtle).unwrap())
+            .unwrap()
+    )
+    .unwrap());
+}
+
+#[test]
+fn test_error_kinds() {
+    let kind = |result: anyhow::Result<String>| ErrorKind::of(&result.unwrap_err());
+    let turtle = RdfFormat::Turtle;
+    let namespace = CatNamespace::default();
+
+    assert_eq!(
+        kind(convert(InputType::Synth, r#"{"batchID": 1}"#, &turtle)),
+        Some(ErrorKind::Parse)
+    );
+    assert_eq!(kind(convert(InputType::Agilent, "Batch ID,23\n", &turtle)), Some(ErrorKind::Parse));
+    assert_eq!(
+        kind(rdf_to_json::<Batch>("not turtle", &turtle, &namespace)),
+        Some(ErrorKind::Parse)
+    );
+    assert_eq!(
+        kind(rdf_to_json::<Batch>(
+            "<http://example.org/a> a <http://example.org/B>.",
+            &turtle,
+            &namespace
+        )),
+        Some(ErrorKind::Mapping)
+    );
+    let detection = convert_input(InputType::Auto, "{}", &turtle, ConvertOptions::default());
+    assert_eq!(ErrorKind::of(&detection.err().unwrap()), Some(ErrorKind::Parse));
+
+    let io_error = anyhow::Error::new(std::io::Error::other("disk full")).context("Failed");
+    assert_eq!(ErrorKind::of(&io_error), Some(ErrorKind::Io));
+    assert_eq!(ErrorKind::of(&anyhow::anyhow!("Failed")), None);
+}
+
+#[test]
+fn test_invalid_argument_exit_code() {
+    let output_dir = tempfile::tempdir().unwrap();
+    let output = output_dir.path().join("23.ttl");
+    let run = |args: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_converter"))
+            .args(["synth", "../../examples/1-Synth.json"])
+            .args(args)
+            .env_remove("CATPLUS_NAMESPACE")
+            .output()
+            .unwrap()
+            .status
+            .code()
+    };
+    let output = output.to_str().unwrap();
+
+    assert_eq!(run(&[output]), Some(0));
+    assert_eq!(run(&[output, "--base-iri", "http://example.org"]), Some(2));
+    assert_eq!(run(&[output, "--namespace", "not an iri"]), Some(2));
+    assert_eq!(run(&[output, "--frame", "frame.json"]), Some(2));
+    assert_eq!(run(&["-", "--zarr", "turtle"]), Some(2));
+    assert_eq!(run(&["23.unknown"]), Some(2));
+}
+
+#[test]
+fn test_batch_io_error_exit_code() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("23.json");
+    std::fs::write(&file, "{}").unwrap();
+    let run = |input_dir: &std::path::Path, output_dir: &std::path::Path| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_converter"))
+            .arg("batch")
+            .args([input_dir, output_dir])
+            .env_remove("CATPLUS_NAMESPACE")
+            .output()
+            .unwrap()
+            .status
+            .code()
+    };
+
+    // The output directory cannot be created under a file, nor a missing directory read
+    assert_eq!(run(dir.path(), &file.join("out")), Some(3));
+    assert_eq!(run(&dir.path().join("missing"), &dir.path().join("out")), Some(3));
+}
+
+#[test]
+fn test_rdf_format_from_extension() {
+    let format = |path: &str| RdfFormat::from_extension(std::path::Path::new(path));
+    assert!(matches!(format("out/batch.nt"), Some(RdfFormat::Ntriples)));
+    assert!(matches!(format("batch.NQ"), Some(RdfFormat::Nquads)));
+    assert!(matches!(format("batch.trig"), Some(RdfFormat::Trig)));
+    assert!(matches!(format("batch.rdf"), Some(RdfFormat::Rdfxml)));
+    assert!(matches!(format("batch.jsonld"), Some(RdfFormat::Jsonld)));
+    assert!(format("batch").is_none());
+    assert!(format("batch.csv").is_none());
+}
+
+#[test]
+fn test_convert_to_compact_jsonld() {
+    let json_data = r#"
+        {
+            "batchID": "23",
+            "Actions": [
+                {
+                    "actionName": "setTemperatureAction",
+                    "startTime": "2024-07-25T12:03:31",
+                    "endingTime": "2024-07-25T12:03:50",
+                    "methodName": "set_temperature",
+                    "equipmentName": "Chemspeed SWING XL",
+                    "subEquipmentName": "heater",
+                    "temperatureShaker": {
+                        "value": 25,
+                        "unit": "°C"
+                    }
+                },
+                {
+                    "actionName": "shakeAction",
+                    "startTime": "2024-07-25T12:04:31",
+                    "endingTime": "2024-07-25T12:05:31",
+                    "methodName": "shake",
+                    "equipmentName": "Chemspeed SWING XL",
+                    "subEquipmentName": "shaker"
+                }
+            ]
+        }
+    "#;
+    let expected =
+        parse_turtle_to_graph(&convert(InputType::Synth, json_data, &RdfFormat::Turtle).unwrap())
+            .unwrap();
+
+    let compacted = convert(InputType::Synth, json_data, &RdfFormat::CompactJsonld).unwrap();
+    let document: serde_json::Value = serde_json::from_str(&compacted).unwrap();
+    assert_eq!(document["@context"]["hasBatch"]["@type"], "@id");
+    assert!(document["@graph"].is_array());
+    assert!(isomorphic_graphs(&parse_jsonld_to_graph(&compacted).unwrap(), &expected).unwrap());
+
+    let compaction = CompactionOptions { frame: Some("cat:Batch".to_string()), context_url: None };
+    let options = ConvertOptions { compaction: Some(&compaction), ..Default::default() };
+    let framed =
+        convert_with(InputType::Synth, json_data, &RdfFormat::CompactJsonld, options).unwrap();
+    let document: serde_json::Value = serde_json::from_str(&framed).unwrap();
+    assert_eq!(document["@type"], "cat:Batch");
+    assert_eq!(document["name"], "23");
+    assert_eq!(document["@reverse"]["hasBatch"].as_array().unwrap().len(), 2);
+    assert!(document.get("@id").is_none());
+    assert!(isomorphic_graphs(&parse_jsonld_to_graph(&framed).unwrap(), &expected).unwrap());
+
+    let compaction = CompactionOptions {
+        frame: None,
+        context_url: Some("https://example.org/catplus/context.jsonld".to_string()),
+    };
+    let options = ConvertOptions { compaction: Some(&compaction), ..Default::default() };
+    let referenced =
+        convert_with(InputType::Synth, json_data, &RdfFormat::CompactJsonld, options).unwrap();
+    let document: serde_json::Value = serde_json::from_str(&referenced).unwrap();
+    assert_eq!(document["@context"][0], "https://example.org/catplus/context.jsonld");
+    assert!(document["@context"][1].get("cat").is_none());
+}
+
+#[test]
+fn test_published_context_is_up_to_date() {
+    let published: serde_json::Value =
+        serde_json::from_str(include_str!("../../../examples/catplus-context.jsonld")).unwrap();
+    assert_eq!(published, prefix_context(&generate_prefix_map()));
+}
+
+#[test]
+fn test_input_schema() {
+    let schema = input_schema(InputType::Synth).unwrap();
+    assert_eq!(schema["$schema"], "https://json-schema.org/draft/2020-12/schema");
+    let units = schema["$defs"]["Unit"]["enum"].as_array().unwrap();
+    assert!(["°C", "µL", "uL"].iter().all(|unit| units.contains(&serde_json::json!(unit))));
+    let action_names = schema["$defs"]["ActionName"].to_string();
+    assert!(["\"setTemperatureAction\"", "\"measureAction\""]
+        .iter()
+        .all(|name| action_names.contains(name)));
+    assert_eq!(input_schema(InputType::HCI).unwrap()["title"], "CampaignWrapper");
+    assert!(input_schema(InputType::Agilent).is_err());
+
+    validate_input(InputType::HCI, include_str!("../../../examples/0-HCI.json")).unwrap();
+    let synth_data = include_str!("../../../examples/1-Synth.json");
+    validate_input(InputType::Auto, synth_data).unwrap();
+
+    let mut document: serde_json::Value = serde_json::from_str(synth_data).unwrap();
+    let actions = document["Actions"].as_array_mut().unwrap();
+    actions[1]["hasSample"].as_object_mut().unwrap().remove("role");
+    actions[3]["startTime"] = serde_json::json!(5);
+    actions[4]["temperatureShaker"] = serde_json::json!({"value": 25, "unit": "K"});
+    let content = serde_json::to_string_pretty(&document).unwrap();
+
+    let error = validate_input(InputType::Synth, &content).unwrap_err();
+    assert_eq!(ErrorKind::of(&error), Some(ErrorKind::Validation));
+    let JsonErrors(errors) = error.downcast_ref::<JsonErrors>().unwrap();
+    let pointers: Vec<&str> = errors.iter().filter_map(|e| e.pointer.as_deref()).collect();
+    assert_eq!(
+        pointers,
+        ["/Actions/1/hasSample/role", "/Actions/3/startTime", "/Actions/4/temperatureShaker/unit"]
+    );
+    let models: Vec<&str> = errors.iter().filter_map(|e| e.model.as_deref()).collect();
+    assert_eq!(models, ["Sample", "Action", "Observation"]);
+    assert_eq!(errors[0].message, "\"role\" is a required property");
+}